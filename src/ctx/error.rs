@@ -0,0 +1,226 @@
+//! コンテキストのエラー
+
+/// グラフィックの初期化に失敗
+#[derive(Debug)]
+pub enum GfxInitError {
+    /// サーフェスの生成に失敗
+    SurfaceCreationError(wgpu::CreateSurfaceError), 
+
+    /// 条件に合うアダプタが存在しない
+    AdapterNotFound, 
+
+    /// デバイスの取得に失敗
+    DeviceRequestError(wgpu::RequestDeviceError), 
+
+    /// サーフェスが対応するフォーマットが存在しない
+    SurfaceFormatNotFound, 
+
+    /// サーフェスが対応するアルファ合成モードが存在しない
+    SurfaceAlphaModeNotFound, 
+
+    /// データの初期化に失敗
+    DataInitError(Box<dyn std::error::Error>), 
+}
+impl std::fmt::Display for GfxInitError {
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        GfxInitError::SurfaceCreationError(e) => f.write_fmt(format_args!(
+            "surface creation error: {e}"
+        )), 
+        GfxInitError::AdapterNotFound => f.write_fmt(format_args!(
+            "Adapter was not detected"
+        )), 
+        GfxInitError::DeviceRequestError(e) => f.write_fmt(format_args!(
+            "device request error: {e}"
+        )), 
+        GfxInitError::SurfaceFormatNotFound => f.write_fmt(format_args!(
+            "surface has not format"
+        )), 
+        GfxInitError::SurfaceAlphaModeNotFound => f.write_fmt(format_args!(
+            "surface has not alpha mode"
+        )), 
+        GfxInitError::DataInitError(e) => f.write_fmt(format_args!(
+            "data initializing process error: {e}"
+        )), 
+    }}
+}
+impl std::error::Error for GfxInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { match self {
+        GfxInitError::SurfaceCreationError(e) => Some(e), 
+        GfxInitError::DeviceRequestError(e) => Some(e), 
+        GfxInitError::DataInitError(e) => Some(e.as_ref()), 
+        GfxInitError::AdapterNotFound 
+        | GfxInitError::SurfaceFormatNotFound 
+        | GfxInitError::SurfaceAlphaModeNotFound => None, 
+    }}
+}
+impl From<wgpu::CreateSurfaceError> for GfxInitError {
+    fn from(e: wgpu::CreateSurfaceError) -> Self { Self::SurfaceCreationError(e) }
+}
+impl From<wgpu::RequestDeviceError> for GfxInitError {
+    fn from(e: wgpu::RequestDeviceError) -> Self { Self::DeviceRequestError(e) }
+}
+
+/// オーディオの初期化に失敗
+#[derive(Debug)]
+pub enum SfxInitError {
+    /// 出力ストリームの生成に失敗
+    StreamError(rodio::StreamError), 
+
+    /// シンクの生成に失敗
+    PlayError(rodio::PlayError), 
+}
+impl std::fmt::Display for SfxInitError {
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        SfxInitError::StreamError(e) => f.write_fmt(format_args!(
+            "audio stream error: {e}"
+        )), 
+        SfxInitError::PlayError(e) => f.write_fmt(format_args!(
+            "audio sink error: {e}"
+        )), 
+    }}
+}
+impl std::error::Error for SfxInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { match self {
+        SfxInitError::StreamError(e) => Some(e), 
+        SfxInitError::PlayError(e) => Some(e), 
+    }}
+}
+impl From<rodio::StreamError> for SfxInitError {
+    fn from(e: rodio::StreamError) -> Self { Self::StreamError(e) }
+}
+impl From<rodio::PlayError> for SfxInitError {
+    fn from(e: rodio::PlayError) -> Self { Self::PlayError(e) }
+}
+
+/// コンテキストの初期化に失敗
+#[derive(Debug)]
+pub enum ContextInitError {
+    /// ウィンドウの生成に失敗
+    WindowError(winit::error::OsError), 
+
+    /// グラフィックの初期化に失敗
+    GfxError(GfxInitError), 
+
+    /// オーディオの初期化に失敗
+    SfxError(SfxInitError), 
+
+    /// フレームの初期化に失敗
+    FrameInitError(Box<dyn std::error::Error>), 
+}
+impl std::fmt::Display for ContextInitError {
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        ContextInitError::WindowError(e) => f.write_fmt(format_args!(
+            "window creation error: {e}"
+        )), 
+        ContextInitError::GfxError(e) => f.write_fmt(format_args!(
+            "graphics initializing process error: {e}"
+        )), 
+        ContextInitError::SfxError(e) => f.write_fmt(format_args!(
+            "audio initializing process error: {e}"
+        )), 
+        ContextInitError::FrameInitError(e) => f.write_fmt(format_args!(
+            "frame initializing process error: {e}"
+        )), 
+    }}
+}
+impl std::error::Error for ContextInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { match self {
+        ContextInitError::WindowError(e) => Some(e), 
+        ContextInitError::GfxError(e) => Some(e), 
+        ContextInitError::SfxError(e) => Some(e), 
+        ContextInitError::FrameInitError(e) => Some(e.as_ref()), 
+    }}
+}
+impl From<winit::error::OsError> for ContextInitError {
+    fn from(e: winit::error::OsError) -> Self { Self::WindowError(e) }
+}
+impl From<GfxInitError> for ContextInitError {
+    fn from(e: GfxInitError) -> Self { Self::GfxError(e) }
+}
+impl From<SfxInitError> for ContextInitError {
+    fn from(e: SfxInitError) -> Self { Self::SfxError(e) }
+}
+
+/// サーフェス・データの再設定に失敗
+#[derive(Debug)]
+pub enum SurfaceConfigError {
+    /// データの再設定に失敗
+    DataReconfigureError(Box<dyn std::error::Error>), 
+}
+impl std::fmt::Display for SurfaceConfigError {
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        SurfaceConfigError::DataReconfigureError(e) => f.write_fmt(format_args!(
+            "data reconfiguring process error: {e}"
+        )), 
+    }}
+}
+impl std::error::Error for SurfaceConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { match self {
+        SurfaceConfigError::DataReconfigureError(e) => Some(e.as_ref()), 
+    }}
+}
+
+/// コンテキストの実行中に発生するエラー
+#[derive(Debug)]
+pub enum ContextRunError {
+    /// サーフェス・データの再設定に失敗
+    ReconfigureError(SurfaceConfigError), 
+
+    /// 描画データの更新に失敗
+    RdrUpdateError(Box<dyn std::error::Error>), 
+
+    /// フレームの更新に失敗
+    FrameUpdateError(Box<dyn std::error::Error>), 
+
+    /// 実行中のウィンドウの生成に失敗
+    WindowSpawnError(ContextInitError), 
+
+    /// 描画中にメモリが不足
+    OutOfMemory, 
+}
+impl std::fmt::Display for ContextRunError {
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        ContextRunError::ReconfigureError(e) => f.write_fmt(format_args!(
+            "reconfiguring process error: {e}"
+        )), 
+        ContextRunError::RdrUpdateError(e) => f.write_fmt(format_args!(
+            "rendering data updating process error: {e}"
+        )), 
+        ContextRunError::FrameUpdateError(e) => f.write_fmt(format_args!(
+            "frame updating process error: {e}"
+        )), 
+        ContextRunError::WindowSpawnError(e) => f.write_fmt(format_args!(
+            "window spawning process error: {e}"
+        )), 
+        ContextRunError::OutOfMemory => f.write_str(
+            "out of memory error occured."
+        ), 
+    }}
+}
+impl std::error::Error for ContextRunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { match self {
+        ContextRunError::ReconfigureError(e) => Some(e), 
+        ContextRunError::RdrUpdateError(e) => Some(e.as_ref()), 
+        ContextRunError::FrameUpdateError(e) => Some(e.as_ref()), 
+        ContextRunError::WindowSpawnError(e) => Some(e), 
+        ContextRunError::OutOfMemory => None, 
+    }}
+}
+impl From<SurfaceConfigError> for ContextRunError {
+    fn from(e: SurfaceConfigError) -> Self { Self::ReconfigureError(e) }
+}
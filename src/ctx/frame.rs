@@ -54,6 +54,13 @@ pub trait Frame<I, GCd> where
         &mut self, 
         delta: MouseScrollDelta, 
     );
+    /// カーソル位置の入力(ウィンドウ座標の物理ピクセル)
+    /// 
+    /// 論理座標へは`GfxCtx::window_to_logical`で変換できます。
+    fn input_cursor_moved(
+        &mut self, 
+        _position: winit::dpi::PhysicalPosition<f64>, 
+    ) {}
     fn window_resizing(
         &mut self, 
         size: winit::dpi::PhysicalSize<u32>, 
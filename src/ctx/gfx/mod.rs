@@ -1,7 +1,9 @@
 use std::sync::Arc;
-use parking_lot::{Mutex, RwLock, RwLockReadGuard};
+use parking_lot::{Mutex, MutexGuard, RwLock, RwLockReadGuard};
 use wgpu::{
+    Adapter, 
     Device, 
+    Instance, 
     Surface, 
     Queue, 
     SurfaceConfiguration, 
@@ -10,11 +12,56 @@ use wgpu::{
 };
 use winit::window::Window;
 
+use super::error::{GfxInitError, SurfaceConfigError};
+
+pub mod post;
+pub mod virtual_res;
+
+/// グラフィックの初期化設定
+#[derive(Debug, Clone)]
+pub struct GfxConfig {
+    /// 使用するバックエンド
+    pub backends: wgpu::Backends, 
+
+    /// アダプタ選択時の電力設定
+    pub power_preference: wgpu::PowerPreference, 
+
+    /// ソフトウェア実装のアダプタを強制するかどうか
+    pub force_fallback_adapter: bool, 
+
+    /// 要求する機能
+    pub features: wgpu::Features, 
+
+    /// 要求する制限(`None`の場合はプラットフォームの既定値)
+    pub limits: Option<wgpu::Limits>, 
+
+    /// 表示モード(非対応の場合は`Fifo`)
+    pub present_mode: wgpu::PresentMode, 
+
+    /// アルファ合成モード(`None`または非対応の場合はサーフェスの既定)
+    pub alpha_mode: Option<wgpu::CompositeAlphaMode>, 
+}
+impl Default for GfxConfig {
+    fn default() -> Self { Self {
+        backends: wgpu::Backends::all(), 
+        power_preference: wgpu::PowerPreference::default(), 
+        force_fallback_adapter: false, 
+        features: wgpu::Features::empty(), 
+        limits: None, 
+        present_mode: wgpu::PresentMode::Fifo, 
+        alpha_mode: None, 
+    }}
+}
+
 /// WGPUのコンテキスト
+/// 
+/// インスタンス・アダプタ・デバイス・キューは全ウィンドウで共有されます。
 pub struct WGPUCtx {
+    pub instance: Arc<Instance>, 
+    pub adapter: Arc<Adapter>, 
     pub surface: Surface, 
-    pub device: Device, 
-    pub queue: Queue, 
+    pub device: Arc<Device>, 
+    pub queue: Arc<Queue>, 
     pub config: SurfaceConfiguration, 
 }
 
@@ -81,20 +128,44 @@ impl<D: Send + Sync> GCData<D> {
 }
 
 /// GfxCtxの描画時に発生するエラー
+#[derive(Debug)]
 pub enum GfxCtxRenderingError {
     SurfaceError(wgpu::SurfaceError), 
     RdrUpdateError(Box<dyn std::error::Error>), 
 }
+impl std::fmt::Display for GfxCtxRenderingError {
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        GfxCtxRenderingError::SurfaceError(e) => f.write_fmt(format_args!(
+            "surface error: {e}"
+        )), 
+        GfxCtxRenderingError::RdrUpdateError(e) => f.write_fmt(format_args!(
+            "rendering data updating process error: {e}"
+        )), 
+    }}
+}
+impl std::error::Error for GfxCtxRenderingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { match self {
+        GfxCtxRenderingError::SurfaceError(e) => Some(e), 
+        GfxCtxRenderingError::RdrUpdateError(e) => Some(e.as_ref()), 
+    }}
+}
 
 /// グラフィック機能をまとめるコンテキスト
 pub struct GfxCtx<D: Send + Sync> {
     pub winit_ctx: WinitCtx, 
     pub wgpu_ctx: WGPUCtx, 
     pub data: GCData<D>, 
+    present_mode_req: Mutex<Option<wgpu::PresentMode>>, 
+    post: Mutex<Option<post::PostProcess>>, 
+    virtual_res: Mutex<Option<virtual_res::VirtualResolution>>, 
 }
 impl<D: Send + Sync> GfxCtx<D> {
     pub async fn new(
         window: &Arc<Window>, 
+        gfx_config: &GfxConfig, 
         dinit: impl FnOnce(
             &WinitCtx, 
             &WGPUCtx, 
@@ -109,10 +180,7 @@ impl<D: Send + Sync> GfxCtx<D> {
             &WGPUCtx, 
             &mut D, 
         ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
-    ) -> Result<
-        Self, 
-        Box<dyn std::error::Error>
-    > {
+    ) -> Result<Self, GfxInitError> {
         // winitのコンテキストの生成
         let winit_ctx = WinitCtx {
             window: window.clone(), 
@@ -123,7 +191,7 @@ impl<D: Send + Sync> GfxCtx<D> {
 
         // WGPUのインスタンスの初期化
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(), 
+            backends: gfx_config.backends, 
             dx12_shader_compiler: Default::default(), 
         });
         
@@ -135,66 +203,49 @@ impl<D: Send + Sync> GfxCtx<D> {
         // アダプタ(GPUの仮想的なインスタンス)の取得
         let adapter = instance.request_adapter(
             &wgpu::RequestAdapterOptionsBase { 
-                power_preference: wgpu::PowerPreference::default(), 
-                force_fallback_adapter: false, 
+                power_preference: gfx_config.power_preference, 
+                force_fallback_adapter: gfx_config.force_fallback_adapter, 
                 compatible_surface: Some(&surface), 
             }
         )
             .await
-            .ok_or("Adapter was not detected")?;
+            .ok_or(GfxInitError::AdapterNotFound)?;
 
         // デバイスの仮想オブジェクトおよびコマンドキューの取得
         let (device, queue) =adapter.request_device(
             &wgpu::DeviceDescriptor {
-                features: wgpu::Features::empty(), 
-                limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                }, 
+                features: gfx_config.features, 
+                limits: gfx_config.limits.clone().unwrap_or_else(|| {
+                    if cfg!(target_arch = "wasm32") {
+                        wgpu::Limits::downlevel_webgl2_defaults()
+                    } else {
+                        wgpu::Limits::default()
+                    }
+                }), 
                 label: None, 
             }, 
             None
         ).await?;
 
-        // サーフェスの機能の取得
-        let surface_caps = surface.get_capabilities(&adapter);
-
-        // サーフェスのテクスチャフォーマットの取得
-        let surface_format = surface_caps.formats.iter()
-            .copied()
-            .filter(|f| f.describe().srgb)
-            .next()
-            .map_or(
-                surface_caps.formats.get(0).copied(), 
-                |f| Some(f)
-            )
-            .ok_or("surface has not format")?;
-
         // サーフェスの設定
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT, 
-            format: surface_format, 
-            width: size.width, 
-            height: size.height, 
-            present_mode: wgpu::PresentMode::Fifo, 
-            /*present_mode: surface_caps.present_modes.get(0)
-                .copied()
-                .ok_or("surface has not present mode")?, */
-            alpha_mode: surface_caps.alpha_modes.get(0)
-                .copied()
-                .ok_or("surface has not alpha mode")?, 
-            view_formats: vec![]
-        };
+        let config = Self::surface_config(
+            &surface, 
+            &adapter, 
+            size, 
+            gfx_config.present_mode, 
+            gfx_config.alpha_mode, 
+        )?;
 
         // サーフェスへの設定の適用
         surface.configure(&device, &config);
 
         // WGPUコンテキストの生成
         let wgpu_ctx = WGPUCtx {
+            instance: Arc::new(instance), 
+            adapter: Arc::new(adapter), 
             surface,
-            device,
-            queue,
+            device: Arc::new(device),
+            queue: Arc::new(queue),
             config,
         };
 
@@ -202,7 +253,7 @@ impl<D: Send + Sync> GfxCtx<D> {
         let data = dinit(
             &winit_ctx, 
             &wgpu_ctx, 
-        )?;
+        ).map_err(GfxInitError::DataInitError)?;
 
         // 処理成功
         Ok(Self {
@@ -213,6 +264,148 @@ impl<D: Send + Sync> GfxCtx<D> {
                 dupdater, 
                 dreconfigureer, 
             ), 
+            present_mode_req: Mutex::new(None), 
+            post: Mutex::new(None), 
+            virtual_res: Mutex::new(None), 
+        })
+    }
+
+    /// 同じデバイスを共有する別ウィンドウ用のコンテキストの生成
+    pub fn create_sub<D2: Send + Sync>(
+        &self, 
+        window: &Arc<Window>, 
+        dinit: impl FnOnce(
+            &WinitCtx, 
+            &WGPUCtx, 
+        ) -> Result<D2, Box<dyn std::error::Error>>, 
+        dupdater: impl FnMut(
+            &WinitCtx, 
+            &WGPUCtx, 
+            &mut D2, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+        dreconfigureer: impl FnMut(
+            &WinitCtx, 
+            &WGPUCtx, 
+            &mut D2, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+    ) -> Result<GfxCtx<D2>, GfxInitError> {
+        GfxCtx::new_shared(
+            &self.wgpu_ctx, 
+            window, 
+            dinit, 
+            dupdater, 
+            dreconfigureer, 
+        )
+    }
+
+    /// 既存のWGPUコンテキストとデバイスを共有したコンテキストの生成
+    pub(crate) fn new_shared(
+        shared: &WGPUCtx, 
+        window: &Arc<Window>, 
+        dinit: impl FnOnce(
+            &WinitCtx, 
+            &WGPUCtx, 
+        ) -> Result<D, Box<dyn std::error::Error>>, 
+        dupdater: impl FnMut(
+            &WinitCtx, 
+            &WGPUCtx, 
+            &mut D, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+        dreconfigureer: impl FnMut(
+            &WinitCtx, 
+            &WGPUCtx, 
+            &mut D, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+    ) -> Result<Self, GfxInitError> {
+        // winitのコンテキストの生成
+        let winit_ctx = WinitCtx {
+            window: window.clone(), 
+        };
+
+        // サーフェスの初期化
+        let surface = unsafe {
+            shared.instance.create_surface(&(*winit_ctx.window))
+        }?;
+
+        // サーフェスの設定
+        let config = Self::surface_config(
+            &surface, 
+            &shared.adapter, 
+            winit_ctx.window.inner_size(), 
+            shared.config.present_mode, 
+            Some(shared.config.alpha_mode), 
+        )?;
+        surface.configure(&shared.device, &config);
+
+        // WGPUコンテキストの生成
+        let wgpu_ctx = WGPUCtx {
+            instance: shared.instance.clone(), 
+            adapter: shared.adapter.clone(), 
+            surface, 
+            device: shared.device.clone(), 
+            queue: shared.queue.clone(), 
+            config, 
+        };
+
+        // データの初期化
+        let data = dinit(
+            &winit_ctx, 
+            &wgpu_ctx, 
+        ).map_err(GfxInitError::DataInitError)?;
+
+        Ok(Self {
+            wgpu_ctx, 
+            winit_ctx, 
+            data: GCData::new(
+                data, 
+                dupdater, 
+                dreconfigureer, 
+            ), 
+            present_mode_req: Mutex::new(None), 
+            post: Mutex::new(None), 
+            virtual_res: Mutex::new(None), 
+        })
+    }
+
+    /// サーフェスの設定の生成
+    fn surface_config(
+        surface: &Surface, 
+        adapter: &Adapter, 
+        size: winit::dpi::PhysicalSize<u32>, 
+        present_mode: wgpu::PresentMode, 
+        alpha_mode: Option<wgpu::CompositeAlphaMode>, 
+    ) -> Result<SurfaceConfiguration, GfxInitError> {
+        // サーフェスの機能の取得
+        let surface_caps = surface.get_capabilities(adapter);
+
+        // サーフェスのテクスチャフォーマットの取得
+        let surface_format = surface_caps.formats.iter()
+            .copied()
+            .filter(|f| f.describe().srgb)
+            .next()
+            .map_or(
+                surface_caps.formats.get(0).copied(), 
+                |f| Some(f)
+            )
+            .ok_or(GfxInitError::SurfaceFormatNotFound)?;
+
+        Ok(wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT, 
+            format: surface_format, 
+            width: size.width, 
+            height: size.height, 
+            present_mode: if surface_caps.present_modes.contains(&present_mode) {
+                present_mode
+            } else {
+                wgpu::PresentMode::Fifo
+            }, 
+            alpha_mode: match alpha_mode {
+                Some(mode) if surface_caps.alpha_modes.contains(&mode) => mode, 
+                _ => surface_caps.alpha_modes.get(0)
+                    .copied()
+                    .ok_or(GfxInitError::SurfaceAlphaModeNotFound)?, 
+            }, 
+            view_formats: vec![]
         })
     }
 
@@ -220,7 +413,7 @@ impl<D: Send + Sync> GfxCtx<D> {
     pub fn reconfigure(
         &mut self, 
         new_size: Option<winit::dpi::PhysicalSize<u32>>, 
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), SurfaceConfigError> {
         // ウィンドウの大きさを得る
         let recfg_size = if let Some(new_size) = new_size {
             new_size
@@ -240,11 +433,104 @@ impl<D: Send + Sync> GfxCtx<D> {
             &self.wgpu_ctx.config
         );
 
+        // ポストエフェクトの中間テクスチャの再生成
+        if let Some(post) = self.post.get_mut() {
+            post.resize(&self.wgpu_ctx)
+        }
+
         // データの再コンフィグ
         self.data.reconfigure(
             &self.winit_ctx, 
             &self.wgpu_ctx, 
-        )
+        ).map_err(SurfaceConfigError::DataReconfigureError)
+    }
+
+    /// 表示モードの変更要求
+    /// 
+    /// 変更はフレームの更新後に適用されます。
+    /// 非対応のモードが指定された場合は`Fifo`になります。
+    pub fn set_present_mode(
+        &self, 
+        present_mode: wgpu::PresentMode, 
+    ) { *self.present_mode_req.lock() = Some(present_mode) }
+
+    /// 表示モードの変更要求の適用
+    pub(super) fn apply_present_mode(&mut self) {
+        let Some(present_mode) = self.present_mode_req.get_mut().take() else {
+            return
+        };
+        let surface_caps = self.wgpu_ctx.surface
+            .get_capabilities(&self.wgpu_ctx.adapter);
+        let present_mode = if surface_caps.present_modes.contains(&present_mode) {
+            present_mode
+        } else {
+            wgpu::PresentMode::Fifo
+        };
+        if self.wgpu_ctx.config.present_mode != present_mode {
+            self.wgpu_ctx.config.present_mode = present_mode;
+            self.wgpu_ctx.surface.configure(
+                &self.wgpu_ctx.device, 
+                &self.wgpu_ctx.config
+            );
+        }
+    }
+
+    /// ポストエフェクトの設定
+    /// 
+    /// 設定されている間、描画はいったん中間テクスチャに行われ、
+    /// エフェクトを適用した結果がサーフェスに出力されます。
+    /// 以前の設定を返します。
+    pub fn set_post_process(
+        &self, 
+        post: Option<post::PostProcess>, 
+    ) -> Option<post::PostProcess> {
+        let mut post = post;
+        if let Some(post) = post.as_mut() {
+            post.resize(&self.wgpu_ctx)
+        }
+        std::mem::replace(&mut *self.post.lock(), post)
+    }
+
+    /// ポストエフェクトの参照
+    /// 
+    /// パラメータの更新などに用います。
+    pub fn post_process(&self) -> MutexGuard<'_, Option<post::PostProcess>> {
+        self.post.lock()
+    }
+
+    /// 仮想解像度の設定
+    /// 
+    /// 設定されている間、描画は論理解像度の中間テクスチャに行われ、
+    /// ウィンドウに合わせて拡大されます。以前の設定を返します。
+    pub fn set_virtual_resolution(
+        &self, 
+        virtual_res: Option<virtual_res::VirtualResolution>, 
+    ) -> Option<virtual_res::VirtualResolution> {
+        std::mem::replace(&mut *self.virtual_res.lock(), virtual_res)
+    }
+
+    /// 仮想解像度の参照
+    pub fn virtual_resolution(
+        &self, 
+    ) -> MutexGuard<'_, Option<virtual_res::VirtualResolution>> {
+        self.virtual_res.lock()
+    }
+
+    /// ウィンドウ座標(物理ピクセル)から論理座標への変換
+    /// 
+    /// 仮想解像度が設定されていない場合はウィンドウ座標をそのまま返します。
+    /// 余白上の位置では`None`を返します。
+    pub fn window_to_logical(
+        &self, 
+        position: winit::dpi::PhysicalPosition<f64>, 
+    ) -> Option<[f32; 2]> {
+        match self.virtual_res.lock().as_ref() {
+            Some(virtual_res) => virtual_res.window_to_logical(
+                [self.wgpu_ctx.config.width, self.wgpu_ctx.config.height], 
+                [position.x, position.y], 
+            ), 
+            None => Some([position.x as f32, position.y as f32]), 
+        }
     }
 
     /// 描画準備
@@ -255,7 +541,19 @@ impl<D: Send + Sync> GfxCtx<D> {
         // 出力先の初期化
         let output = self.wgpu_ctx.surface.get_current_texture()
             .map_err(|e| GfxCtxRenderingError::SurfaceError(e))?;
-        let view = output.texture.create_view(&Default::default());
+        let virtual_view = self.virtual_res.lock()
+            .as_ref()
+            .map(|virtual_res| virtual_res.scene_view());
+        let post_view = self.post.lock()
+            .as_ref()
+            .map(|post| post.scene_view());
+        let virtual_enabled = virtual_view.is_some();
+        let post_enabled = post_view.is_some();
+        let view = virtual_view
+            .or(post_view)
+            .unwrap_or_else(|| {
+                output.texture.create_view(&Default::default())
+            });
         self.data.update(&self.winit_ctx, &self.wgpu_ctx)
             .map_err(|e| GfxCtxRenderingError::RdrUpdateError(e))?;
         
@@ -264,6 +562,8 @@ impl<D: Send + Sync> GfxCtx<D> {
             gfx: self, 
             output, 
             view, 
+            virtual_enabled, 
+            post_enabled, 
             fglob_ref, 
         })
     }
@@ -290,6 +590,8 @@ pub struct RenderingChain<
     gfx: &'a GfxCtx<GCd>, 
     output: SurfaceTexture, 
     view: TextureView, 
+    virtual_enabled: bool, 
+    post_enabled: bool, 
     fglob_ref: &'b FrG, 
 }
 impl<'a, 'b, GCd, FrG> RenderingChain<'a, 'b, GCd, FrG> where
@@ -313,6 +615,31 @@ impl<'a, 'b, GCd, FrG> RenderingChain<'a, 'b, GCd, FrG> where
 
     /// 描画
     pub(super) fn present(self) {
+        let surface_view = self.output.texture.create_view(&Default::default());
+        let post_guard = self.gfx.post.lock();
+        let post = post_guard.as_ref().filter(|_| self.post_enabled);
+
+        // 仮想解像度の場合は拡大してポストエフェクトかサーフェスへ出力する
+        if self.virtual_enabled {
+            if let Some(virtual_res) = self.gfx.virtual_res.lock().as_ref() {
+                match post {
+                    Some(post) => virtual_res.apply(
+                        &self.gfx.wgpu_ctx, 
+                        &post.scene_view(), 
+                    ), 
+                    None => virtual_res.apply(
+                        &self.gfx.wgpu_ctx, 
+                        &surface_view, 
+                    ), 
+                }
+            }
+        }
+
+        // ポストエフェクトを経由する場合は中間テクスチャから出力する
+        if let Some(post) = post {
+            post.apply(&self.gfx.wgpu_ctx, &surface_view)
+        }
+        drop(post_guard);
         self.output.present();
     }
 }
\ No newline at end of file
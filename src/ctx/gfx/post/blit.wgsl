@@ -0,0 +1,6 @@
+// 入力をそのまま出力する
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_source, s_source, in.uv);
+}
@@ -0,0 +1,34 @@
+// 簡易ブルーム(1パスで明部をぼかして加算する)
+
+struct Params {
+    threshold: f32, 
+    intensity: f32, 
+    radius: f32, 
+    reserved: f32, 
+}
+@group(1) @binding(0)
+var<uniform> params: Params;
+
+fn bright(uv: vec2<f32>) -> vec3<f32> {
+    let c = textureSample(t_source, s_source, uv).rgb;
+    let luma = dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return c * smoothstep(params.threshold, params.threshold + 0.1, luma);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let src = textureSample(t_source, s_source, in.uv);
+    let texel = params.radius / max(globals.resolution, vec2<f32>(1.));
+
+    // 2重の環状サンプリング
+    var sum = bright(in.uv);
+    var weight = 1.;
+    for (var i = 0; i < 8; i++) {
+        let a = f32(i) * 0.78539816;
+        let dir = vec2<f32>(cos(a), sin(a));
+        sum += bright(in.uv + dir * texel) * 0.6;
+        sum += bright(in.uv + dir * texel * 2.) * 0.3;
+        weight += 0.9;
+    }
+    return vec4<f32>(src.rgb + sum / weight * params.intensity, src.a);
+}
@@ -0,0 +1,22 @@
+// 色調補正
+
+struct Params {
+    tint: vec4<f32>, 
+    brightness: f32, 
+    contrast: f32, 
+    saturation: f32, 
+    gamma: f32, 
+}
+@group(1) @binding(0)
+var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let src = textureSample(t_source, s_source, in.uv);
+    var rgb = src.rgb + params.brightness;
+    rgb = (rgb - 0.5) * params.contrast + 0.5;
+    let luma = dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    rgb = mix(vec3<f32>(luma), rgb, params.saturation);
+    rgb = pow(max(rgb, vec3<f32>(0.)), vec3<f32>(1. / max(params.gamma, 0.001)));
+    return vec4<f32>(rgb * params.tint.rgb, src.a);
+}
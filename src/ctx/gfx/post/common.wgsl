@@ -0,0 +1,42 @@
+// ポストエフェクト共通部
+
+struct PostGlobals {
+    resolution: vec2<f32>, 
+    time: f32, 
+    reserved: f32, 
+}
+
+// 入力テクスチャ
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(0) @binding(2)
+var<uniform> globals: PostGlobals;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>, 
+    @location(0) uv: vec2<f32>, 
+}
+
+// 画面全体を覆う三角形
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32, 
+) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(
+        f32((index << 1u) & 2u), 
+        f32(index & 2u), 
+    );
+    out.clip_position = vec4<f32>(
+        uv.x * 2. - 1., 
+        1. - uv.y * 2., 
+        0., 
+        1., 
+    );
+    out.uv = uv;
+    return out;
+}
@@ -0,0 +1,568 @@
+//! ポストエフェクト
+//! 
+//! シーンを中間テクスチャに描画し、全画面のWGSLエフェクトを
+//! 順に適用してからサーフェスに出力します。
+//! 
+//! エフェクトのシェーダには共通部(`common.wgsl`)が前置されます。
+//! 入力は`t_source`/`s_source`、画面情報は`globals`として参照でき、
+//! パラメータは`@group(1) @binding(0)`のユニフォームで受け取ります。
+//! フラグメントシェーダのエントリポイントは`fs_main`です。
+
+use super::WGPUCtx;
+
+/// 共通部のシェーダ
+const COMMON_WGSL: &str = include_str!("common.wgsl");
+
+/// エフェクト共通のユニフォーム
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostGlobalsRaw {
+    resolution: [f32; 2], 
+    time: f32, 
+    _dummy: f32, 
+}
+
+/// 組み込みのエフェクト
+pub trait PostPreset: bytemuck::Pod {
+    /// ラベル
+    const LABEL: &'static str;
+
+    /// フラグメントシェーダ
+    const SOURCE: &'static str;
+}
+
+/// ビネット
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vignette {
+    pub color: [f32; 4], 
+    pub intensity: f32, 
+    pub radius: f32, 
+    pub softness: f32, 
+    pub _dummy: f32, 
+}
+impl Default for Vignette {
+    fn default() -> Self { Self {
+        color: [0., 0., 0., 1.], 
+        intensity: 0.8, 
+        radius: 0.4, 
+        softness: 0.5, 
+        _dummy: 0., 
+    }}
+}
+impl PostPreset for Vignette {
+    const LABEL: &'static str = "vignette";
+    const SOURCE: &'static str = include_str!("vignette.wgsl");
+}
+
+/// CRT風の走査線
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Scanline {
+    pub intensity: f32, 
+    pub line_count: f32, 
+    pub curvature: f32, 
+    pub speed: f32, 
+}
+impl Default for Scanline {
+    fn default() -> Self { Self {
+        intensity: 0.3, 
+        line_count: 240., 
+        curvature: 0.05, 
+        speed: 0., 
+    }}
+}
+impl PostPreset for Scanline {
+    const LABEL: &'static str = "scanline";
+    const SOURCE: &'static str = include_str!("scanline.wgsl");
+}
+
+/// 色調補正
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorGrade {
+    pub tint: [f32; 4], 
+    pub brightness: f32, 
+    pub contrast: f32, 
+    pub saturation: f32, 
+    pub gamma: f32, 
+}
+impl Default for ColorGrade {
+    fn default() -> Self { Self {
+        tint: [1., 1., 1., 1.], 
+        brightness: 0., 
+        contrast: 1., 
+        saturation: 1., 
+        gamma: 1., 
+    }}
+}
+impl PostPreset for ColorGrade {
+    const LABEL: &'static str = "color grade";
+    const SOURCE: &'static str = include_str!("color_grade.wgsl");
+}
+
+/// 簡易ブルーム
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Bloom {
+    pub threshold: f32, 
+    pub intensity: f32, 
+    pub radius: f32, 
+    pub _dummy: f32, 
+}
+impl Default for Bloom {
+    fn default() -> Self { Self {
+        threshold: 0.7, 
+        intensity: 0.8, 
+        radius: 4., 
+        _dummy: 0., 
+    }}
+}
+impl PostPreset for Bloom {
+    const LABEL: &'static str = "bloom";
+    const SOURCE: &'static str = include_str!("bloom.wgsl");
+}
+
+/// エフェクトのパス
+struct PostEffect {
+    pipeline: wgpu::RenderPipeline, 
+    params: wgpu::Buffer, 
+    params_bg: wgpu::BindGroup, 
+
+    /// 追加時のパラメータの大きさ(バイト)
+    params_size: usize, 
+    enabled: bool, 
+}
+
+/// 中間テクスチャ
+struct PostTarget {
+    texture: wgpu::Texture, 
+    view: wgpu::TextureView, 
+    bg: wgpu::BindGroup, 
+}
+
+/// ポストエフェクトの連鎖
+pub struct PostProcess {
+    effects: Vec<PostEffect>, 
+    targets: [PostTarget; 2], 
+    sampler: wgpu::Sampler, 
+    source_layout: wgpu::BindGroupLayout, 
+    params_layout: wgpu::BindGroupLayout, 
+    globals: PostGlobalsRaw, 
+    globals_buffer: wgpu::Buffer, 
+    blit: wgpu::RenderPipeline, 
+    format: wgpu::TextureFormat, 
+    size: [u32; 2], 
+}
+impl PostProcess {
+    pub fn new(
+        gfx: &WGPUCtx, 
+    ) -> Self {
+        use wgpu::util::DeviceExt;
+
+        let size = [gfx.config.width.max(1), gfx.config.height.max(1)];
+        let format = gfx.config.format;
+
+        // サンプラの作成
+        let sampler = gfx.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge, 
+            address_mode_v: wgpu::AddressMode::ClampToEdge, 
+            address_mode_w: wgpu::AddressMode::ClampToEdge, 
+            mag_filter: wgpu::FilterMode::Linear, 
+            min_filter: wgpu::FilterMode::Linear, 
+            mipmap_filter: wgpu::FilterMode::Nearest, 
+            ..Default::default()
+        });
+
+        // 入力用のバインドグループレイアウト
+        let source_layout = gfx.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("post source bind group layout"), 
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0, 
+                        visibility: wgpu::ShaderStages::FRAGMENT, 
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true }, 
+                            view_dimension: wgpu::TextureViewDimension::D2, 
+                            multisampled: false, 
+                        }, 
+                        count: None, 
+                    }, 
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1, 
+                        visibility: wgpu::ShaderStages::FRAGMENT, 
+                        ty: wgpu::BindingType::Sampler(
+                            wgpu::SamplerBindingType::Filtering, 
+                        ), 
+                        count: None, 
+                    }, 
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2, 
+                        visibility: wgpu::ShaderStages::FRAGMENT, 
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform, 
+                            has_dynamic_offset: false, 
+                            min_binding_size: None, 
+                        }, 
+                        count: None, 
+                    }, 
+                ], 
+            }
+        );
+
+        // パラメータ用のバインドグループレイアウト
+        let params_layout = gfx.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("post params bind group layout"), 
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0, 
+                        visibility: wgpu::ShaderStages::FRAGMENT, 
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform, 
+                            has_dynamic_offset: false, 
+                            min_binding_size: None, 
+                        }, 
+                        count: None, 
+                    }, 
+                ], 
+            }
+        );
+
+        // 共通のユニフォーム
+        let globals = PostGlobalsRaw {
+            resolution: [size[0] as f32, size[1] as f32], 
+            time: 0., 
+            _dummy: 0., 
+        };
+        let globals_buffer = gfx.device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("post globals buffer"), 
+                contents: bytemuck::cast_slice(&[globals]), 
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST, 
+            }
+        );
+
+        // 中間テクスチャ
+        let targets = std::array::from_fn(|_| Self::create_target(
+            gfx, 
+            size, 
+            format, 
+            &sampler, 
+            &source_layout, 
+            &globals_buffer, 
+        ));
+
+        // 出力用のパイプライン
+        let blit = Self::create_pipeline(
+            gfx, 
+            "post blit", 
+            include_str!("blit.wgsl"), 
+            &[&source_layout], 
+            format, 
+        );
+
+        Self {
+            effects: Vec::new(), 
+            targets, 
+            sampler, 
+            source_layout, 
+            params_layout, 
+            globals, 
+            globals_buffer, 
+            blit, 
+            format, 
+            size, 
+        }
+    }
+
+    fn create_target(
+        gfx: &WGPUCtx, 
+        size: [u32; 2], 
+        format: wgpu::TextureFormat, 
+        sampler: &wgpu::Sampler, 
+        source_layout: &wgpu::BindGroupLayout, 
+        globals_buffer: &wgpu::Buffer, 
+    ) -> PostTarget {
+        let texture = gfx.device.create_texture(
+            &wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: size[0], 
+                    height: size[1], 
+                    depth_or_array_layers: 1, 
+                }, 
+                mip_level_count: 1, 
+                sample_count: 1, 
+                dimension: wgpu::TextureDimension::D2, 
+                format, 
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING, 
+                label: Some("post target texture"), 
+                view_formats: &[], 
+            }
+        );
+        let view = texture.create_view(&Default::default());
+        let bg = gfx.device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: source_layout, 
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0, 
+                        resource: wgpu::BindingResource::TextureView(&view), 
+                    }, 
+                    wgpu::BindGroupEntry {
+                        binding: 1, 
+                        resource: wgpu::BindingResource::Sampler(sampler), 
+                    }, 
+                    wgpu::BindGroupEntry {
+                        binding: 2, 
+                        resource: globals_buffer.as_entire_binding(), 
+                    }, 
+                ], 
+                label: Some("post source bind group"), 
+            }
+        );
+        PostTarget { texture, view, bg }
+    }
+
+    fn create_pipeline(
+        gfx: &WGPUCtx, 
+        label: &str, 
+        fragment: &str, 
+        bind_group_layouts: &[&wgpu::BindGroupLayout], 
+        format: wgpu::TextureFormat, 
+    ) -> wgpu::RenderPipeline {
+        // シェーダモジュールの読み込み
+        let shader = gfx.device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some(label), 
+                source: wgpu::ShaderSource::Wgsl(
+                    format!("{COMMON_WGSL}\n{fragment}").into(), 
+                )
+            }
+        );
+
+        // パイプラインレイアウトの初期化
+        let pipeline_layout = gfx.device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some(label), 
+                bind_group_layouts, 
+                push_constant_ranges: &[]
+            }
+        );
+
+        // パイプラインの初期化
+        gfx.device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(label), 
+                layout: Some(&pipeline_layout), 
+                vertex: wgpu::VertexState {
+                    module: &shader, 
+                    entry_point: "vs_main", 
+                    buffers: &[], 
+                }, 
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader, 
+                    entry_point: "fs_main", 
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format, 
+                        blend: None, 
+                        write_mask: wgpu::ColorWrites::all()
+                    })]
+                }), 
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList, 
+                    strip_index_format: None, 
+                    front_face: wgpu::FrontFace::Ccw, 
+                    cull_mode: None, 
+                    unclipped_depth: false, 
+                    polygon_mode: wgpu::PolygonMode::Fill, 
+                    conservative: false, 
+                }, 
+                depth_stencil: None, 
+                multisample: wgpu::MultisampleState {
+                    count: 1, 
+                    mask: !0, 
+                    alpha_to_coverage_enabled: false, 
+                }, 
+                multiview: None, 
+            }
+        )
+    }
+
+    /// エフェクトの追加
+    /// 
+    /// `params`はユニフォームとして渡されるため、WGSL側の
+    /// 構造体とレイアウトを合わせてください。追加したエフェクトの番号を返します。
+    pub fn push_effect<P: bytemuck::Pod>(
+        &mut self, 
+        gfx: &WGPUCtx, 
+        label: &str, 
+        fragment: &str, 
+        params: &P, 
+    ) -> usize {
+        // ユニフォームは16バイト単位に揃える
+        let bytes = bytemuck::bytes_of(params);
+        let size = (bytes.len().max(1).div_ceil(16) * 16) as u64;
+        let buffer = gfx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label), 
+            size, 
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST, 
+            mapped_at_creation: false, 
+        });
+        if !bytes.is_empty() {
+            gfx.queue.write_buffer(&buffer, 0, bytes);
+        }
+        let params_bg = gfx.device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &self.params_layout, 
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0, 
+                        resource: buffer.as_entire_binding(), 
+                    }, 
+                ], 
+                label: Some(label), 
+            }
+        );
+
+        let pipeline = Self::create_pipeline(
+            gfx, 
+            label, 
+            fragment, 
+            &[&self.source_layout, &self.params_layout], 
+            self.format, 
+        );
+
+        self.effects.push(PostEffect {
+            pipeline, 
+            params: buffer, 
+            params_bg, 
+            params_size: bytes.len(), 
+            enabled: true, 
+        });
+        self.effects.len() - 1
+    }
+
+    /// 組み込みのエフェクトの追加
+    pub fn push_preset<P: PostPreset>(
+        &mut self, 
+        gfx: &WGPUCtx, 
+        params: &P, 
+    ) -> usize { self.push_effect(gfx, P::LABEL, P::SOURCE, params) }
+
+    /// パラメータの更新
+    /// 
+    /// エフェクトが存在しない場合や、パラメータの大きさが
+    /// 追加時と異なる場合は更新せず`false`を返します。
+    pub fn set_params<P: bytemuck::Pod>(
+        &self, 
+        gfx: &WGPUCtx, 
+        index: usize, 
+        params: &P, 
+    ) -> bool {
+        let bytes = bytemuck::bytes_of(params);
+        let Some(effect) = self.effects.get(index) else { return false };
+        if effect.params_size != bytes.len() { return false }
+        if !bytes.is_empty() {
+            gfx.queue.write_buffer(&effect.params, 0, bytes)
+        }
+        true
+    }
+
+    /// 有効・無効の切り替え
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(effect) = self.effects.get_mut(index) {
+            effect.enabled = enabled
+        }
+    }
+
+    /// シェーダに渡す時刻(秒)の設定
+    pub fn set_time(&mut self, time: f32) { self.globals.time = time }
+
+    /// エフェクトの数
+    pub fn len(&self) -> usize { self.effects.len() }
+
+    pub fn is_empty(&self) -> bool { self.effects.is_empty() }
+
+    /// 大きさの変更
+    pub(super) fn resize(&mut self, gfx: &WGPUCtx) {
+        let size = [gfx.config.width.max(1), gfx.config.height.max(1)];
+        if size == self.size { return }
+        self.size = size;
+        self.globals.resolution = [size[0] as f32, size[1] as f32];
+        self.targets = std::array::from_fn(|_| Self::create_target(
+            gfx, 
+            size, 
+            self.format, 
+            &self.sampler, 
+            &self.source_layout, 
+            &self.globals_buffer, 
+        ));
+    }
+
+    /// シーンの描画先のビューの生成
+    pub(super) fn scene_view(&self) -> wgpu::TextureView {
+        self.targets[0].texture.create_view(&Default::default())
+    }
+
+    /// エフェクトを適用して出力先へ描画する
+    pub(super) fn apply(
+        &self, 
+        gfx: &WGPUCtx, 
+        output: &wgpu::TextureView, 
+    ) {
+        gfx.queue.write_buffer(
+            &self.globals_buffer, 
+            0, 
+            bytemuck::cast_slice(&[self.globals]), 
+        );
+
+        let mut encoder = gfx.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor { label: Some("post encoder") }
+        );
+
+        let enabled = self.effects.iter()
+            .filter(|e| e.enabled)
+            .collect::<Vec<_>>();
+        let mut current = 0;
+        let pass_count = enabled.len().max(1);
+        for i in 0..pass_count {
+            let dst = if i + 1 == pass_count {
+                output
+            } else {
+                &self.targets[1 - current].view
+            };
+
+            let mut render_pass = encoder.begin_render_pass(
+                &wgpu::RenderPassDescriptor {
+                    label: Some("post pass"), 
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: dst, 
+                        resolve_target: None, 
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), 
+                            store: true
+                        }
+                    })], 
+                    depth_stencil_attachment: None, 
+                }
+            );
+            render_pass.set_bind_group(0, &self.targets[current].bg, &[]);
+            match enabled.get(i) {
+                Some(effect) => {
+                    render_pass.set_pipeline(&effect.pipeline);
+                    render_pass.set_bind_group(1, &effect.params_bg, &[]);
+                }, 
+                None => render_pass.set_pipeline(&self.blit), 
+            }
+            render_pass.draw(0..3, 0..1);
+            drop(render_pass);
+
+            current = 1 - current;
+        }
+
+        gfx.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
@@ -0,0 +1,27 @@
+// CRT風の走査線と画面の湾曲
+
+struct Params {
+    intensity: f32, 
+    line_count: f32, 
+    curvature: f32, 
+    speed: f32, 
+}
+@group(1) @binding(0)
+var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 画面の湾曲
+    var uv = in.uv * 2. - 1.;
+    uv *= 1. + params.curvature * dot(uv.yx, uv.yx);
+    uv = uv * 0.5 + 0.5;
+    if uv.x < 0. || 1. < uv.x || uv.y < 0. || 1. < uv.y {
+        return vec4<f32>(0., 0., 0., 1.);
+    }
+
+    let src = textureSampleLevel(t_source, s_source, uv, 0.);
+    let line = sin((uv.y * params.line_count + globals.time * params.speed)
+        * 3.14159265);
+    let v = 1. - params.intensity * (0.5 - 0.5 * line);
+    return vec4<f32>(src.rgb * v, src.a);
+}
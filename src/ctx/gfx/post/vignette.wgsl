@@ -0,0 +1,21 @@
+// ビネット
+
+struct Params {
+    color: vec4<f32>, 
+    intensity: f32, 
+    radius: f32, 
+    softness: f32, 
+    reserved: f32, 
+}
+@group(1) @binding(0)
+var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let src = textureSample(t_source, s_source, in.uv);
+    let aspect = globals.resolution.x / max(globals.resolution.y, 1.);
+    let d = length((in.uv - 0.5) * vec2<f32>(aspect, 1.));
+    let v = smoothstep(params.radius, params.radius + params.softness, d)
+        * params.intensity;
+    return vec4<f32>(mix(src.rgb, params.color.rgb, v), src.a);
+}
@@ -0,0 +1,423 @@
+//! 仮想解像度
+//! 
+//! シーンを固定の論理解像度で描画し、ウィンドウに合わせて拡大します。
+//! 余白は帯として塗りつぶされます。
+
+use super::WGPUCtx;
+
+/// 拡大方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// 整数倍に拡大(ドットが崩れない)
+    /// 
+    /// ウィンドウが論理解像度より小さい場合のみ`Fit`と同様に縮小します。
+    Integer, 
+
+    /// 縦横比を保ってウィンドウに収まる最大の大きさに拡大
+    Fit, 
+
+    /// 縦横比を無視してウィンドウ全体に拡大
+    Stretch, 
+}
+impl ScaleMode {
+    /// 論理解像度を拡大したウィンドウ上の描画領域の計算
+    pub fn viewport(self, logical_size: [u32; 2], window_size: [u32; 2]) -> Viewport {
+        let window = [window_size[0] as f32, window_size[1] as f32];
+        let logical = [logical_size[0] as f32, logical_size[1] as f32];
+        let scale = match self {
+            ScaleMode::Integer => {
+                // ウィンドウが論理解像度より小さい場合は縮小する
+                let fit = (window[0] / logical[0]).min(window[1] / logical[1]);
+                let s = if 1. <= fit { fit.floor() } else { fit };
+                [s, s]
+            }, 
+            ScaleMode::Fit => {
+                let s = (window[0] / logical[0]).min(window[1] / logical[1]);
+                [s, s]
+            }, 
+            ScaleMode::Stretch => [
+                window[0] / logical[0], 
+                window[1] / logical[1], 
+            ], 
+        };
+        let width = logical[0] * scale[0];
+        let height = logical[1] * scale[1];
+        Viewport {
+            x: ((window[0] - width) * 0.5).floor(), 
+            y: ((window[1] - height) * 0.5).floor(), 
+            width, 
+            height, 
+        }
+    }
+}
+
+/// ウィンドウ上の描画領域(物理ピクセル)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32, 
+    pub y: f32, 
+    pub width: f32, 
+    pub height: f32, 
+}
+impl Viewport {
+    /// 描画先全体に対する割合からの生成
+    /// 
+    /// `ratio`は`[x, y, width, height]`で、それぞれ0から1の範囲です。
+    /// 画面分割やピクチャインピクチャの領域の指定に用います。
+    pub fn from_ratio(
+        target_size: [u32; 2], 
+        ratio: [f32; 4], 
+    ) -> Self {
+        let w = target_size[0] as f32;
+        let h = target_size[1] as f32;
+        let x = (ratio[0] * w).floor();
+        let y = (ratio[1] * h).floor();
+        Self {
+            x, 
+            y, 
+            width: ((ratio[0] + ratio[2]) * w).floor() - x, 
+            height: ((ratio[1] + ratio[3]) * h).floor() - y, 
+        }
+    }
+
+    /// 位置が領域内にあるか
+    pub fn contains(&self, position: [f32; 2]) -> bool {
+        (self.x..self.x + self.width).contains(&position[0])
+            && (self.y..self.y + self.height).contains(&position[1])
+    }
+
+    /// ウィンドウ座標から、この領域に`logical_size`を拡大した論理座標への変換
+    /// 
+    /// 領域外の位置では`None`を返します。
+    pub fn to_logical(
+        &self, 
+        logical_size: [u32; 2], 
+        position: [f64; 2], 
+    ) -> Option<[f32; 2]> {
+        let x = (position[0] as f32 - self.x) / self.width * logical_size[0] as f32;
+        let y = (position[1] as f32 - self.y) / self.height * logical_size[1] as f32;
+        if (0. ..logical_size[0] as f32).contains(&x)
+            && (0. ..logical_size[1] as f32).contains(&y)
+        {
+            Some([x, y])
+        } else {
+            None
+        }
+    }
+
+    /// 論理座標からウィンドウ座標への変換
+    pub fn to_window(
+        &self, 
+        logical_size: [u32; 2], 
+        position: [f32; 2], 
+    ) -> [f64; 2] {
+        [
+            (self.x + position[0] / logical_size[0] as f32 * self.width) as f64, 
+            (self.y + position[1] / logical_size[1] as f32 * self.height) as f64, 
+        ]
+    }
+}
+
+/// 仮想解像度での描画
+pub struct VirtualResolution {
+    size: [u32; 2], 
+    mode: ScaleMode, 
+    bar_color: wgpu::Color, 
+    texture: wgpu::Texture, 
+    bind_group: wgpu::BindGroup, 
+    pipeline: wgpu::RenderPipeline, 
+}
+impl VirtualResolution {
+    pub fn new(
+        gfx: &WGPUCtx, 
+        width: u32, 
+        height: u32, 
+        mode: ScaleMode, 
+    ) -> Self {
+        let size = [width.max(1), height.max(1)];
+
+        // 論理解像度の描画先
+        let texture = gfx.device.create_texture(
+            &wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: size[0], 
+                    height: size[1], 
+                    depth_or_array_layers: 1, 
+                }, 
+                mip_level_count: 1, 
+                sample_count: 1, 
+                dimension: wgpu::TextureDimension::D2, 
+                format: gfx.config.format, 
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING, 
+                label: Some("virtual resolution texture"), 
+                view_formats: &[], 
+            }
+        );
+        let view = texture.create_view(&Default::default());
+
+        // 拡大時にドットが滲まないよう最近傍補間
+        let sampler = gfx.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge, 
+            address_mode_v: wgpu::AddressMode::ClampToEdge, 
+            address_mode_w: wgpu::AddressMode::ClampToEdge, 
+            mag_filter: wgpu::FilterMode::Nearest, 
+            min_filter: wgpu::FilterMode::Nearest, 
+            mipmap_filter: wgpu::FilterMode::Nearest, 
+            ..Default::default()
+        });
+
+        // バインドグループ
+        let layout = gfx.device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("virtual resolution bind group layout"), 
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0, 
+                        visibility: wgpu::ShaderStages::FRAGMENT, 
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true }, 
+                            view_dimension: wgpu::TextureViewDimension::D2, 
+                            multisampled: false, 
+                        }, 
+                        count: None, 
+                    }, 
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1, 
+                        visibility: wgpu::ShaderStages::FRAGMENT, 
+                        ty: wgpu::BindingType::Sampler(
+                            wgpu::SamplerBindingType::Filtering, 
+                        ), 
+                        count: None, 
+                    }, 
+                ], 
+            }
+        );
+        let bind_group = gfx.device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &layout, 
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0, 
+                        resource: wgpu::BindingResource::TextureView(&view), 
+                    }, 
+                    wgpu::BindGroupEntry {
+                        binding: 1, 
+                        resource: wgpu::BindingResource::Sampler(&sampler), 
+                    }, 
+                ], 
+                label: Some("virtual resolution bind group"), 
+            }
+        );
+
+        // 拡大用のパイプライン(ポストエフェクトの出力用シェーダを流用)
+        let shader = gfx.device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("virtual resolution shader"), 
+                source: wgpu::ShaderSource::Wgsl(concat!(
+                    include_str!("post/common.wgsl"), 
+                    "\n", 
+                    include_str!("post/blit.wgsl"), 
+                ).into())
+            }
+        );
+        let pipeline_layout = gfx.device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("virtual resolution pipeline layout"), 
+                bind_group_layouts: &[&layout], 
+                push_constant_ranges: &[]
+            }
+        );
+        let pipeline = gfx.device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("virtual resolution pipeline"), 
+                layout: Some(&pipeline_layout), 
+                vertex: wgpu::VertexState {
+                    module: &shader, 
+                    entry_point: "vs_main", 
+                    buffers: &[], 
+                }, 
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader, 
+                    entry_point: "fs_main", 
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gfx.config.format, 
+                        blend: None, 
+                        write_mask: wgpu::ColorWrites::all()
+                    })]
+                }), 
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList, 
+                    strip_index_format: None, 
+                    front_face: wgpu::FrontFace::Ccw, 
+                    cull_mode: None, 
+                    unclipped_depth: false, 
+                    polygon_mode: wgpu::PolygonMode::Fill, 
+                    conservative: false, 
+                }, 
+                depth_stencil: None, 
+                multisample: wgpu::MultisampleState {
+                    count: 1, 
+                    mask: !0, 
+                    alpha_to_coverage_enabled: false, 
+                }, 
+                multiview: None, 
+            }
+        );
+
+        Self {
+            size, 
+            mode, 
+            bar_color: wgpu::Color::BLACK, 
+            texture, 
+            bind_group, 
+            pipeline, 
+        }
+    }
+
+    /// 論理解像度
+    pub fn size(&self) -> [u32; 2] { self.size }
+
+    /// 拡大方法
+    pub fn mode(&self) -> ScaleMode { self.mode }
+
+    pub fn set_mode(&mut self, mode: ScaleMode) { self.mode = mode }
+
+    /// 余白の色の設定
+    pub fn set_bar_color(&mut self, color: wgpu::Color) { self.bar_color = color }
+
+    /// ウィンドウ上の描画領域の計算
+    pub fn viewport(&self, window_size: [u32; 2]) -> Viewport {
+        self.mode.viewport(self.size, window_size)
+    }
+
+    /// ウィンドウ座標(物理ピクセル)から論理座標への変換
+    /// 
+    /// 論理座標は左上を原点とした論理解像度のピクセル単位です。
+    /// 余白上の位置では`None`を返します。
+    pub fn window_to_logical(
+        &self, 
+        window_size: [u32; 2], 
+        position: [f64; 2], 
+    ) -> Option<[f32; 2]> {
+        self.viewport(window_size).to_logical(self.size, position)
+    }
+
+    /// 論理座標からウィンドウ座標(物理ピクセル)への変換
+    pub fn logical_to_window(
+        &self, 
+        window_size: [u32; 2], 
+        position: [f32; 2], 
+    ) -> [f64; 2] {
+        self.viewport(window_size).to_window(self.size, position)
+    }
+
+    /// シーンの描画先のビューの生成
+    pub(super) fn scene_view(&self) -> wgpu::TextureView {
+        self.texture.create_view(&Default::default())
+    }
+
+    /// 拡大して出力先へ描画する
+    pub(super) fn apply(
+        &self, 
+        gfx: &WGPUCtx, 
+        output: &wgpu::TextureView, 
+    ) {
+        let vp = self.viewport([gfx.config.width, gfx.config.height]);
+
+        let mut encoder = gfx.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor { label: Some("virtual resolution encoder") }
+        );
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("virtual resolution pass"), 
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output, 
+                    resolve_target: None, 
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.bar_color), 
+                        store: true
+                    }
+                })], 
+                depth_stencil_attachment: None, 
+            }
+        );
+        if 1. <= vp.width && 1. <= vp.height {
+            render_pass.set_viewport(vp.x, vp.y, vp.width, vp.height, 0., 1.);
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        drop(render_pass);
+
+        gfx.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGICAL: [u32; 2] = [320, 180];
+
+    #[test]
+    fn integer_scale() {
+        // 3.3倍まで入るが3倍にして中央に寄せる
+        assert_eq!(ScaleMode::Integer.viewport(LOGICAL, [1056, 600]), Viewport {
+            x: 48., 
+            y: 30., 
+            width: 960., 
+            height: 540., 
+        });
+
+        // 論理解像度より小さい場合は縮小する
+        let vp = ScaleMode::Integer.viewport(LOGICAL, [160, 180]);
+        assert_eq!([vp.width, vp.height], [160., 90.]);
+    }
+
+    #[test]
+    fn fit_and_stretch() {
+        assert_eq!(ScaleMode::Fit.viewport(LOGICAL, [800, 600]), Viewport {
+            x: 0., 
+            y: 75., 
+            width: 800., 
+            height: 450., 
+        });
+        assert_eq!(ScaleMode::Stretch.viewport(LOGICAL, [800, 600]), Viewport {
+            x: 0., 
+            y: 0., 
+            width: 800., 
+            height: 600., 
+        });
+    }
+
+    #[test]
+    fn from_ratio() {
+        assert_eq!(Viewport::from_ratio([801, 600], [0.5, 0., 0.5, 0.5]), Viewport {
+            x: 400., 
+            y: 0., 
+            width: 401., 
+            height: 300., 
+        });
+
+        // 隣り合う領域は隙間なく並ぶ
+        let left = Viewport::from_ratio([801, 600], [0., 0., 0.5, 1.]);
+        let right = Viewport::from_ratio([801, 600], [0.5, 0., 0.5, 1.]);
+        assert_eq!(left.x + left.width, right.x);
+        assert!(left.contains([399.5, 10.]) && !left.contains([400., 10.]));
+    }
+
+    #[test]
+    fn cursor_in_bars() {
+        // 上下に75ピクセルの帯
+        let vp = ScaleMode::Fit.viewport(LOGICAL, [800, 600]);
+        assert_eq!(vp.to_logical(LOGICAL, [400., 10.]), None);
+        assert_eq!(vp.to_logical(LOGICAL, [400., 590.]), None);
+        assert_eq!(vp.to_logical(LOGICAL, [0., 75.]), Some([0., 0.]));
+        assert_eq!(vp.to_logical(LOGICAL, [400., 300.]), Some([160., 90.]));
+        assert_eq!(vp.to_logical(LOGICAL, [800., 300.]), None);
+
+        let window = vp.to_window(LOGICAL, [160., 90.]);
+        assert_eq!(window, [400., 300.]);
+    }
+}
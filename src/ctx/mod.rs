@@ -1,32 +1,37 @@
 use std::sync::Arc;
 use winit::{
-    window::Window, 
+    window::WindowId, 
     platform::run_return::EventLoopExtRunReturn, 
     event::{
         Event, 
         WindowEvent, 
-        KeyboardInput, 
-        DeviceEvent
     }
 };
 
-use self::frame::FrameGlobal;
+use self::{
+    frame::FrameGlobal, 
+    error::{ContextInitError, ContextRunError}, 
+    window::{WindowHandler, WindowUnit}, 
+};
 
+pub mod error;
 pub mod gfx;
 pub mod sfx;
 pub mod frame;
+mod window;
+
+pub use window::WindowSpawner;
 
 /// 全体のコンテキスト
 pub struct Context<I, F: frame::Frame<I, GCd>, GCd> where
     GCd: Send + Sync, 
 {
-    _dummy: std::marker::PhantomData<I>, 
     ev_loop: winit::event_loop::EventLoop<()>, 
-    window: Arc<Window>, 
-    gfx: gfx::GfxCtx<GCd>, 
+    main: WindowUnit<I, F, GCd>, 
+    subs: Vec<Box<dyn WindowHandler>>, 
+    focused: Option<WindowId>, 
     sfx: sfx::SfxCtx, 
-    fglob: F::FrG, 
-    frame: F, 
+    spawner: WindowSpawner, 
 }
 impl<I, F: frame::Frame<I, GCd>, GCd> Context<I, F, GCd> where
     GCd: Send + Sync, 
@@ -47,28 +52,28 @@ impl<I, F: frame::Frame<I, GCd>, GCd> Context<I, F, GCd> where
             &gfx::WGPUCtx, 
             &mut GCd, 
         ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
-    ) -> Result<
-        Self, 
-        Box<dyn std::error::Error>
-    > {
+        gfx_config: gfx::GfxConfig, 
+        sfx_config: sfx::SfxConfig, 
+    ) -> Result<Self, ContextInitError> {
         // ウィンドウのイベント受信・処理に使うイベントループの初期化
         let ev_loop = winit::event_loop::EventLoopBuilder::new().build();
 
         // ウィンドウの生成
         let window = F::window_builder()
             .build(&ev_loop)?;
-        let window = std::sync::Arc::new(window);
+        let window = Arc::new(window);
 
         // グラフィクスの初期化
         let gfx = gfx::GfxCtx::new(
             &window, 
+            &gfx_config, 
             gfx_ctx_data_init, 
             dupdater, 
             dreconfigureer
         ).await?;
 
         // オーディオの初期化
-        let sfx = sfx::SfxCtx::new(0.063)?;
+        let sfx = sfx::SfxCtx::new(sfx_config)?;
 
         // フレームの初期化
         let (fglob, frame) = F::new(
@@ -76,134 +81,163 @@ impl<I, F: frame::Frame<I, GCd>, GCd> Context<I, F, GCd> where
             &window, 
             &gfx, 
             &sfx, 
-        )?;
+        ).map_err(ContextInitError::FrameInitError)?;
 
         Ok(Self {
-            _dummy: std::marker::PhantomData, 
+            spawner: WindowSpawner::new(ev_loop.create_proxy()), 
             ev_loop, 
-            window, 
-            gfx, 
+            main: WindowUnit::new(window, gfx, fglob, frame), 
+            subs: Vec::new(), 
+            focused: None, 
             sfx, 
-            fglob, 
-            frame, 
         })
     }
 
+    /// 追加のウィンドウの生成
+    /// 
+    /// ウィンドウはメインウィンドウとデバイスを共有し、
+    /// 独自のサーフェス・描画データ・フレームを持ちます。
+    /// 追加のウィンドウが閉じられた場合はそのウィンドウのみが破棄されます。
+    pub fn add_window<I2, F2, GCd2>(
+        &mut self, 
+        frame_initializer: I2, 
+        gfx_ctx_data_init: impl FnOnce(
+            &gfx::WinitCtx, 
+            &gfx::WGPUCtx, 
+        ) -> Result<GCd2, Box<dyn std::error::Error>>, 
+        dupdater: impl FnMut(
+            &gfx::WinitCtx, 
+            &gfx::WGPUCtx, 
+            &mut GCd2, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+        dreconfigureer: impl FnMut(
+            &gfx::WinitCtx, 
+            &gfx::WGPUCtx, 
+            &mut GCd2, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+    ) -> Result<WindowId, ContextInitError> where
+        I2: 'static, 
+        F2: frame::Frame<I2, GCd2> + 'static, 
+        GCd2: Send + Sync + 'static, 
+    {
+        let sub = window::build_window::<I2, F2, GCd2>(
+            &self.ev_loop, 
+            &self.main.gfx.wgpu_ctx, 
+            &self.sfx, 
+            frame_initializer, 
+            gfx_ctx_data_init, 
+            dupdater, 
+            dreconfigureer, 
+        )?;
+        let id = sub.window_id();
+        self.subs.push(sub);
+        Ok(id)
+    }
+
+    /// 実行中にウィンドウを生成するためのハンドル
+    /// 
+    /// `run`の開始後は`add_window`を呼べないため、こちらを用います。
+    pub fn window_spawner(&self) -> WindowSpawner { self.spawner.clone() }
+
     /// 実行
     pub fn run(
-        mut self, 
-    ) -> (i32, Result<(), Box<dyn std::error::Error>>) {
+        self, 
+    ) -> (i32, Result<(), ContextRunError>) {
+        let Self {
+            mut ev_loop, 
+            mut main, 
+            mut subs, 
+            mut focused, 
+            sfx, 
+            spawner, 
+        } = self;
+
+        // IDに対応するウィンドウの処理
+        fn handler<'a>(
+            main: &'a mut (dyn WindowHandler + 'a), 
+            subs: &'a mut [Box<dyn WindowHandler>], 
+            window_id: WindowId, 
+        ) -> Option<&'a mut (dyn WindowHandler + 'a)> {
+            if main.window_id() == window_id { return Some(main) }
+            subs.iter_mut()
+                .find(|w| w.window_id() == window_id)
+                .map(|w| &mut **w as &mut (dyn WindowHandler + 'a))
+        }
+
         let mut ret = Ok(());
-        (self.ev_loop.run_return(|
+        (ev_loop.run_return(|
             event, 
-            _, 
+            target, 
             ctrl
         | {
-            match event {
+            let result = match event {
+                // ウィンドウが閉じられたときの処理
+                Event::WindowEvent { 
+                    window_id, 
+                    event: WindowEvent::CloseRequested, 
+                } => {
+                    if main.window.id() == window_id {
+                        ctrl.set_exit()
+                    } else {
+                        subs.retain(|w| w.window_id() != window_id)
+                    }
+                    Ok(())
+                }, 
                 // ウィンドウ固有イベントの処理
                 Event::WindowEvent { 
                     window_id, 
-                    event 
-                } if self.window.id() == window_id => match event {
-                    WindowEvent::CloseRequested => ctrl.set_exit(), 
-                    WindowEvent::KeyboardInput { 
-                        input: KeyboardInput {
-                            state,
-                            virtual_keycode: Some(keycode),
-                            ..
-                        }, 
-                        .. 
-                    } => self.frame.input_key(keycode, state), 
-                    WindowEvent::MouseInput { 
-                        state, 
-                        button, 
-                        .. 
-                    } => self.frame.input_mouse_button(button, state), 
-                    WindowEvent::Resized(
-                        new_size
-                    ) => match self.gfx.reconfigure(Some(new_size)) {
-                        Ok(_) => self.frame.window_resizing(new_size), 
-                        Err(e) => {
-                            ret = Err(e);
-                            ctrl.set_exit();
-                        }
-                    }, 
-                    _ => {}, 
+                    event, 
+                } => {
+                    // フォーカスの追跡
+                    if let WindowEvent::Focused(true) = event {
+                        focused = Some(window_id)
+                    }
+                    match handler(&mut main, &mut subs, window_id) {
+                        Some(w) => w.window_event(event), 
+                        None => Ok(()), 
+                    }
                 }, 
+                // デバイスイベントはフォーカスされたウィンドウに送る
                 Event::DeviceEvent { 
                     event, 
                     .. 
-                } => match event {
-                    DeviceEvent::MouseMotion { 
-                        delta 
-                    } => self.frame.input_mouse_motion(delta), 
-                    DeviceEvent::MouseWheel { 
-                        delta
-                    } => self.frame.input_mouse_scroll(delta), 
-                    _ => {}, 
+                } => {
+                    let target = focused.unwrap_or_else(|| main.window.id());
+                    match handler(&mut main, &mut subs, target) {
+                        Some(w) => w.device_event(event), 
+                        None => main.device_event(event), 
+                    }
+                    Ok(())
                 }, 
                 // 描画の必要性が生じたときの処理
                 Event::RedrawRequested(
                     window_id
-                ) if self.window.id() == window_id => match self.gfx.rendering(
-                    &self.fglob
-                ) {
-                    Ok(
-                        render_chain
-                    ) => self.frame.rendering(
-                        render_chain
-                    ).present(), 
-                    Err(gfx::GfxCtxRenderingError::SurfaceError(
-                        wgpu::SurfaceError::Lost
-                    )) => match self.gfx.reconfigure(
-                        None
-                    ) {
-                        Ok(_) => {}, 
-                        Err(e) => {
-                            ret = Err(e);
-                            ctrl.set_exit();
-                        }
-                    }, 
-                    Err(gfx::GfxCtxRenderingError::SurfaceError(
-                        wgpu::SurfaceError::OutOfMemory
-                    )) => {
-                        eprintln!("out of memory error occured.");
-                        ctrl.set_exit_with_code(-1)
-                    }, 
-                    Err(gfx::GfxCtxRenderingError::SurfaceError(
-                        e
-                    )) => eprintln!("{e:?}"), 
-                    Err(gfx::GfxCtxRenderingError::RdrUpdateError(
-                        e
-                    )) => {
-                        ret = Err(e);
-                        ctrl.set_exit();
-                    }
+                ) => match handler(&mut main, &mut subs, window_id) {
+                    Some(w) => w.redraw(), 
+                    None => Ok(()), 
                 }, 
                 // すべてのイベントの処理を終えた時の処理
-                Event::MainEventsCleared => {
-                    match match self.fglob.update(
-                        &self.gfx, 
-                        &self.sfx
-                    ) {
-                        Ok(_) => self.frame.update(
-                            ctrl, 
-                            &self.fglob, 
-                            &self.gfx, 
-                            &self.sfx, 
-                        ), 
-                        e @ _ => e, 
-                    } {
-                        Ok(_) => self.window.request_redraw(), 
-                        Err(e) => {
-                            ret = Err(e);
-                            ctrl.set_exit();
-                        }
-                    }
-                    // 描画要求の発令
-                    self.window.request_redraw(); 
-                }, 
-                _ => {}, 
+                Event::MainEventsCleared => spawner.take()
+                    .into_iter()
+                    // 実行中に要求されたウィンドウの生成
+                    .try_for_each(|spawn| {
+                        subs.push(spawn(target, &main.gfx.wgpu_ctx, &sfx)
+                            .map_err(ContextRunError::WindowSpawnError)?);
+                        Ok(())
+                    })
+                    .and_then(|_| main.update(ctrl, &sfx))
+                    .and_then(|_| subs.iter_mut()
+                        .try_for_each(|w| w.update(ctrl, &sfx))
+                    ), 
+                _ => Ok(()), 
+            };
+            if let Err(e) = result {
+                if let ContextRunError::OutOfMemory = e {
+                    ctrl.set_exit_with_code(-1)
+                } else {
+                    ctrl.set_exit()
+                }
+                ret = Err(e);
             }
         }), ret)
     }
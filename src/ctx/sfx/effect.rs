@@ -0,0 +1,588 @@
+//! 音響効果
+//! 
+//! 音源に被せて使うローパスフィルタ・リバーブ・ダッキングです。
+//! パラメータは`EffectParam`で共有され、再生中に変化させることが出来ます。
+
+use std::sync::{
+    Arc, 
+    atomic::{AtomicU32, AtomicU64, Ordering}, 
+};
+use rodio::Source;
+
+/// パラメータの変化を確認する間隔(フレーム数)
+const POLL_INTERVAL: u32 = 32;
+
+/// 再生中に変更可能なパラメータ
+/// 
+/// クローンしたハンドルは同じパラメータを指します。
+#[derive(Clone)]
+pub struct EffectParam(Arc<ParamShared>);
+struct ParamShared {
+    /// 目標値(上位32ビット)と変化にかける秒数(下位32ビット)のf32のビット列
+    /// 
+    /// 2つを1度に読み書きし、別々の`ramp_to`の値が混ざらないようにします。
+    state: AtomicU64, 
+    generation: AtomicU32, 
+}
+impl ParamShared {
+    fn pack(target: f32, ramp: f32) -> u64 {
+        (target.to_bits() as u64) << 32 | ramp.to_bits() as u64
+    }
+
+    /// 目標値と変化にかける秒数の取得
+    fn load(&self) -> (f32, f32) {
+        let state = self.state.load(Ordering::Acquire);
+        (f32::from_bits((state >> 32) as u32), f32::from_bits(state as u32))
+    }
+}
+impl EffectParam {
+    pub fn new(value: f32) -> Self { Self(Arc::new(ParamShared {
+        state: AtomicU64::new(ParamShared::pack(value, 0.)), 
+        generation: AtomicU32::new(0), 
+    }))}
+
+    /// 値の即時設定
+    pub fn set(&self, value: f32) { self.ramp_to(value, 0.) }
+
+    /// 指定の秒数をかけて値を変化させる
+    pub fn ramp_to(&self, value: f32, secs: f32) {
+        self.0.state.store(ParamShared::pack(value, secs.max(0.)), Ordering::Release);
+        self.0.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// 目標値の取得
+    pub fn target(&self) -> f32 { self.0.load().0 }
+}
+impl From<f32> for EffectParam {
+    fn from(value: f32) -> Self { Self::new(value) }
+}
+
+/// 音源側でパラメータを追従させる
+struct ParamFollower {
+    param: EffectParam, 
+    generation: u32, 
+    value: f32, 
+    target: f32, 
+    step: f32, 
+    remain: u32, 
+    poll_count: u32, 
+}
+impl ParamFollower {
+    fn new(param: EffectParam) -> Self {
+        let value = param.target();
+        Self {
+            generation: param.0.generation.load(Ordering::Acquire), 
+            param, 
+            value, 
+            target: value, 
+            step: 0., 
+            remain: 0, 
+            poll_count: POLL_INTERVAL, 
+        }
+    }
+
+    /// 1フレーム進めて値を返す
+    fn next(&mut self, sample_rate: u32) -> f32 {
+        self.poll_count -= 1;
+        if self.poll_count == 0 {
+            self.poll(sample_rate);
+            self.poll_count = POLL_INTERVAL;
+        }
+        if 0 < self.remain {
+            self.remain -= 1;
+            self.value = if self.remain == 0 {
+                self.target
+            } else {
+                self.value + self.step
+            };
+        }
+        self.value
+    }
+
+    fn poll(&mut self, sample_rate: u32) {
+        let generation = self.param.0.generation.load(Ordering::Acquire);
+        if generation == self.generation { return }
+        self.generation = generation;
+        let (target, ramp) = self.param.0.load();
+        self.target = target;
+        let frames = (ramp * sample_rate as f32) as u32;
+        if frames == 0 {
+            self.value = self.target;
+            self.remain = 0;
+        } else {
+            self.step = (self.target - self.value) / frames as f32;
+            self.remain = frames;
+        }
+    }
+}
+
+/// 効果の処理単位
+/// 
+/// チャンネルごとの状態は効果側で持ちます。
+pub trait EffectCore: Send {
+    /// フレームの先頭で呼び出されるパラメータの更新
+    fn update(&mut self, sample_rate: u32, channels: u16);
+
+    /// サンプルの処理
+    fn process(&mut self, channel: usize, sample: f32) -> f32;
+}
+
+/// 効果を適用した音源
+pub struct Effected<S: Source<Item = f32>, E> {
+    src: S, 
+    core: E, 
+    channel: usize, 
+}
+impl<S: Source<Item = f32>, E: EffectCore> Iterator for Effected<S, E> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let channels = self.src.channels().max(1);
+        let sample = self.src.next()?;
+        if self.channel == 0 {
+            self.core.update(self.src.sample_rate(), channels);
+        }
+        let out = self.core.process(self.channel, sample);
+        self.channel = (self.channel + 1) % channels as usize;
+        Some(out)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.src.size_hint() }
+}
+impl<S: Source<Item = f32>, E: EffectCore> Source for Effected<S, E> {
+    fn current_frame_len(&self) -> Option<usize> { self.src.current_frame_len() }
+    fn channels(&self) -> u16 { self.src.channels() }
+    fn sample_rate(&self) -> u32 { self.src.sample_rate() }
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        self.src.total_duration()
+    }
+}
+
+/// ローパスフィルタ(双二次フィルタ)
+pub struct LowPass {
+    cutoff: ParamFollower, 
+    q: ParamFollower, 
+    coef: Option<(f32, f32, u32)>, 
+    b: [f32; 3], 
+    a: [f32; 2], 
+    state: Vec<[f32; 4]>, 
+}
+impl LowPass {
+    fn new(cutoff: EffectParam, q: EffectParam) -> Self { Self {
+        cutoff: ParamFollower::new(cutoff), 
+        q: ParamFollower::new(q), 
+        coef: None, 
+        b: [1., 0., 0.], 
+        a: [0., 0.], 
+        state: Vec::new(), 
+    }}
+}
+impl EffectCore for LowPass {
+    fn update(&mut self, sample_rate: u32, channels: u16) {
+        self.state.resize(channels as usize, [0.; 4]);
+        let cutoff = self.cutoff.next(sample_rate)
+            .clamp(10., sample_rate as f32 * 0.49);
+        let q = self.q.next(sample_rate).max(0.01);
+        if self.coef == Some((cutoff, q, sample_rate)) { return }
+        self.coef = Some((cutoff, q, sample_rate));
+
+        // RBJのクックブックに基づく係数
+        let w0 = std::f32::consts::TAU * cutoff / sample_rate as f32;
+        let alpha = w0.sin() / (2. * q);
+        let cos = w0.cos();
+        let a0 = 1. + alpha;
+        self.b = [
+            (1. - cos) * 0.5 / a0, 
+            (1. - cos) / a0, 
+            (1. - cos) * 0.5 / a0, 
+        ];
+        self.a = [
+            -2. * cos / a0, 
+            (1. - alpha) / a0, 
+        ];
+    }
+
+    fn process(&mut self, channel: usize, sample: f32) -> f32 {
+        let [x1, x2, y1, y2] = self.state[channel];
+        let y = self.b[0] * sample + self.b[1] * x1 + self.b[2] * x2
+            - self.a[0] * y1 - self.a[1] * y2;
+        self.state[channel] = [sample, x1, y, y1];
+        y
+    }
+}
+
+/// 遅延線
+struct DelayLine {
+    buf: Vec<f32>, 
+    pos: usize, 
+}
+impl DelayLine {
+    fn new(len: usize) -> Self { Self {
+        buf: vec![0.; len.max(1)], 
+        pos: 0, 
+    }}
+
+    fn read(&self) -> f32 { self.buf[self.pos] }
+
+    fn write(&mut self, v: f32) {
+        self.buf[self.pos] = v;
+        self.pos = (self.pos + 1) % self.buf.len();
+    }
+}
+
+/// リバーブのチャンネルごとの状態
+struct ReverbChannel {
+    combs: Vec<(DelayLine, f32)>, 
+    allpasses: Vec<DelayLine>, 
+}
+
+/// 簡易リバーブ(Freeverb方式)
+pub struct Reverb {
+    room_size: ParamFollower, 
+    damping: ParamFollower, 
+    mix: ParamFollower, 
+    params: [f32; 3], 
+    sample_rate: u32, 
+    channels: Vec<ReverbChannel>, 
+}
+impl Reverb {
+    /// 44.1kHz時のくし形フィルタの遅延長
+    const COMB_TUNING: [usize; 4] = [1116, 1188, 1277, 1356];
+
+    /// 44.1kHz時のオールパスフィルタの遅延長
+    const ALLPASS_TUNING: [usize; 2] = [556, 441];
+
+    /// チャンネルごとの遅延長のずらし幅
+    const STEREO_SPREAD: usize = 23;
+
+    fn new(
+        room_size: EffectParam, 
+        damping: EffectParam, 
+        mix: EffectParam, 
+    ) -> Self { Self {
+        room_size: ParamFollower::new(room_size), 
+        damping: ParamFollower::new(damping), 
+        mix: ParamFollower::new(mix), 
+        params: [0.; 3], 
+        sample_rate: 0, 
+        channels: Vec::new(), 
+    }}
+
+    fn build_channel(sample_rate: u32, channel: usize) -> ReverbChannel {
+        let scale = |len: usize| (
+            (len + channel * Self::STEREO_SPREAD) as u64
+                * sample_rate as u64 / 44100
+        ) as usize;
+        ReverbChannel {
+            combs: Self::COMB_TUNING.iter()
+                .map(|l| (DelayLine::new(scale(*l)), 0.))
+                .collect(), 
+            allpasses: Self::ALLPASS_TUNING.iter()
+                .map(|l| DelayLine::new(scale(*l)))
+                .collect(), 
+        }
+    }
+}
+impl EffectCore for Reverb {
+    fn update(&mut self, sample_rate: u32, channels: u16) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.channels.clear();
+        }
+        while self.channels.len() < channels as usize {
+            self.channels.push(Self::build_channel(
+                sample_rate, 
+                self.channels.len(), 
+            ));
+        }
+        self.params = [
+            self.room_size.next(sample_rate).clamp(0., 1.) * 0.28 + 0.7, 
+            self.damping.next(sample_rate).clamp(0., 1.) * 0.4, 
+            self.mix.next(sample_rate).clamp(0., 1.), 
+        ];
+    }
+
+    fn process(&mut self, channel: usize, sample: f32) -> f32 {
+        let [feedback, damp, mix] = self.params;
+        let ch = &mut self.channels[channel];
+        let input = sample * 0.015;
+
+        // くし形フィルタの並列処理
+        let mut wet = 0.;
+        for (line, store) in ch.combs.iter_mut() {
+            let out = line.read();
+            *store = out * (1. - damp) + *store * damp;
+            line.write(input + *store * feedback);
+            wet += out;
+        }
+
+        // オールパスフィルタの直列処理
+        for line in ch.allpasses.iter_mut() {
+            let buffered = line.read();
+            line.write(wet + buffered * 0.5);
+            wet = buffered - wet;
+        }
+
+        sample * (1. - mix) + wet * mix * 3.
+    }
+}
+
+/// ダッキング用のサイドチェイン
+/// 
+/// `SidechainSend`を通る音の大きさに応じて、
+/// `Ducked`を通る音の音量を下げます。
+/// 
+/// 入力レベルはf32のビット列として共有され、
+/// 音声スレッドからロックせずに読み書きされます。
+#[derive(Clone, Default)]
+pub struct DuckSidechain(Arc<AtomicU32>);
+impl DuckSidechain {
+    pub fn new() -> Self { Self::default() }
+
+    /// 現在の入力レベル
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// サイドチェインへの入力
+/// 
+/// 音はそのまま通過します。
+pub struct SidechainSend {
+    level: Arc<AtomicU32>, 
+    published: u32, 
+    envelope: f32, 
+    peak: f32, 
+    count: u32, 
+}
+impl SidechainSend {
+    /// エンベロープの立ち上がり時間(秒)
+    const ATTACK: f32 = 0.005;
+
+    /// エンベロープの減衰時間(秒)
+    const RELEASE: f32 = 0.2;
+
+    /// 入力レベルの公開
+    /// 
+    /// 非負のf32はビット列の大小と値の大小が一致するため、整数として比較します。
+    /// 自身が公開した値が最大のままなら置き換え、そうでなければ最大値を更新します。
+    /// 他の入力がより大きい間に下げた場合も、その入力の次の公開で戻ります。
+    fn publish(&mut self, level: f32) {
+        let bits = level.max(0.).to_bits();
+        if self.level.compare_exchange(
+            self.published, 
+            bits, 
+            Ordering::Relaxed, 
+            Ordering::Relaxed, 
+        ).is_err() {
+            self.level.fetch_max(bits, Ordering::Relaxed);
+        }
+        self.published = bits;
+    }
+}
+impl EffectCore for SidechainSend {
+    fn update(&mut self, sample_rate: u32, _channels: u16) {
+        let coef = if self.envelope < self.peak {
+            Self::ATTACK
+        } else {
+            Self::RELEASE
+        };
+        let coef = 1. - (-1. / (coef * sample_rate as f32)).exp();
+        self.envelope += (self.peak - self.envelope) * coef;
+        self.peak = 0.;
+        self.count += 1;
+        if POLL_INTERVAL <= self.count {
+            self.count = 0;
+            self.publish(self.envelope);
+        }
+    }
+
+    fn process(&mut self, _channel: usize, sample: f32) -> f32 {
+        self.peak = self.peak.max(sample.abs());
+        sample
+    }
+}
+impl Drop for SidechainSend {
+    fn drop(&mut self) {
+        self.publish(0.)
+    }
+}
+
+/// サイドチェインによる音量の抑制
+pub struct Ducked {
+    chain: DuckSidechain, 
+    threshold: ParamFollower, 
+    depth: ParamFollower, 
+    level: f32, 
+    gain: f32, 
+    count: u32, 
+    frame_gain: f32, 
+}
+impl Ducked {
+    /// 音量変化にかける時間(秒)
+    const SMOOTHING: f32 = 0.05;
+}
+impl EffectCore for Ducked {
+    fn update(&mut self, sample_rate: u32, _channels: u16) {
+        self.count = self.count.saturating_sub(1);
+        if self.count == 0 {
+            self.level = self.chain.level();
+            self.count = POLL_INTERVAL;
+        }
+        let threshold = self.threshold.next(sample_rate).max(f32::EPSILON);
+        let depth = self.depth.next(sample_rate).clamp(0., 1.);
+        let target = 1. - depth * (self.level / threshold).min(1.);
+        let coef = 1. - (-1. / (Self::SMOOTHING * sample_rate as f32)).exp();
+        self.gain += (target - self.gain) * coef;
+        self.frame_gain = self.gain;
+    }
+
+    fn process(&mut self, _channel: usize, sample: f32) -> f32 {
+        sample * self.frame_gain
+    }
+}
+
+/// 音源に効果を被せるための拡張
+pub trait SfxEffectExt: Source<Item = f32> + Sized {
+    /// 任意の効果
+    fn effect<E: EffectCore>(
+        self, 
+        core: E, 
+    ) -> Effected<Self, E> { Effected {
+        src: self, 
+        core, 
+        channel: 0, 
+    }}
+
+    /// ローパスフィルタ
+    /// 
+    /// `cutoff`は遮断周波数(Hz)、`q`は共振の鋭さ(0.707で平坦)です。
+    fn low_pass(
+        self, 
+        cutoff: impl Into<EffectParam>, 
+        q: impl Into<EffectParam>, 
+    ) -> Effected<Self, LowPass> { Effected {
+        src: self, 
+        core: LowPass::new(cutoff.into(), q.into()), 
+        channel: 0, 
+    }}
+
+    /// リバーブ
+    /// 
+    /// パラメータはすべて0から1の値をとります。
+    fn reverb(
+        self, 
+        room_size: impl Into<EffectParam>, 
+        damping: impl Into<EffectParam>, 
+        mix: impl Into<EffectParam>, 
+    ) -> Effected<Self, Reverb> { Effected {
+        src: self, 
+        core: Reverb::new(room_size.into(), damping.into(), mix.into()), 
+        channel: 0, 
+    }}
+
+    /// サイドチェインへの入力
+    fn sidechain(
+        self, 
+        chain: &DuckSidechain, 
+    ) -> Effected<Self, SidechainSend> { Effected {
+        src: self, 
+        core: SidechainSend {
+            level: chain.0.clone(), 
+            published: 0f32.to_bits(), 
+            envelope: 0., 
+            peak: 0., 
+            count: 0, 
+        }, 
+        channel: 0, 
+    }}
+
+    /// サイドチェインによるダッキング
+    /// 
+    /// サイドチェインの入力が`threshold`に達したとき、
+    /// 音量が`1 - depth`倍まで下がります。
+    fn duck(
+        self, 
+        chain: &DuckSidechain, 
+        threshold: impl Into<EffectParam>, 
+        depth: impl Into<EffectParam>, 
+    ) -> Effected<Self, Ducked> { Effected {
+        src: self, 
+        core: Ducked {
+            chain: chain.clone(), 
+            threshold: ParamFollower::new(threshold.into()), 
+            depth: ParamFollower::new(depth.into()), 
+            level: 0., 
+            gain: 1., 
+            count: 0, 
+            frame_gain: 1., 
+        }, 
+        channel: 0, 
+    }}
+}
+impl<S: Source<Item = f32>> SfxEffectExt for S {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::{buffer::SamplesBuffer, source::SineWave};
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    fn rms(samples: impl Iterator<Item = f32>) -> f32 {
+        let (sum, count) = samples.fold((0., 0), |(s, c), v| (s + v * v, c + 1));
+        (sum / count as f32).sqrt()
+    }
+
+    /// 一定の値が続く音源
+    fn constant(value: f32, secs: f32) -> SamplesBuffer<f32> {
+        let len = (SAMPLE_RATE as f32 * secs) as usize;
+        SamplesBuffer::new(1, SAMPLE_RATE, vec![value; len])
+    }
+
+    #[test]
+    fn ramp_follows_target() {
+        let param = EffectParam::new(0.);
+        let mut follower = ParamFollower::new(param.clone());
+        param.ramp_to(1., 0.01);
+        assert_eq!(param.target(), 1.);
+        assert_eq!(param.0.load(), (1., 0.01));
+
+        let values: Vec<f32> = (0..SAMPLE_RATE / 10)
+            .map(|_| follower.next(SAMPLE_RATE))
+            .collect();
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(values.last(), Some(&1.));
+    }
+
+    #[test]
+    fn low_pass_attenuates_high_frequency() {
+        let filtered = |frequency: f32| {
+            let src = SineWave::new(frequency).take_duration(std::time::Duration::from_millis(200));
+            let dry = rms(src.clone());
+            let wet = rms(SfxEffectExt::low_pass(src, 500., 0.707).skip(SAMPLE_RATE as usize / 100));
+            wet / dry
+        };
+        assert!(filtered(8000.) < 0.05, "{}", filtered(8000.));
+        assert!(0.9 < filtered(100.), "{}", filtered(100.));
+    }
+
+    #[test]
+    fn duck_lowers_and_restores() {
+        let chain = DuckSidechain::new();
+        let mut send = constant(1., 1.).sidechain(&chain);
+        send.by_ref().take(SAMPLE_RATE as usize / 10).for_each(drop);
+        assert!(0.9 < chain.level(), "{}", chain.level());
+
+        // 0.5以上の入力で0.2倍まで下がる
+        let mut ducked = constant(1., 1.).duck(&chain, 0.5, 0.8);
+        let lowered = ducked.by_ref().take(SAMPLE_RATE as usize / 5).last().unwrap();
+        assert!((lowered - 0.2).abs() < 0.05, "{lowered}");
+
+        // 入力が無くなると元に戻る
+        drop(send);
+        assert_eq!(chain.level(), 0.);
+        let restored = ducked.by_ref().take(SAMPLE_RATE as usize * 3 / 10).last().unwrap();
+        assert!(0.95 < restored, "{restored}");
+    }
+}
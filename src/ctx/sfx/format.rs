@@ -0,0 +1,186 @@
+//! 出力形式
+//! 
+//! 出力のサンプリング周波数・チャンネル構成と、
+//! 音源のチャンネル構成の変換を扱います。
+
+use rodio::Source;
+
+/// チャンネル構成
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLayout {
+    /// モノラル
+    Mono, 
+
+    /// ステレオ
+    Stereo, 
+}
+impl ChannelLayout {
+    /// チャンネル数
+    pub fn channels(&self) -> u16 { match self {
+        Self::Mono => 1, 
+        Self::Stereo => 2, 
+    }}
+}
+
+/// サウンドの設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SfxConfig {
+    /// ミキサのサンプリング周波数
+    pub sample_rate: u32, 
+
+    /// ミキサのチャンネル構成
+    pub layout: ChannelLayout, 
+
+    /// 初期音量
+    pub volume: f32, 
+}
+impl Default for SfxConfig {
+    fn default() -> Self { Self {
+        sample_rate: 44100, 
+        layout: ChannelLayout::Stereo, 
+        volume: 0.063, 
+    }}
+}
+
+/// チャンネル構成を変換する音源
+/// 
+/// モノラルは全チャンネルに複製し、3チャンネル以上の音源は
+/// WAVEのチャンネル順(FL, FR, FC, LFE, BL, BR, SL, SR)として
+/// ステレオへダウンミックスします。
+/// センターは両側へ、サラウンドは同じ側へ約-3dB(0.707倍)で加え、
+/// LFEは用いません。
+pub struct ChannelMix<S: Source<Item = f32>> {
+    src: S, 
+    layout: ChannelLayout, 
+    frame: [f32; 2], 
+    cursor: usize, 
+}
+impl<S: Source<Item = f32>> ChannelMix<S> {
+    pub fn new(src: S, layout: ChannelLayout) -> Self { Self {
+        src, 
+        layout, 
+        frame: [0., 0.], 
+        cursor: 0, 
+    }}
+
+    /// 入力の1フレームを読み出してまとめる
+    fn read_frame(&mut self) -> Option<[f32; 2]> {
+        let channels = self.src.channels().max(1);
+        let first = self.src.next()?;
+        if channels == 1 { return Some([first, first]) }
+
+        let mut mix = [first, 0.];
+        for ch in 1..channels as usize {
+            match self.src.next() {
+                Some(s) => {
+                    let [l, r] = downmix_gain(channels, ch);
+                    mix[0] += s * l;
+                    mix[1] += s * r;
+                }, 
+                None => break, 
+            }
+        }
+        Some(mix)
+    }
+}
+
+/// ステレオへのダウンミックスでの各チャンネルの係数
+/// 
+/// 既知の構成以外の追加チャンネルは偶数番目を左、奇数番目を右とします。
+fn downmix_gain(channels: u16, ch: usize) -> [f32; 2] {
+    const C: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    match (channels, ch) {
+        (_, 0) => [1., 0.], 
+        (_, 1) => [0., 1.], 
+        // センター
+        (3 | 5..=8, 2) => [C, C], 
+        // LFE
+        (6..=8, 3) => [0., 0.], 
+        // 4チャンネル(FL, FR, BL, BR)
+        (4, 2) => [C, 0.], 
+        (4, 3) => [0., C], 
+        // 5チャンネル(FL, FR, FC, BL, BR)
+        (5, 3) => [C, 0.], 
+        (5, 4) => [0., C], 
+        // 6.1チャンネル(FL, FR, FC, LFE, BC, SL, SR)
+        (7, 4) => [C * C, C * C], 
+        (7, 5) => [C, 0.], 
+        (7, 6) => [0., C], 
+        // 5.1・7.1チャンネル
+        (6 | 8, 4) | (8, 6) => [C, 0.], 
+        (6 | 8, 5) | (8, 7) => [0., C], 
+        (_, ch) => if ch % 2 == 0 { [C, 0.] } else { [0., C] }, 
+    }
+}
+impl<S: Source<Item = f32>> Iterator for ChannelMix<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let out_channels = self.layout.channels() as usize;
+        if self.cursor == 0 {
+            let [l, r] = self.read_frame()?;
+            self.frame = match self.layout {
+                ChannelLayout::Mono => [(l + r) * 0.5, 0.], 
+                ChannelLayout::Stereo => [l, r], 
+            };
+        }
+        let sample = self.frame[self.cursor];
+        self.cursor = (self.cursor + 1) % out_channels;
+        Some(sample)
+    }
+}
+impl<S: Source<Item = f32>> Source for ChannelMix<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        let channels = self.src.channels().max(1) as usize;
+        let out_channels = self.layout.channels() as usize;
+        self.src.current_frame_len()
+            .map(|len| len / channels * out_channels + self.cursor)
+    }
+
+    fn channels(&self) -> u16 { self.layout.channels() }
+
+    fn sample_rate(&self) -> u32 { self.src.sample_rate() }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        self.src.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downmix_5_1() {
+        const C: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+        // FL, FR, FC, LFE, BL, BR
+        let frame = [0.1, 0.2, 0.4, 0.9, 0.3, 0.5];
+        let src = rodio::buffer::SamplesBuffer::new(6, 48000, frame.to_vec());
+        let out: Vec<f32> = ChannelMix::new(src, ChannelLayout::Stereo).collect();
+
+        let expected = [
+            0.1 + 0.4 * C + 0.3 * C, 
+            0.2 + 0.4 * C + 0.5 * C, 
+        ];
+        assert_eq!(out.len(), 2);
+        for (o, e) in out.iter().zip(expected) {
+            assert!((o - e).abs() < 1e-6, "{out:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn downmix_5_1_drops_lfe() {
+        let frame = [0., 0., 0., 1., 0., 0.];
+        let src = rodio::buffer::SamplesBuffer::new(6, 48000, frame.to_vec());
+        let out: Vec<f32> = ChannelMix::new(src, ChannelLayout::Stereo).collect();
+        assert_eq!(out, [0., 0.]);
+    }
+
+    #[test]
+    fn mono_is_duplicated() {
+        let src = rodio::buffer::SamplesBuffer::new(1, 48000, vec![0.5, -0.25]);
+        let out: Vec<f32> = ChannelMix::new(src, ChannelLayout::Stereo).collect();
+        assert_eq!(out, [0.5, 0.5, -0.25, -0.25]);
+    }
+}
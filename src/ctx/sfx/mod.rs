@@ -1,26 +1,45 @@
-use std::{sync::Arc, borrow::Cow};
+use std::{
+    sync::{Arc, atomic::{AtomicBool, Ordering}}, 
+    borrow::Cow, 
+    time::Duration, 
+};
 
 use parking_lot::RwLock;
 use rodio::{
     OutputStream, 
     Sink, 
-    dynamic_mixer::{DynamicMixerController, mixer}, 
+    dynamic_mixer::{DynamicMixer, DynamicMixerController, mixer}, 
     OutputStreamHandle, 
-    source::{Buffered, Zero}, 
-    Decoder, 
+    source::Zero, 
     Source, cpal::FromSample, 
 };
 
+pub mod spatial;
+pub mod synth;
+pub mod resource;
+pub mod effect;
+pub mod format;
+
+use super::error::SfxInitError;
+pub use format::{ChannelLayout, SfxConfig};
+use format::ChannelMix;
+use resource::{
+    SfxPcm, 
+    SfxPcmSource, 
+    SfxResInfo, 
+    SfxResMngr, 
+};
+
 /// サウンドモジュール
 pub struct SfxCtx(Arc<RwLock<SfxModule>>);
 impl SfxCtx {
-    pub fn new(volume: f32) -> Result<
-        Self, 
-        Box<dyn std::error::Error>, 
-    > {
-        Ok(Self(Arc::new(RwLock::new(SfxModule::new(volume)?))))
+    pub fn new(config: SfxConfig) -> Result<Self, SfxInitError> {
+        Ok(Self(Arc::new(RwLock::new(SfxModule::new(config)?))))
     }
 
+    /// 出力の設定
+    pub fn config(&self) -> SfxConfig { self.0.read().config }
+
     pub fn set_volume(&self, volume: f32) {
         self.0.write().set_volume(volume)
     }
@@ -32,12 +51,81 @@ impl SfxCtx {
     ) { self.0.read().play(src) }
 
     /// リソースの追加
-    pub fn add_resource(
+    /// 
+    /// 音源はすべてデコードされ、PCMとして保持されます。
+    /// サンプリング周波数が出力と異なる場合は変換されます。
+    pub fn add_resource<S>(
         &self, 
         name: impl Into<Cow<'static, str>>, 
-        file: Decoder<std::fs::File>, 
-    ) -> Option<Buffered<Decoder<std::fs::File>>> {
-        self.0.write().add_resource(name, file)
+        src: S, 
+    ) -> Option<SfxPcm> where
+        S: Source, 
+        S::Item: rodio::Sample, 
+        f32: FromSample<S::Item>, 
+    {
+        self.add_resource_pcm(name, SfxPcm::from_source(src))
+    }
+
+    /// デコード済みのリソースの追加
+    /// 
+    /// 周波数の変換はロックを取る前に行います。
+    pub fn add_resource_pcm(
+        &self, 
+        name: impl Into<Cow<'static, str>>, 
+        pcm: SfxPcm, 
+    ) -> Option<SfxPcm> {
+        let pcm = pcm.resample(self.config().sample_rate);
+        self.0.write().res_mngr.add(name, pcm)
+    }
+
+    /// 読み出し可能なデータからのリソースの追加
+    pub fn add_resource_from_reader<R>(
+        &self, 
+        name: impl Into<Cow<'static, str>>, 
+        reader: R, 
+    ) -> Result<Option<SfxPcm>, rodio::decoder::DecoderError> where
+        R: std::io::Read + std::io::Seek + Send + Sync + 'static, 
+    {
+        Ok(self.add_resource_pcm(name, SfxPcm::decode(reader)?))
+    }
+
+    /// メモリ上のデータからのリソースの追加
+    pub fn add_resource_from_bytes(
+        &self, 
+        name: impl Into<Cow<'static, str>>, 
+        bytes: &[u8], 
+    ) -> Result<Option<SfxPcm>, rodio::decoder::DecoderError> {
+        Ok(self.add_resource_pcm(name, SfxPcm::decode_bytes(bytes)?))
+    }
+
+    /// リソースの除去
+    pub fn remove_resource<Q: ?Sized + Eq + std::hash::Hash>(
+        &self, 
+        name: &Q, 
+    ) -> Option<SfxPcm> where
+        Cow<'static, str>: std::borrow::Borrow<Q>, 
+    {
+        self.0.write().res_mngr.remove(name)
+    }
+
+    /// リソースの取得
+    pub fn get_resource<Q: ?Sized + Eq + std::hash::Hash>(
+        &self, 
+        name: &Q, 
+    ) -> Option<SfxPcm> where
+        Cow<'static, str>: std::borrow::Borrow<Q>, 
+    {
+        self.0.read().res_mngr.get_pcm(name).cloned()
+    }
+
+    /// リソースの一覧
+    pub fn list_resources(&self) -> Vec<(Cow<'static, str>, SfxResInfo)> {
+        self.0.read().res_mngr.list()
+    }
+
+    /// リソース全体のメモリ使用量(バイト)
+    pub fn resource_memory_usage(&self) -> usize {
+        self.0.read().res_mngr.memory_usage()
     }
 
     /// リソースの再生
@@ -48,7 +136,7 @@ impl SfxCtx {
     >(
         &self, 
         name: &Q, 
-        f: impl FnMut(Buffered<Decoder<std::fs::File>>) -> T, 
+        f: impl FnMut(SfxPcmSource) -> T, 
     ) -> bool where
         Cow<'static, str>: std::borrow::Borrow<Q>, 
         f32: FromSample<S>, 
@@ -56,6 +144,134 @@ impl SfxCtx {
         self.0.read().play_resource(name, f)
     }
 
+    /// バスの生成
+    /// 
+    /// バスに流した音はまとめて`effects`で加工されてから再生されます。
+    pub fn create_bus<T: Source<Item = f32> + Send + 'static>(
+        &self, 
+        effects: impl FnOnce(DynamicMixer<f32>) -> T, 
+    ) -> SfxBus { self.0.read().create_bus(effects) }
+
+    /// リソースのバスへの再生
+    pub fn play_resource_on<
+        Q: ?Sized + Eq + std::hash::Hash, 
+        S: rodio::Sample, 
+        T: Source<Item = S> + Send + 'static, 
+    >(
+        &self, 
+        bus: &SfxBus, 
+        name: &Q, 
+        f: impl FnMut(SfxPcmSource) -> T, 
+    ) -> bool where
+        Cow<'static, str>: std::borrow::Borrow<Q>, 
+        f32: FromSample<S>, 
+    {
+        if let Some(src) = self.0.read().res_mngr.get(name, f) {
+            bus.play(src);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// リスナーとなるカメラの更新
+    pub fn update_listener(
+        &self, 
+        camera: &crate::util::simple2d::types::Camera, 
+    ) { self.0.read().listener.update_camera(camera) }
+
+    /// 距離減衰カーブの設定
+    pub fn set_rolloff(&self, rolloff: spatial::Rolloff) {
+        self.0.read().listener.set_rolloff(rolloff)
+    }
+
+    /// 左右の定位の広がりの設定
+    /// 
+    /// 1で画面端が完全に左右に振り切れます。
+    pub fn set_pan_width(&self, pan_width: f32) {
+        self.0.read().listener.set_pan_width(pan_width)
+    }
+
+    /// 位置付きの音の再生
+    pub fn play_spatial<T: Source<Item = f32> + Send + 'static>(
+        &self, 
+        src: T, 
+        emitter: &spatial::SfxEmitter, 
+    ) { self.0.read().play_spatial(src, emitter) }
+
+    /// リソースの位置付き再生
+    pub fn play_resource_spatial<
+        Q: ?Sized + Eq + std::hash::Hash, 
+        S: rodio::Sample, 
+        T: Source<Item = S> + Send + 'static, 
+    >(
+        &self, 
+        name: &Q, 
+        emitter: &spatial::SfxEmitter, 
+        f: impl FnMut(SfxPcmSource) -> T, 
+    ) -> bool where
+        Cow<'static, str>: std::borrow::Borrow<Q>, 
+        f32: FromSample<S>, 
+    {
+        let module = self.0.read();
+        if let Some(src) = module.res_mngr.get(name, f) {
+            module.play_spatial(src, emitter);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// サウンドバス
+/// 
+/// 複数の音をまとめて効果をかけるための経路です。
+/// 全ての複製が破棄されると、再生中の音を鳴らし終えた後にバスも終了します。
+#[derive(Clone)]
+pub struct SfxBus {
+    ctrl: Arc<DynamicMixerController<f32>>, 
+    layout: ChannelLayout, 
+    _alive: Arc<BusAlive>, 
+}
+
+/// バスの生存の通知
+/// 
+/// 破棄時に停止フラグを立てます。
+struct BusAlive(Arc<AtomicBool>);
+impl Drop for BusAlive {
+    fn drop(&mut self) { self.0.store(true, Ordering::Relaxed) }
+}
+
+/// 停止フラグが立つまで無音を出し続ける音源
+/// 
+/// 再生する音がない間もバスのミキサを維持します。
+struct BusKeeper {
+    zero: Zero<f32>, 
+    stop: Arc<AtomicBool>, 
+}
+impl Iterator for BusKeeper {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stop.load(Ordering::Relaxed) { return None }
+        self.zero.next()
+    }
+}
+impl Source for BusKeeper {
+    fn current_frame_len(&self) -> Option<usize> { None }
+
+    fn channels(&self) -> u16 { self.zero.channels() }
+
+    fn sample_rate(&self) -> u32 { self.zero.sample_rate() }
+
+    fn total_duration(&self) -> Option<Duration> { None }
+}
+impl SfxBus {
+    /// バスで音を再生する
+    pub fn play<T: Source<Item = f32> + Send + 'static>(
+        &self, 
+        src: T, 
+    ) { self.ctrl.add(ChannelMix::new(src, self.layout)) }
 }
 
 /// サウンド関係のモジュール
@@ -65,11 +281,14 @@ struct SfxModule {
     sink: Sink, 
     mixer_ctrl: Arc<DynamicMixerController<f32>>, 
     res_mngr: SfxResMngr, 
+    listener: spatial::SfxListener, 
+    config: SfxConfig, 
 }
 impl SfxModule {
-    const MIXER_CHANNEL: u16 = 16;
-    const MIXER_SAMPLE_RATE: u32 = 44100;
-    fn new(volume: f32) -> Result<Self, Box<dyn std::error::Error>> {
+    fn new(config: SfxConfig) -> Result<Self, SfxInitError> {
+        let channels = config.layout.channels();
+        let sample_rate = config.sample_rate.max(1);
+
         // ストリーム出力の初期化
         let (
             stream, 
@@ -80,16 +299,16 @@ impl SfxModule {
         let (
             mixer_ctrl, 
             mixer, 
-        ) = mixer(Self::MIXER_CHANNEL, Self::MIXER_SAMPLE_RATE);
+        ) = mixer(channels, sample_rate);
 
         // シンクの初期化
         let sink = Sink::try_new(&stream_handle)?;
 
         // 音量のない音を準備する
-        mixer_ctrl.add(Zero::new(Self::MIXER_CHANNEL, Self::MIXER_SAMPLE_RATE));
+        mixer_ctrl.add(Zero::new(channels, sample_rate));
 
         // 音量を初期化
-        sink.set_volume(volume);
+        sink.set_volume(config.volume);
 
         // 音源にミキサを入力
         sink.append(mixer);
@@ -98,7 +317,7 @@ impl SfxModule {
         sink.play();
 
         // リソースマネージャの初期化
-        let res_mngr = SfxResMngr::new();
+        let res_mngr = SfxResMngr::new(sample_rate);
 
         Ok(Self {
             _stream: stream,
@@ -106,6 +325,11 @@ impl SfxModule {
             sink,
             mixer_ctrl,
             res_mngr,
+            listener: Default::default(), 
+            config: SfxConfig {
+                sample_rate, 
+                ..config
+            }, 
         })
     }
 
@@ -114,6 +338,7 @@ impl SfxModule {
         &mut self, 
         volume: f32, 
     ) {
+        self.config.volume = volume;
         self.sink.set_volume(volume)
     }
 
@@ -121,72 +346,65 @@ impl SfxModule {
     fn play<T: Source<Item = f32> + Send + 'static> (
         &self, 
         src: T, 
-    ) { self.mixer_ctrl.add(src) }
+    ) { self.mixer_ctrl.add(ChannelMix::new(src, self.config.layout)) }
 
-    /// リソースの追加
-    fn add_resource(
-        &mut self, 
-        name: impl Into<Cow<'static, str>>, 
-        file: Decoder<std::fs::File>, 
-    ) -> Option<Buffered<Decoder<std::fs::File>>> {
-        self.res_mngr.add(name, file)
+    /// バスの生成
+    fn create_bus<T: Source<Item = f32> + Send + 'static>(
+        &self, 
+        effects: impl FnOnce(DynamicMixer<f32>) -> T, 
+    ) -> SfxBus {
+        let (
+            ctrl, 
+            bus_mixer, 
+        ) = mixer(self.config.layout.channels(), self.config.sample_rate);
+
+        // 再生する音がなくても、ハンドルが残る間はバスを維持する
+        let stop = Arc::new(AtomicBool::new(false));
+        ctrl.add(BusKeeper {
+            zero: Zero::new(
+                self.config.layout.channels(), 
+                self.config.sample_rate, 
+            ), 
+            stop: stop.clone(), 
+        });
+
+        self.play(effects(bus_mixer));
+        SfxBus {
+            ctrl, 
+            layout: self.config.layout, 
+            _alive: Arc::new(BusAlive(stop)), 
+        }
     }
 
-    /// リソースの再生
-    fn play_resource<
-        Q: ?Sized + Eq + std::hash::Hash, 
-        S: rodio::Sample, 
-        T: Source<Item = S> + Send + 'static, 
-    >(
+    /// 位置付きの音の再生
+    fn play_spatial<T: Source<Item = f32> + Send + 'static>(
         &self, 
-        name: &Q, 
-        f: impl FnMut(Buffered<Decoder<std::fs::File>>) -> T, 
-    ) -> bool where
-        Cow<'static, str>: std::borrow::Borrow<Q>, 
-        f32: FromSample<S>, 
-    {
-        self.res_mngr.play(name, self, f)
-    }
-}
+        src: T, 
+        emitter: &spatial::SfxEmitter, 
+    ) { self.play(spatial::Spatialized::new(
+        src, 
+        self.listener.clone(), 
+        emitter.clone(), 
+    )) }
 
-/// リソース管理機構
-struct SfxResMngr {
-    resources: hashbrown::HashMap<
-        Cow<'static, str>, 
-        Buffered<Decoder<std::fs::File>>
-    >, 
-}
-impl SfxResMngr {
-    fn new() -> Self { Self { resources: Default::default() } }
-    fn add (
-        &mut self, 
-        name: impl Into<Cow<'static, str>>, 
-        file: Decoder<std::fs::File>, 
-    ) -> Option<Buffered<Decoder<std::fs::File>>> {
-        self.resources.insert(
-            name.into(), 
-            file.buffered(), 
-        )
-    }
-    fn play<
+    /// リソースの再生
+    fn play_resource<
         Q: ?Sized + Eq + std::hash::Hash, 
         S: rodio::Sample, 
         T: Source<Item = S> + Send + 'static, 
     >(
         &self, 
         name: &Q, 
-        sfx_ctx: &SfxModule, 
-        mut f: impl FnMut(
-            Buffered<Decoder<std::fs::File>>
-        ) -> T
+        f: impl FnMut(SfxPcmSource) -> T, 
     ) -> bool where
         Cow<'static, str>: std::borrow::Borrow<Q>, 
         f32: FromSample<S>, 
     {
-        if let Some(res) = self.resources.get(name)
-        {
-            sfx_ctx.play(f(res.clone()).convert_samples());
+        if let Some(src) = self.res_mngr.get(name, f) {
+            self.play(src);
             true
-        } else { false }
+        } else {
+            false
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,380 @@
+//! サウンドリソース
+//! 
+//! 読み込んだ音声はデコード済みのPCMとして保持し、
+//! 再生時にはデータを共有したまま音源を生成します。
+
+use std::{
+    sync::Arc, 
+    borrow::Cow, 
+    io::{Read, Seek}, 
+};
+use rodio::{
+    Decoder, 
+    Source, 
+    Sample, 
+    cpal::FromSample, 
+    decoder::DecoderError, 
+};
+
+/// デコード済みのPCMデータ
+#[derive(Debug, Clone)]
+pub struct SfxPcm {
+    samples: Arc<[f32]>, 
+    channels: u16, 
+    sample_rate: u32, 
+}
+impl SfxPcm {
+    /// 音源をすべて読み出してPCMにする
+    /// 
+    /// 途中でチャンネル数やサンプリング周波数が変わる音源は、
+    /// 最初の区間の形式に揃えて変換します。
+    /// 無限に続く音源を渡した場合は処理が終わりません。
+    pub fn from_source<S>(src: S) -> Self where
+        S: Source, 
+        S::Item: Sample, 
+        f32: FromSample<S::Item>, 
+    {
+        let mut src = src.convert_samples::<f32>();
+
+        // 形式の同じ区間ごとに読み出す
+        let mut spans: Vec<(u16, u32, Vec<f32>)> = Vec::new();
+        loop {
+            let channels = src.channels().max(1);
+            let sample_rate = src.sample_rate().max(1);
+            let span = match spans.last_mut() {
+                Some(last) if last.0 == channels && last.1 == sample_rate => &mut last.2, 
+                _ => {
+                    spans.push((channels, sample_rate, Vec::new()));
+                    &mut spans.last_mut().expect("span was just pushed").2
+                }, 
+            };
+            let len = span.len();
+            match src.current_frame_len() {
+                Some(0) => {}, 
+                Some(frame_len) => span.extend((&mut src).take(frame_len)), 
+                None => span.extend(&mut src), 
+            }
+            if span.len() == len {
+                if span.is_empty() { spans.pop(); }
+                break
+            }
+        }
+
+        // 最初の区間の形式へ揃えて連結する
+        let mut spans = spans.into_iter();
+        let Some((channels, sample_rate, mut samples)) = spans.next() else {
+            return Self {
+                samples: Arc::new([]), 
+                channels: src.channels().max(1), 
+                sample_rate: src.sample_rate().max(1), 
+            }
+        };
+        for (span_channels, span_rate, span) in spans {
+            let span = Self {
+                samples: span.into(), 
+                channels: span_channels, 
+                sample_rate: span_rate, 
+            }.remix(channels).resample(sample_rate);
+            samples.extend_from_slice(&span.samples);
+        }
+        Self {
+            samples: samples.into(), 
+            channels, 
+            sample_rate, 
+        }
+    }
+
+    /// チャンネル数の変換
+    /// 
+    /// モノラルからは複製し、モノラルへは平均します。
+    /// それ以外は足りないチャンネルを既存のチャンネルから繰り返して補います。
+    fn remix(&self, channels: u16) -> Self {
+        if channels == self.channels { return self.clone() }
+        let src_channels = self.channels as usize;
+        let samples = self.samples.chunks_exact(src_channels)
+            .flat_map(|frame| (0..channels as usize).map(move |ch| {
+                if channels == 1 {
+                    frame.iter().sum::<f32>() / src_channels as f32
+                } else {
+                    frame[ch % src_channels]
+                }
+            }))
+            .collect();
+        Self {
+            samples, 
+            channels, 
+            sample_rate: self.sample_rate, 
+        }
+    }
+
+    /// 読み出し可能なデータのデコード
+    pub fn decode<R>(reader: R) -> Result<Self, DecoderError> where
+        R: Read + Seek + Send + Sync + 'static, 
+    {
+        Ok(Self::from_source(Decoder::new(reader)?))
+    }
+
+    /// メモリ上のデータのデコード
+    pub fn decode_bytes(bytes: &[u8]) -> Result<Self, DecoderError> {
+        Self::decode(std::io::Cursor::new(bytes.to_vec()))
+    }
+
+    /// 静的なメモリ上のデータのデコード(`include_bytes!`向け)
+    pub fn decode_static(bytes: &'static [u8]) -> Result<Self, DecoderError> {
+        Self::decode(std::io::Cursor::new(bytes))
+    }
+
+    /// サンプリング周波数の変換(線形補間)
+    pub fn resample(&self, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1);
+        if sample_rate == self.sample_rate { return self.clone() }
+
+        let channels = self.channels as usize;
+        let frames = self.samples.len() / channels;
+        if frames == 0 { return Self {
+            samples: self.samples.clone(), 
+            channels: self.channels, 
+            sample_rate, 
+        }}
+        let out_frames = (frames as u64 * sample_rate as u64
+            / self.sample_rate as u64) as usize;
+        let ratio = self.sample_rate as f64 / sample_rate as f64;
+
+        let samples = (0..out_frames).flat_map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = (pos as usize).min(frames - 1);
+            let next = (idx + 1).min(frames - 1);
+            let t = (pos - idx as f64) as f32;
+            (0..channels).map(move |ch| {
+                let a = self.samples[idx * channels + ch];
+                let b = self.samples[next * channels + ch];
+                a + (b - a) * t
+            })
+        }).collect();
+
+        Self {
+            samples, 
+            channels: self.channels, 
+            sample_rate, 
+        }
+    }
+
+    /// 再生用の音源の生成
+    pub fn source(&self) -> SfxPcmSource { SfxPcmSource {
+        samples: self.samples.clone(), 
+        pos: 0, 
+        channels: self.channels, 
+        sample_rate: self.sample_rate, 
+    }}
+
+    pub fn channels(&self) -> u16 { self.channels }
+    pub fn sample_rate(&self) -> u32 { self.sample_rate }
+    pub fn samples(&self) -> &[f32] { &self.samples }
+
+    /// 再生時間
+    pub fn duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(
+            self.samples.len() as f64
+                / self.channels as f64
+                / self.sample_rate as f64
+        )
+    }
+
+    /// データが占めるメモリ量(バイト)
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of_val(&*self.samples)
+    }
+
+    /// リソース情報の取得
+    pub fn info(&self) -> SfxResInfo { SfxResInfo {
+        channels: self.channels, 
+        sample_rate: self.sample_rate, 
+        duration: self.duration(), 
+        memory_usage: self.memory_usage(), 
+    }}
+}
+
+/// PCMデータを再生する音源
+#[derive(Debug, Clone)]
+pub struct SfxPcmSource {
+    samples: Arc<[f32]>, 
+    pos: usize, 
+    channels: u16, 
+    sample_rate: u32, 
+}
+impl Iterator for SfxPcmSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.samples.get(self.pos).copied()?;
+        self.pos += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remain = self.samples.len() - self.pos;
+        (remain, Some(remain))
+    }
+}
+impl ExactSizeIterator for SfxPcmSource {}
+impl Source for SfxPcmSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len() - self.pos)
+    }
+
+    fn channels(&self) -> u16 { self.channels }
+
+    fn sample_rate(&self) -> u32 { self.sample_rate }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        Some(std::time::Duration::from_secs_f64(
+            self.samples.len() as f64
+                / self.channels as f64
+                / self.sample_rate as f64
+        ))
+    }
+}
+
+/// リソースの情報
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SfxResInfo {
+    pub channels: u16, 
+    pub sample_rate: u32, 
+    pub duration: std::time::Duration, 
+    pub memory_usage: usize, 
+}
+
+/// リソース管理機構
+pub(super) struct SfxResMngr {
+    sample_rate: u32, 
+    resources: hashbrown::HashMap<
+        Cow<'static, str>, 
+        SfxPcm, 
+    >, 
+}
+impl SfxResMngr {
+    pub(super) fn new(sample_rate: u32) -> Self { Self {
+        sample_rate, 
+        resources: Default::default(), 
+    }}
+
+    pub(super) fn add(
+        &mut self, 
+        name: impl Into<Cow<'static, str>>, 
+        pcm: SfxPcm, 
+    ) -> Option<SfxPcm> {
+        // 出力と周波数が異なる場合は事前に変換しておく
+        // (通常は呼び出し側でロックの外で変換済み)
+        let pcm = if pcm.sample_rate() == self.sample_rate {
+            pcm
+        } else {
+            pcm.resample(self.sample_rate)
+        };
+        self.resources.insert(
+            name.into(), 
+            pcm, 
+        )
+    }
+
+    pub(super) fn remove<Q: ?Sized + Eq + std::hash::Hash>(
+        &mut self, 
+        name: &Q, 
+    ) -> Option<SfxPcm> where
+        Cow<'static, str>: std::borrow::Borrow<Q>, 
+    {
+        self.resources.remove(name)
+    }
+
+    pub(super) fn get_pcm<Q: ?Sized + Eq + std::hash::Hash>(
+        &self, 
+        name: &Q, 
+    ) -> Option<&SfxPcm> where
+        Cow<'static, str>: std::borrow::Borrow<Q>, 
+    {
+        self.resources.get(name)
+    }
+
+    pub(super) fn list(&self) -> Vec<(Cow<'static, str>, SfxResInfo)> {
+        self.resources.iter()
+            .map(|(name, pcm)| (name.clone(), pcm.info()))
+            .collect()
+    }
+
+    pub(super) fn memory_usage(&self) -> usize {
+        self.resources.values()
+            .map(|pcm| pcm.memory_usage())
+            .sum()
+    }
+
+    pub(super) fn get<
+        Q: ?Sized + Eq + std::hash::Hash, 
+        S: Sample, 
+        T: Source<Item = S> + Send + 'static, 
+    >(
+        &self, 
+        name: &Q, 
+        mut f: impl FnMut(SfxPcmSource) -> T, 
+    ) -> Option<rodio::source::SamplesConverter<T, f32>> where
+        Cow<'static, str>: std::borrow::Borrow<Q>, 
+        f32: FromSample<S>, 
+    {
+        self.resources.get(name)
+            .map(|res| f(res.source()).convert_samples())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 区間ごとに形式が変わる音源
+    struct Spans {
+        spans: Vec<(u16, u32, Vec<f32>)>, 
+        pos: usize, 
+    }
+    impl Iterator for Spans {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            let (_, _, samples) = self.spans.first()?;
+            let sample = samples[self.pos];
+            self.pos += 1;
+            if samples.len() <= self.pos {
+                self.spans.remove(0);
+                self.pos = 0;
+            }
+            Some(sample)
+        }
+    }
+    impl Source for Spans {
+        fn current_frame_len(&self) -> Option<usize> {
+            Some(self.spans.first().map_or(0, |s| s.2.len() - self.pos))
+        }
+
+        fn channels(&self) -> u16 { self.spans.first().map_or(1, |s| s.0) }
+
+        fn sample_rate(&self) -> u32 { self.spans.first().map_or(44100, |s| s.1) }
+
+        fn total_duration(&self) -> Option<std::time::Duration> { None }
+    }
+
+    #[test]
+    fn format_change_mid_stream() {
+        let pcm = SfxPcm::from_source(Spans {
+            spans: vec![
+                (2, 100, vec![0.1, 0.2, 0.3, 0.4]), 
+                (2, 100, vec![0.5, 0.6]), 
+                (1, 100, vec![0.7, 0.8]), 
+                (2, 200, vec![0.9, 1.0, 0.9, 1.0, 0.9, 1.0, 0.9, 1.0]), 
+            ], 
+            pos: 0, 
+        });
+        assert_eq!(pcm.channels(), 2);
+        assert_eq!(pcm.sample_rate(), 100);
+        assert_eq!(pcm.samples(), [
+            0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 
+            0.7, 0.7, 0.8, 0.8, 
+            0.9, 1.0, 0.9, 1.0, 
+        ]);
+    }
+}
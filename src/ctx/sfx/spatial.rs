@@ -0,0 +1,288 @@
+//! 位置付きサウンド
+//! 
+//! 2Dカメラをリスナーとみなし、発音体の位置に応じて
+//! 左右の定位と距離減衰を行います。
+
+use std::sync::Arc;
+use parking_lot::RwLock;
+use rodio::Source;
+
+/// 距離減衰のカーブ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rolloff {
+    /// 減衰しない
+    None, 
+
+    /// ワールド座標の距離に対する線形減衰
+    /// 
+    /// `min`以下で最大音量、`max`以上で無音になります。
+    Linear {
+        min: f32, 
+        max: f32, 
+    }, 
+
+    /// ワールド座標の距離に対する逆数減衰
+    /// 
+    /// `reference`以下で最大音量となり、以降は
+    /// `reference / (reference + factor * (距離 - reference))`で減衰します。
+    Inverse {
+        reference: f32, 
+        factor: f32, 
+    }, 
+
+    /// 画面の大きさを基準にした線形減衰
+    /// 
+    /// 距離は画面の半分の大きさを1とした値で測ります。
+    /// `inner`以下で最大音量、`outer`以上で無音になります。
+    ViewRelative {
+        inner: f32, 
+        outer: f32, 
+    }, 
+}
+impl Default for Rolloff {
+    fn default() -> Self { Self::ViewRelative {
+        inner: 1., 
+        outer: 2., 
+    }}
+}
+impl Rolloff {
+    /// 減衰率の計算
+    pub fn attenuation(
+        &self, 
+        world_dist: f32, 
+        view_dist: f32, 
+    ) -> f32 { match *self {
+        Rolloff::None => 1., 
+        Rolloff::Linear { min, max } => linear_attenuation(
+            world_dist, min, max
+        ), 
+        Rolloff::Inverse { reference, factor } => if world_dist <= reference {
+            1.
+        } else {
+            reference / (reference + factor * (world_dist - reference))
+        }, 
+        Rolloff::ViewRelative { inner, outer } => linear_attenuation(
+            view_dist, inner, outer
+        ), 
+    }}
+}
+
+fn linear_attenuation(dist: f32, min: f32, max: f32) -> f32 {
+    if dist <= min { 1. }
+    else if max <= dist { 0. }
+    else { 1. - (dist - min) / (max - min) }
+}
+
+/// リスナーの状態
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ListenerState {
+    position: nalgebra::Point2<f32>, 
+    half_extent: nalgebra::Vector2<f32>, 
+    rotation: [f32; 2], 
+    rolloff: Rolloff, 
+    pan_width: f32, 
+}
+impl Default for ListenerState {
+    fn default() -> Self { Self {
+        position: [0., 0.].into(), 
+        half_extent: [1., 1.].into(), 
+        rotation: [1., 0.], 
+        rolloff: Rolloff::default(), 
+        pan_width: 1., 
+    }}
+}
+impl ListenerState {
+    /// カメラ座標系での相対位置(画面端が±1)とワールド距離の計算
+    fn relative(
+        &self, 
+        position: nalgebra::Point2<f32>, 
+    ) -> (nalgebra::Vector2<f32>, f32) {
+        let rel = position - self.position;
+        let rotated = nalgebra::Vector2::new(
+            rel.x * self.rotation[0] - rel.y * self.rotation[1], 
+            rel.x * self.rotation[1] + rel.y * self.rotation[0], 
+        );
+        (
+            rotated.component_div(&self.half_extent), 
+            rel.norm(), 
+        )
+    }
+
+    /// 左右チャンネルの音量の計算
+    fn gains(
+        &self, 
+        emitter: &EmitterState, 
+    ) -> [f32; 2] {
+        let (view_rel, world_dist) = self.relative(emitter.position);
+        let att = emitter.rolloff.unwrap_or(self.rolloff)
+            .attenuation(world_dist, view_rel.norm())
+            * emitter.volume;
+
+        // 等パワーパンニング
+        let pan = (view_rel.x * self.pan_width).clamp(-1., 1.);
+        let angle = (pan + 1.) * std::f32::consts::FRAC_PI_4;
+        [angle.cos() * att, angle.sin() * att]
+    }
+}
+
+/// 音の聞き手(カメラ)
+#[derive(Clone, Default)]
+pub(super) struct SfxListener(Arc<RwLock<ListenerState>>);
+impl SfxListener {
+    /// カメラ情報によるリスナーの更新
+    pub(super) fn update_camera(
+        &self, 
+        camera: &crate::util::simple2d::types::Camera, 
+    ) {
+        let mut state = self.0.write();
+        state.position = camera.position;
+        state.half_extent = (camera.size * 0.5 / camera.zoom)
+            .map(|v| v.abs().max(f32::EPSILON));
+        state.rotation = [
+            (-camera.rotation).cos(), 
+            (-camera.rotation).sin(), 
+        ];
+    }
+
+    pub(super) fn set_rolloff(&self, rolloff: Rolloff) {
+        self.0.write().rolloff = rolloff
+    }
+
+    pub(super) fn set_pan_width(&self, pan_width: f32) {
+        self.0.write().pan_width = pan_width
+    }
+}
+
+/// 発音体の状態
+#[derive(Debug, Clone, Copy)]
+struct EmitterState {
+    position: nalgebra::Point2<f32>, 
+    volume: f32, 
+    rolloff: Option<Rolloff>, 
+}
+
+/// 発音体
+/// 
+/// クローンしたハンドルは同じ発音体を指し、位置の更新は
+/// 再生中の音に即座に反映されます。
+#[derive(Clone)]
+pub struct SfxEmitter(Arc<RwLock<EmitterState>>);
+impl SfxEmitter {
+    pub fn new(
+        position: impl Into<nalgebra::Point2<f32>>, 
+    ) -> Self { Self(Arc::new(RwLock::new(EmitterState {
+        position: position.into(), 
+        volume: 1., 
+        rolloff: None, 
+    })))}
+
+    /// 位置の取得
+    pub fn position(&self) -> nalgebra::Point2<f32> { self.0.read().position }
+
+    /// 位置の設定
+    pub fn set_position(
+        &self, 
+        position: impl Into<nalgebra::Point2<f32>>, 
+    ) { self.0.write().position = position.into() }
+
+    /// 音量の設定
+    pub fn set_volume(&self, volume: f32) { self.0.write().volume = volume }
+
+    /// この発音体だけに用いる減衰カーブの設定
+    /// 
+    /// `None`の場合はリスナーの減衰カーブを用います。
+    pub fn set_rolloff(&self, rolloff: Option<Rolloff>) {
+        self.0.write().rolloff = rolloff
+    }
+}
+
+/// 定位と減衰を行う音源
+/// 
+/// 入力をモノラルにまとめ、ステレオで出力します。
+pub struct Spatialized<S: Source<Item = f32>> {
+    src: S, 
+    listener: SfxListener, 
+    emitter: SfxEmitter, 
+    gains: [f32; 2], 
+    target: [f32; 2], 
+    frame: [f32; 2], 
+    cursor: usize, 
+    update_count: u32, 
+}
+impl<S: Source<Item = f32>> Spatialized<S> {
+    /// 定位の再計算を行う間隔(フレーム数)
+    const UPDATE_INTERVAL: u32 = 128;
+
+    /// 音量変化の平滑化係数
+    const SMOOTHING: f32 = 0.01;
+
+    pub(super) fn new(
+        src: S, 
+        listener: SfxListener, 
+        emitter: SfxEmitter, 
+    ) -> Self {
+        let gains = listener.0.read().gains(&emitter.0.read());
+        Self {
+            src, 
+            listener, 
+            emitter, 
+            gains, 
+            target: gains, 
+            frame: [0., 0.], 
+            cursor: 0, 
+            update_count: Self::UPDATE_INTERVAL, 
+        }
+    }
+
+    /// 発音体の参照
+    pub fn emitter(&self) -> &SfxEmitter { &self.emitter }
+}
+impl<S: Source<Item = f32>> Iterator for Spatialized<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor == 0 {
+            // 入力をモノラルにまとめる
+            let channels = self.src.channels().max(1);
+            let mut sum = self.src.next()?;
+            for _ in 1..channels {
+                match self.src.next() {
+                    Some(s) => sum += s, 
+                    None => break, 
+                }
+            }
+            let mono = sum / channels as f32;
+
+            // 定位の更新
+            self.update_count -= 1;
+            if self.update_count == 0 {
+                self.target = self.listener.0.read()
+                    .gains(&self.emitter.0.read());
+                self.update_count = Self::UPDATE_INTERVAL;
+            }
+            for (g, t) in self.gains.iter_mut().zip(self.target) {
+                *g += (t - *g) * Self::SMOOTHING;
+            }
+
+            self.frame = [mono * self.gains[0], mono * self.gains[1]];
+        }
+        let sample = self.frame[self.cursor];
+        self.cursor = (self.cursor + 1) % 2;
+        Some(sample)
+    }
+}
+impl<S: Source<Item = f32>> Source for Spatialized<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        let channels = self.src.channels().max(1) as usize;
+        self.src.current_frame_len()
+            .map(|len| len / channels * 2 + self.cursor)
+    }
+
+    fn channels(&self) -> u16 { 2 }
+
+    fn sample_rate(&self) -> u32 { self.src.sample_rate() }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        self.src.total_duration()
+    }
+}
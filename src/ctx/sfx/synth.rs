@@ -0,0 +1,426 @@
+//! 効果音合成
+//! 
+//! sfxr風の簡易的な音声合成器です。
+//! 同じパラメータからは常に同じ波形が生成されます。
+
+use rodio::Source;
+
+/// 波形
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    /// 矩形波(`duty`はデューティ比で0から1)
+    Square { duty: f32 }, 
+
+    /// のこぎり波
+    Saw, 
+
+    /// 三角波
+    Triangle, 
+
+    /// 正弦波
+    Sine, 
+
+    /// ノイズ
+    Noise, 
+}
+
+/// ADSRエンベロープ
+/// 
+/// 時間はすべて秒単位です。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32, 
+    pub decay: f32, 
+    pub sustain_level: f32, 
+    pub sustain: f32, 
+    pub release: f32, 
+}
+impl Default for Envelope {
+    fn default() -> Self { Self {
+        attack: 0., 
+        decay: 0.05, 
+        sustain_level: 0.5, 
+        sustain: 0.1, 
+        release: 0.2, 
+    }}
+}
+impl Envelope {
+    /// 全体の長さ
+    pub fn duration(&self) -> f32 {
+        self.attack + self.decay + self.sustain + self.release
+    }
+
+    /// 時刻`t`での音量
+    pub fn level(&self, t: f32) -> f32 {
+        let mut t = t;
+        if t < self.attack { return t / self.attack }
+        t -= self.attack;
+        if t < self.decay {
+            return 1. - (1. - self.sustain_level) * t / self.decay
+        }
+        t -= self.decay;
+        if t < self.sustain { return self.sustain_level }
+        t -= self.sustain;
+        if t < self.release {
+            return self.sustain_level * (1. - t / self.release)
+        }
+        0.
+    }
+}
+
+/// ピッチスイープ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitchSweep {
+    /// 1秒あたりの周波数変化(オクターブ)
+    pub slide: f32, 
+
+    /// 1秒あたりの`slide`の変化
+    pub delta_slide: f32, 
+
+    /// 周波数の下限
+    pub min_frequency: f32, 
+}
+impl Default for PitchSweep {
+    fn default() -> Self { Self {
+        slide: 0., 
+        delta_slide: 0., 
+        min_frequency: 20., 
+    }}
+}
+
+/// ビブラート
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vibrato {
+    /// 揺れ幅(周波数に対する比)
+    /// 
+    /// 周波数が負にならないよう、0以上1未満に制限されます。
+    pub depth: f32, 
+
+    /// 1秒あたりの揺れの回数
+    pub speed: f32, 
+}
+
+/// 合成パラメータ
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthParam {
+    pub waveform: Waveform, 
+    pub frequency: f32, 
+    pub sweep: PitchSweep, 
+    pub vibrato: Option<Vibrato>, 
+    pub envelope: Envelope, 
+    pub volume: f32, 
+    pub seed: u64, 
+    pub sample_rate: u32, 
+}
+impl Default for SynthParam {
+    fn default() -> Self { Self {
+        waveform: Waveform::Square { duty: 0.5 }, 
+        frequency: 440., 
+        sweep: PitchSweep::default(), 
+        vibrato: None, 
+        envelope: Envelope::default(), 
+        volume: 0.5, 
+        seed: 0x2545_F491_4F6C_DD1D, 
+        sample_rate: 44100, 
+    }}
+}
+impl SynthParam {
+    /// コイン取得音
+    pub fn pickup() -> Self { Self {
+        waveform: Waveform::Square { duty: 0.5 }, 
+        frequency: 988., 
+        envelope: Envelope {
+            attack: 0., 
+            decay: 0.02, 
+            sustain_level: 0.8, 
+            sustain: 0.06, 
+            release: 0.15, 
+        }, 
+        sweep: PitchSweep {
+            slide: 1.5, 
+            ..Default::default()
+        }, 
+        ..Default::default()
+    }}
+
+    /// 射撃音
+    pub fn laser() -> Self { Self {
+        waveform: Waveform::Saw, 
+        frequency: 1200., 
+        envelope: Envelope {
+            attack: 0., 
+            decay: 0., 
+            sustain_level: 1., 
+            sustain: 0.05, 
+            release: 0.15, 
+        }, 
+        sweep: PitchSweep {
+            slide: -12., 
+            delta_slide: 20., 
+            min_frequency: 100., 
+        }, 
+        ..Default::default()
+    }}
+
+    /// 爆発音
+    pub fn explosion() -> Self { Self {
+        waveform: Waveform::Noise, 
+        frequency: 220., 
+        envelope: Envelope {
+            attack: 0., 
+            decay: 0.1, 
+            sustain_level: 0.6, 
+            sustain: 0.15, 
+            release: 0.4, 
+        }, 
+        sweep: PitchSweep {
+            slide: -2., 
+            ..Default::default()
+        }, 
+        ..Default::default()
+    }}
+
+    /// ジャンプ音
+    pub fn jump() -> Self { Self {
+        waveform: Waveform::Square { duty: 0.25 }, 
+        frequency: 330., 
+        envelope: Envelope {
+            attack: 0., 
+            decay: 0.02, 
+            sustain_level: 0.7, 
+            sustain: 0.1, 
+            release: 0.1, 
+        }, 
+        sweep: PitchSweep {
+            slide: 4., 
+            ..Default::default()
+        }, 
+        ..Default::default()
+    }}
+
+    /// 被弾音
+    pub fn hit() -> Self { Self {
+        waveform: Waveform::Noise, 
+        frequency: 660., 
+        envelope: Envelope {
+            attack: 0., 
+            decay: 0., 
+            sustain_level: 1., 
+            sustain: 0.03, 
+            release: 0.1, 
+        }, 
+        sweep: PitchSweep {
+            slide: -6., 
+            ..Default::default()
+        }, 
+        ..Default::default()
+    }}
+
+    /// 音源の生成
+    pub fn build(&self) -> Synth { Synth::new(self.clone()) }
+}
+
+/// xorshift64*による乱数生成器
+#[derive(Debug, Clone)]
+struct XorShift(u64);
+impl XorShift {
+    fn new(seed: u64) -> Self { Self(if seed == 0 { 1 } else { seed }) }
+
+    /// -1から1の範囲の値
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let v = self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 40;
+        v as f32 / (1u64 << 23) as f32 - 1.
+    }
+}
+
+/// 合成された音源
+#[derive(Debug, Clone)]
+pub struct Synth {
+    param: SynthParam, 
+    rng: XorShift, 
+    index: u64, 
+    total: u64, 
+    phase: f32, 
+    frequency: f32, 
+    slide: f32, 
+    noise: f32, 
+    noise_step: u32, 
+}
+impl Synth {
+    /// ノイズの1周期あたりの値の更新回数
+    const NOISE_STEPS: f32 = 16.;
+
+    /// ビブラートの揺れ幅の上限(周波数が負にならない範囲)
+    const MAX_VIBRATO_DEPTH: f32 = 1. - f32::EPSILON;
+
+    pub fn new(param: SynthParam) -> Self {
+        let sample_rate = param.sample_rate.max(1);
+        let total = (param.envelope.duration() * sample_rate as f32)
+            .ceil() as u64;
+        let mut rng = XorShift::new(param.seed);
+        let noise = rng.next_f32();
+        Self {
+            frequency: param.frequency, 
+            slide: param.sweep.slide, 
+            param, 
+            rng, 
+            index: 0, 
+            total, 
+            phase: 0., 
+            noise, 
+            noise_step: 0, 
+        }
+    }
+
+    /// 合成パラメータの参照
+    pub fn param(&self) -> &SynthParam { &self.param }
+
+    /// 波形の値の計算
+    fn oscillate(&mut self) -> f32 {
+        let p = self.phase;
+        match self.param.waveform {
+            Waveform::Square { duty } => if p < duty { 1. } else { -1. }, 
+            Waveform::Saw => 1. - 2. * p, 
+            Waveform::Triangle => if p < 0.5 { 4. * p - 1. } else { 3. - 4. * p }, 
+            Waveform::Sine => (p * std::f32::consts::TAU).sin(), 
+            Waveform::Noise => {
+                let step = (p * Self::NOISE_STEPS) as u32;
+                if step != self.noise_step {
+                    self.noise_step = step;
+                    self.noise = self.rng.next_f32();
+                }
+                self.noise
+            }, 
+        }
+    }
+}
+impl Iterator for Synth {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.total <= self.index { return None }
+        let dt = (self.param.sample_rate.max(1) as f32).recip();
+        let t = self.index as f32 * dt;
+
+        let value = self.oscillate()
+            * self.param.envelope.level(t)
+            * self.param.volume;
+
+        // ピッチの更新
+        // 下限は範囲外やNaNでも可聴域へ収める
+        let lo = match self.param.sweep.min_frequency {
+            f if f.is_nan() => 0., 
+            f => f.clamp(0., 20000.), 
+        };
+        self.slide += self.param.sweep.delta_slide * dt;
+        self.frequency = (self.frequency * (self.slide * dt).exp2())
+            .clamp(lo, 20000.);
+        let frequency = match self.param.vibrato {
+            Some(v) => {
+                let depth = match v.depth {
+                    d if d.is_nan() => 0., 
+                    d => d.clamp(0., Self::MAX_VIBRATO_DEPTH), 
+                };
+                self.frequency * (
+                    1. + depth * (t * v.speed * std::f32::consts::TAU).sin()
+                )
+            }, 
+            None => self.frequency, 
+        };
+
+        // 位相の更新
+        let phase = self.phase + frequency * dt;
+        self.phase = phase.rem_euclid(1.);
+        if !(0. ..1.).contains(&phase) {
+            self.noise_step = u32::MAX;
+        }
+
+        self.index += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remain = (self.total - self.index) as usize;
+        (remain, Some(remain))
+    }
+}
+impl ExactSizeIterator for Synth {}
+impl Source for Synth {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some((self.total - self.index) as usize)
+    }
+
+    fn channels(&self) -> u16 { 1 }
+
+    fn sample_rate(&self) -> u32 { self.param.sample_rate.max(1) }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        Some(std::time::Duration::from_secs_f32(
+            self.total as f32 / self.sample_rate() as f32
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_samples() {
+        for param in [
+            SynthParam::pickup(), 
+            SynthParam::laser(), 
+            SynthParam::explosion(), 
+        ] {
+            let a: Vec<f32> = Synth::new(param.clone()).collect();
+            let b: Vec<f32> = Synth::new(param).collect();
+            assert!(!a.is_empty());
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn out_of_range_min_frequency() {
+        for min_frequency in [-10., 30000., f32::NAN] {
+            let param = SynthParam {
+                sweep: PitchSweep {
+                    slide: -4., 
+                    min_frequency, 
+                    ..Default::default()
+                }, 
+                ..SynthParam::laser()
+            };
+            assert!(Synth::new(param).all(|s| s.is_finite()));
+        }
+    }
+
+    #[test]
+    fn different_seed_noise() {
+        let param = SynthParam {
+            waveform: Waveform::Noise, 
+            ..Default::default()
+        };
+        let a: Vec<f32> = Synth::new(SynthParam { seed: 1, ..param.clone() }).collect();
+        let b: Vec<f32> = Synth::new(SynthParam { seed: 2, ..param }).collect();
+        assert_eq!(a.len(), b.len());
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn deep_vibrato_keeps_phase() {
+        for depth in [1., 4., f32::NAN] {
+            let mut synth = Synth::new(SynthParam {
+                waveform: Waveform::Saw, 
+                vibrato: Some(Vibrato { depth, speed: 30. }), 
+                ..Default::default()
+            });
+            while let Some(s) = synth.next() {
+                assert!(s.is_finite());
+                assert!((0. ..1.).contains(&synth.phase), "{}", synth.phase);
+            }
+        }
+    }
+}
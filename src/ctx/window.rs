@@ -0,0 +1,308 @@
+//! ウィンドウごとの処理単位
+
+use std::sync::Arc;
+use parking_lot::Mutex;
+use winit::{
+    window::{Window, WindowId}, 
+    event::{
+        WindowEvent, 
+        KeyboardInput, 
+        DeviceEvent, 
+    }, 
+    event_loop::{ControlFlow, EventLoopProxy, EventLoopWindowTarget}, 
+};
+
+use super::{
+    gfx, 
+    sfx, 
+    frame::{Frame, FrameGlobal}, 
+    error::{ContextInitError, ContextRunError}, 
+};
+
+/// ウィンドウとその描画・フレームをまとめたもの
+pub(super) struct WindowUnit<I, F: Frame<I, GCd>, GCd> where
+    GCd: Send + Sync, 
+{
+    _dummy: std::marker::PhantomData<I>, 
+    pub(super) window: Arc<Window>, 
+    pub(super) gfx: gfx::GfxCtx<GCd>, 
+    fglob: F::FrG, 
+    frame: F, 
+}
+impl<I, F: Frame<I, GCd>, GCd> WindowUnit<I, F, GCd> where
+    GCd: Send + Sync, 
+{
+    pub(super) fn new(
+        window: Arc<Window>, 
+        gfx: gfx::GfxCtx<GCd>, 
+        fglob: F::FrG, 
+        frame: F, 
+    ) -> Self { Self {
+        _dummy: std::marker::PhantomData, 
+        window, 
+        gfx, 
+        fglob, 
+        frame, 
+    }}
+}
+
+/// 型を隠蔽したウィンドウの処理
+pub(super) trait WindowHandler {
+    /// ウィンドウのID
+    fn window_id(&self) -> WindowId;
+
+    /// ウィンドウ固有イベントの処理
+    fn window_event(
+        &mut self, 
+        event: WindowEvent, 
+    ) -> Result<(), ContextRunError>;
+
+    /// デバイスイベントの処理
+    fn device_event(
+        &mut self, 
+        event: DeviceEvent, 
+    );
+
+    /// 描画
+    fn redraw(&mut self) -> Result<(), ContextRunError>;
+
+    /// 更新
+    fn update(
+        &mut self, 
+        ctrl: &mut ControlFlow, 
+        sfx: &sfx::SfxCtx, 
+    ) -> Result<(), ContextRunError>;
+}
+impl<I, F: Frame<I, GCd>, GCd> WindowHandler for WindowUnit<I, F, GCd> where
+    GCd: Send + Sync, 
+{
+    fn window_id(&self) -> WindowId { self.window.id() }
+
+    fn window_event(
+        &mut self, 
+        event: WindowEvent, 
+    ) -> Result<(), ContextRunError> {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state, 
+                    virtual_keycode: Some(keycode), 
+                    ..
+                }, 
+                ..
+            } => self.frame.input_key(keycode, state), 
+            WindowEvent::MouseInput {
+                state, 
+                button, 
+                ..
+            } => self.frame.input_mouse_button(button, state), 
+            WindowEvent::CursorMoved { 
+                position, 
+                .. 
+            } => self.frame.input_cursor_moved(position), 
+            WindowEvent::Resized(
+                new_size
+            ) => {
+                self.gfx.reconfigure(Some(new_size))
+                    .map_err(ContextRunError::ReconfigureError)?;
+                self.frame.window_resizing(new_size)
+            }, 
+            _ => {}, 
+        }
+        Ok(())
+    }
+
+    fn device_event(
+        &mut self, 
+        event: DeviceEvent, 
+    ) { match event {
+        DeviceEvent::MouseMotion {
+            delta
+        } => self.frame.input_mouse_motion(delta), 
+        DeviceEvent::MouseWheel {
+            delta
+        } => self.frame.input_mouse_scroll(delta), 
+        _ => {}, 
+    }}
+
+    fn redraw(&mut self) -> Result<(), ContextRunError> {
+        match self.gfx.rendering(
+            &self.fglob
+        ) {
+            Ok(
+                render_chain
+            ) => self.frame.rendering(
+                render_chain
+            ).present(), 
+            Err(gfx::GfxCtxRenderingError::SurfaceError(
+                wgpu::SurfaceError::Lost
+            )) => self.gfx.reconfigure(
+                None
+            ).map_err(ContextRunError::ReconfigureError)?, 
+            Err(gfx::GfxCtxRenderingError::SurfaceError(
+                wgpu::SurfaceError::OutOfMemory
+            )) => return Err(ContextRunError::OutOfMemory), 
+            Err(gfx::GfxCtxRenderingError::SurfaceError(
+                e
+            )) => eprintln!("{e:?}"), 
+            Err(gfx::GfxCtxRenderingError::RdrUpdateError(
+                e
+            )) => return Err(ContextRunError::RdrUpdateError(e)), 
+        }
+        Ok(())
+    }
+
+    fn update(
+        &mut self, 
+        ctrl: &mut ControlFlow, 
+        sfx: &sfx::SfxCtx, 
+    ) -> Result<(), ContextRunError> {
+        self.fglob.update(
+            &self.gfx, 
+            sfx, 
+        ).and_then(|_| self.frame.update(
+            ctrl, 
+            &self.fglob, 
+            &self.gfx, 
+            sfx, 
+        )).map_err(ContextRunError::FrameUpdateError)?;
+
+        // 表示モードの変更要求の適用
+        self.gfx.apply_present_mode();
+
+        // 描画要求の発令
+        self.window.request_redraw();
+        Ok(())
+    }
+}
+
+/// ウィンドウの生成とグラフィクス・フレームの初期化
+/// 
+/// グラフィクスは`shared`とデバイスを共有します。
+pub(super) fn build_window<I, F, GCd>(
+    target: &EventLoopWindowTarget<()>, 
+    shared: &gfx::WGPUCtx, 
+    sfx: &sfx::SfxCtx, 
+    frame_initializer: I, 
+    gfx_ctx_data_init: impl FnOnce(
+        &gfx::WinitCtx, 
+        &gfx::WGPUCtx, 
+    ) -> Result<GCd, Box<dyn std::error::Error>>, 
+    dupdater: impl FnMut(
+        &gfx::WinitCtx, 
+        &gfx::WGPUCtx, 
+        &mut GCd, 
+    ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+    dreconfigureer: impl FnMut(
+        &gfx::WinitCtx, 
+        &gfx::WGPUCtx, 
+        &mut GCd, 
+    ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+) -> Result<Box<dyn WindowHandler>, ContextInitError> where
+    I: 'static, 
+    F: Frame<I, GCd> + 'static, 
+    GCd: Send + Sync + 'static, 
+{
+    // ウィンドウの生成
+    let window = F::window_builder()
+        .build(target)?;
+    let window = Arc::new(window);
+
+    // デバイスを共有したグラフィクスの初期化
+    let gfx = gfx::GfxCtx::new_shared(
+        shared, 
+        &window, 
+        gfx_ctx_data_init, 
+        dupdater, 
+        dreconfigureer, 
+    )?;
+
+    // フレームの初期化
+    let (fglob, frame) = F::new(
+        frame_initializer, 
+        &window, 
+        &gfx, 
+        sfx, 
+    ).map_err(ContextInitError::FrameInitError)?;
+
+    Ok(Box::new(WindowUnit::<I, F, GCd>::new(
+        window, 
+        gfx, 
+        fglob, 
+        frame, 
+    )))
+}
+
+/// 実行中に要求されたウィンドウの生成処理
+pub(super) type SpawnFn = dyn FnOnce(
+    &EventLoopWindowTarget<()>, 
+    &gfx::WGPUCtx, 
+    &sfx::SfxCtx, 
+) -> Result<Box<dyn WindowHandler>, ContextInitError> + Send;
+
+/// 実行中のウィンドウの生成要求
+/// 
+/// `Context::window_spawner`で取得します。`Context::run`の開始後でも
+/// 追加のウィンドウを要求でき、要求はイベントの処理を終えた時に
+/// まとめて処理されます。
+#[derive(Clone)]
+pub struct WindowSpawner {
+    queue: Arc<Mutex<Vec<Box<SpawnFn>>>>, 
+    proxy: EventLoopProxy<()>, 
+}
+impl WindowSpawner {
+    pub(super) fn new(proxy: EventLoopProxy<()>) -> Self { Self {
+        queue: Arc::new(Mutex::new(Vec::new())), 
+        proxy, 
+    }}
+
+    /// 追加のウィンドウの生成要求
+    /// 
+    /// 引数は`Context::add_window`と同じです。
+    /// 生成に失敗した場合は`Context::run`がエラーを返して終了します。
+    /// イベントループの終了後の要求は無視されます。
+    pub fn spawn<I, F, GCd>(
+        &self, 
+        frame_initializer: I, 
+        gfx_ctx_data_init: impl FnOnce(
+            &gfx::WinitCtx, 
+            &gfx::WGPUCtx, 
+        ) -> Result<GCd, Box<dyn std::error::Error>> + Send + 'static, 
+        dupdater: impl FnMut(
+            &gfx::WinitCtx, 
+            &gfx::WGPUCtx, 
+            &mut GCd, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+        dreconfigureer: impl FnMut(
+            &gfx::WinitCtx, 
+            &gfx::WGPUCtx, 
+            &mut GCd, 
+        ) -> Result<(), Box<dyn std::error::Error>> + Send + Sync + 'static, 
+    ) where
+        I: Send + 'static, 
+        F: Frame<I, GCd> + 'static, 
+        GCd: Send + Sync + 'static, 
+    {
+        self.queue.lock().push(Box::new(move |
+            target, 
+            shared, 
+            sfx, 
+        | build_window::<I, F, GCd>(
+            target, 
+            shared, 
+            sfx, 
+            frame_initializer, 
+            gfx_ctx_data_init, 
+            dupdater, 
+            dreconfigureer, 
+        )));
+
+        // 待機中のイベントループを起こす
+        let _ = self.proxy.send_event(());
+    }
+
+    /// 溜まった生成要求の取り出し
+    pub(super) fn take(&self) -> Vec<Box<SpawnFn>> {
+        std::mem::take(&mut *self.queue.lock())
+    }
+}
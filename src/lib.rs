@@ -27,9 +27,13 @@ pub mod prelude {
         }, 
         gfx::{
             GfxCtx, 
+            GfxConfig, 
             Renderer, 
         }, 
-        sfx::SfxCtx, 
+        sfx::{
+            SfxCtx, 
+            SfxConfig, 
+        }, 
     };
     pub use crate::util::*;
 }
\ No newline at end of file
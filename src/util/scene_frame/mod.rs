@@ -73,6 +73,12 @@ pub trait Scene: Sized + Send + Sync {
         delta: MouseScrollDelta, 
     );
 
+    /// カーソル位置の入力
+    fn input_cursor_moved(
+        &mut self, 
+        _position: winit::dpi::PhysicalPosition<f64>, 
+    ) {}
+
     /// ウィンドウのリサイズ
     fn window_resizing(
         &mut self, 
@@ -217,6 +223,13 @@ impl<S> Frame<S::InitV, S::Rdr> for SceneFrame<S> where
         self.scenes.input_mouse_scroll(delta)
     }
 
+    fn input_cursor_moved(
+        &mut self, 
+        position: winit::dpi::PhysicalPosition<f64>, 
+    ) {
+        self.scenes.input_cursor_moved(position)
+    }
+
     fn window_resizing(
         &mut self, 
         size: winit::dpi::PhysicalSize<u32>, 
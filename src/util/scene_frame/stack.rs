@@ -177,6 +177,15 @@ impl<S: Scene> SceneStack<S> {
             .map(|s| s.scene.input_mouse_scroll(delta));
     }
 
+    /// カーソル位置の入力
+    pub fn input_cursor_moved(
+        &mut self, 
+        position: winit::dpi::PhysicalPosition<f64>, 
+    ) {
+        self.scenes.back_mut()
+            .map(|s| s.scene.input_cursor_moved(position));
+    }
+
     /// ウィンドウのリサイズ
     pub fn window_resizing(
         &mut self, 
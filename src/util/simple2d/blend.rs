@@ -0,0 +1,143 @@
+//! 合成方法
+//! 
+//! レンダラごとに合成方法を選択できます。パイプラインは合成方法と
+//! 深度バッファの有無の組み合わせごとに、初めて用いた時に生成されます。
+
+use once_cell::sync::OnceCell;
+
+/// 合成方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// 通常の半透明合成
+    #[default]
+    Alpha, 
+
+    /// 加算合成(不透明度を掛けて加算する)
+    Additive, 
+
+    /// 乗算合成
+    /// 
+    /// 不透明度は考慮されないため、影などには白を背景とした画像を用います。
+    Multiply, 
+
+    /// スクリーン合成
+    /// 
+    /// 不透明度は考慮されないため、黒を背景とした画像を用います。
+    Screen, 
+
+    /// 乗算済みアルファの半透明合成
+    /// 
+    /// テクスチャは色に不透明度を掛け済みである必要があります。
+    /// 読み込み時の変換は`SamplerOption::premultiply`や
+    /// `AtlasRenderingModule::insert_image_with`で行えます。
+    /// 色の乗算・加算や頂点色・文字色はシェーダで不透明度を掛けて出力します。
+    Premultiplied, 
+}
+impl BlendMode {
+    /// 全ての合成方法
+    pub const ALL: [Self; 5] = [
+        Self::Alpha, 
+        Self::Additive, 
+        Self::Multiply, 
+        Self::Screen, 
+        Self::Premultiplied, 
+    ];
+
+    /// 描画先の不透明度を変えない成分
+    const KEEP_ALPHA: wgpu::BlendComponent = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero, 
+        dst_factor: wgpu::BlendFactor::One, 
+        operation: wgpu::BlendOperation::Add, 
+    };
+
+    pub fn blend_state(self) -> wgpu::BlendState { match self {
+        Self::Alpha => wgpu::BlendState::ALPHA_BLENDING, 
+        Self::Additive => wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha, 
+                dst_factor: wgpu::BlendFactor::One, 
+                operation: wgpu::BlendOperation::Add, 
+            }, 
+            alpha: Self::KEEP_ALPHA, 
+        }, 
+        Self::Multiply => wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Dst, 
+                dst_factor: wgpu::BlendFactor::Zero, 
+                operation: wgpu::BlendOperation::Add, 
+            }, 
+            alpha: Self::KEEP_ALPHA, 
+        }, 
+        Self::Screen => wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One, 
+                dst_factor: wgpu::BlendFactor::OneMinusSrc, 
+                operation: wgpu::BlendOperation::Add, 
+            }, 
+            alpha: Self::KEEP_ALPHA, 
+        }, 
+        Self::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING, 
+    }}
+
+    /// フラグメントシェーダのエントリポイント
+    /// 
+    /// 乗算済みアルファの場合は不透明度を掛けた色を出力する`fs_premultiplied`です。
+    pub(crate) fn fragment_entry(self) -> &'static str { match self {
+        Self::Premultiplied => "fs_premultiplied", 
+        _ => "fs_main", 
+    }}
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|m| *m == self).unwrap_or(0)
+    }
+}
+
+/// パイプラインの生成処理
+/// 
+/// 合成方法と深度の設定を受け取ってパイプラインを生成します。
+type CreatePipeline = dyn Fn(
+    &wgpu::Device, 
+    BlendMode, 
+    Option<wgpu::DepthStencilState>, 
+) -> wgpu::RenderPipeline + Send + Sync;
+
+/// 合成方法ごとのパイプラインのキャッシュ
+pub(crate) struct PipelineCache {
+    pipelines: [[OnceCell<wgpu::RenderPipeline>; 2]; BlendMode::ALL.len()], 
+    create: Box<CreatePipeline>, 
+}
+impl PipelineCache {
+    /// キャッシュの生成
+    /// 
+    /// 既定の合成方法のパイプラインは生成時に作成します。
+    pub(crate) fn new(
+        device: &wgpu::Device, 
+        create: impl Fn(
+            &wgpu::Device, 
+            BlendMode, 
+            Option<wgpu::DepthStencilState>, 
+        ) -> wgpu::RenderPipeline + Send + Sync + 'static, 
+    ) -> Self {
+        let cache = Self {
+            pipelines: Default::default(), 
+            create: Box::new(create), 
+        };
+        cache.get(device, BlendMode::default(), false);
+        cache.get(device, BlendMode::default(), true);
+        cache
+    }
+
+    /// 合成方法と深度バッファの有無に応じたパイプライン
+    pub(crate) fn get(
+        &self, 
+        device: &wgpu::Device, 
+        mode: BlendMode, 
+        depth: bool, 
+    ) -> &wgpu::RenderPipeline {
+        self.pipelines[mode.index()][depth as usize].get_or_init(|| (self.create)(
+            device, 
+            mode, 
+            depth.then(super::depth::depth_stencil_state), 
+        ))
+    }
+}
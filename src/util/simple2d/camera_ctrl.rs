@@ -0,0 +1,305 @@
+//! カメラの制御
+//! 
+//! 追従・不感帯・移動範囲の制限・平滑化・複数対象への
+//! ズーム・トラウマ方式の画面揺れをまとめて扱います。
+//! `update`には`CycleMeasure::dur`を渡して毎フレーム呼び出します。
+
+use super::{
+    MAX_DT, 
+    types::Camera, 
+};
+
+/// 画面揺れの設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShakeParam {
+    /// 最大の揺れ幅(ワールド座標)
+    pub max_offset: nalgebra::Vector2<f32>, 
+
+    /// 最大の回転の揺れ(ラジアン)
+    pub max_rotation: f32, 
+
+    /// 揺れの速さ(1秒あたりの振動数)
+    pub frequency: f32, 
+
+    /// 1秒あたりのトラウマの減衰量
+    pub decay: f32, 
+}
+impl Default for ShakeParam {
+    fn default() -> Self { Self {
+        max_offset: [16., 16.].into(), 
+        max_rotation: 0.05, 
+        frequency: 15., 
+        decay: 1., 
+    }}
+}
+
+/// カメラの制御器
+#[derive(Debug, Clone)]
+pub struct CameraController {
+    /// 追従対象
+    pub target: Option<nalgebra::Point2<f32>>, 
+
+    /// 不感帯(中心からの半分の大きさ、ワールド座標)
+    pub dead_zone: nalgebra::Vector2<f32>, 
+
+    /// 移動範囲(最小点と最大点)
+    pub bounds: Option<[nalgebra::Point2<f32>; 2]>, 
+
+    /// 位置の平滑化の時定数(秒、0で即座に追従)
+    pub smoothing: f32, 
+
+    /// ズームの平滑化の時定数(秒、0で即座に追従)
+    pub zoom_smoothing: f32, 
+
+    /// ズームの範囲
+    pub zoom_range: [f32; 2], 
+
+    /// 画面揺れの設定
+    pub shake: ShakeParam, 
+
+    focus: nalgebra::Point2<f32>, 
+    goal: nalgebra::Point2<f32>, 
+    zoom: f32, 
+    goal_zoom: f32, 
+    rotation: f32, 
+    trauma: f32, 
+    time: f32, 
+}
+impl CameraController {
+    /// 現在のカメラの状態からの生成
+    pub fn new(camera: &Camera) -> Self { Self {
+        target: None, 
+        dead_zone: [0., 0.].into(), 
+        bounds: None, 
+        smoothing: 0.1, 
+        zoom_smoothing: 0.2, 
+        zoom_range: [0.1, 10.], 
+        shake: ShakeParam::default(), 
+        focus: camera.position, 
+        goal: camera.position, 
+        zoom: camera.zoom, 
+        goal_zoom: camera.zoom, 
+        rotation: camera.rotation, 
+        trauma: 0., 
+        time: 0., 
+    }}
+
+    /// 追従対象の設定
+    pub fn follow(&mut self, target: impl Into<nalgebra::Point2<f32>>) {
+        self.target = Some(target.into())
+    }
+
+    /// 位置の即時設定
+    pub fn warp(&mut self, position: impl Into<nalgebra::Point2<f32>>) {
+        let position = position.into();
+        self.focus = position;
+        self.goal = position;
+    }
+
+    /// ズームの目標値の設定
+    /// 
+    /// `zoom_range`の上下が逆でも範囲として扱います。
+    pub fn set_zoom(&mut self, zoom: f32) {
+        let [a, b] = self.zoom_range;
+        self.goal_zoom = zoom.max(a.min(b)).min(a.max(b))
+    }
+
+    /// 回転(揺れを含まない)の設定
+    pub fn set_rotation(&mut self, rotation: f32) { self.rotation = rotation }
+
+    /// 揺れを含まない注視点
+    pub fn focus(&self) -> nalgebra::Point2<f32> { self.focus }
+
+    /// 現在のトラウマ(0から1)
+    pub fn trauma(&self) -> f32 { self.trauma }
+
+    /// トラウマの追加
+    /// 
+    /// 揺れの大きさはトラウマの2乗に比例します。
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0., 1.)
+    }
+
+    /// 複数の点がすべて収まるように注視点とズームを設定する
+    /// 
+    /// `margin`は画面端との余白(ワールド座標)です。
+    /// 追従対象は解除されます。
+    pub fn zoom_to_fit<P: Into<nalgebra::Point2<f32>>>(
+        &mut self, 
+        camera: &Camera, 
+        points: impl IntoIterator<Item = P>, 
+        margin: f32, 
+    ) {
+        let mut iter = points.into_iter().map(Into::into);
+        let Some(first) = iter.next() else { return };
+        let (min, max) = iter.fold((first, first), |(min, max), p| (
+            min.inf(&p), 
+            max.sup(&p), 
+        ));
+
+        self.target = None;
+        self.goal = nalgebra::center(&min, &max);
+        let half = (max - min) * 0.5
+            + nalgebra::Vector2::new(margin, margin);
+        let zoom = (camera.size.x * 0.5 / half.x.max(f32::EPSILON))
+            .min(camera.size.y * 0.5 / half.y.max(f32::EPSILON));
+        self.set_zoom(zoom);
+    }
+
+    /// 更新
+    /// 
+    /// `dt`は前回からの経過時間(秒)です。
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let dt = dt.clamp(0., MAX_DT);
+
+        // 不感帯を考慮した追従
+        if let Some(target) = self.target {
+            for i in 0..2 {
+                let diff = target[i] - self.goal[i];
+                if self.dead_zone[i] < diff.abs() {
+                    self.goal[i] += diff - diff.signum() * self.dead_zone[i];
+                }
+            }
+        }
+
+        // 平滑化
+        self.focus += (self.goal - self.focus) * damping(self.smoothing, dt);
+        self.zoom += (self.goal_zoom - self.zoom) * damping(self.zoom_smoothing, dt);
+
+        // 移動範囲の制限
+        if let Some([min, max]) = self.bounds {
+            let half = camera.size * 0.5 / self.zoom.max(f32::EPSILON);
+            for i in 0..2 {
+                let lo = min[i] + half[i];
+                let hi = max[i] - half[i];
+                self.focus[i] = if lo <= hi {
+                    self.focus[i].clamp(lo, hi)
+                } else {
+                    (min[i] + max[i]) * 0.5
+                };
+                self.goal[i] = self.goal[i].clamp(
+                    lo.min(self.focus[i]), 
+                    hi.max(self.focus[i]), 
+                );
+            }
+        }
+
+        // 画面揺れ
+        self.time += dt;
+        self.trauma = (self.trauma - self.shake.decay * dt).max(0.);
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.shake.frequency;
+        let offset = nalgebra::Vector2::new(
+            self.shake.max_offset.x * shake * noise(t, 0.), 
+            self.shake.max_offset.y * shake * noise(t, 31.7), 
+        );
+
+        // カメラへの反映
+        camera.position = self.focus + offset;
+        camera.zoom = self.zoom;
+        camera.rotation = self.rotation
+            + self.shake.max_rotation * shake * noise(t, 73.1);
+    }
+}
+
+/// 時定数に基づく補間係数
+fn damping(time_constant: f32, dt: f32) -> f32 {
+    if time_constant <= 0. { 1. } else { 1. - (-dt / time_constant).exp() }
+}
+
+/// -1から1の範囲の滑らかな疑似ノイズ
+fn noise(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() * 0.5
+        + (t * 2.3 + seed * 1.7).sin() * 0.3
+        + (t * 4.1 + seed * 2.9).sin() * 0.2)
+        .clamp(-1., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera {
+            position: [0., 0.].into(), 
+            size: [40., 20.].into(), 
+            zoom: 1., 
+            rotation: 0., 
+        }
+    }
+
+    fn controller(camera: &Camera) -> CameraController {
+        let mut ctrl = CameraController::new(camera);
+        ctrl.smoothing = 0.;
+        ctrl.zoom_smoothing = 0.;
+        ctrl
+    }
+
+    #[test]
+    fn dead_zone() {
+        let mut cam = camera();
+        let mut ctrl = controller(&cam);
+        ctrl.dead_zone = [10., 5.].into();
+
+        // 不感帯の中では動かない
+        ctrl.follow([8., -4.]);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.position, [0., 0.].into());
+
+        // 不感帯の端まで追従する
+        ctrl.follow([25., -8.]);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.position, [15., -3.].into());
+    }
+
+    #[test]
+    fn bounds_clamp() {
+        let mut cam = camera();
+        let mut ctrl = controller(&cam);
+        ctrl.bounds = Some([[0., 0.].into(), [100., 100.].into()]);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.position, [20., 10.].into());
+
+        ctrl.warp([200., 50.]);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.position, [80., 50.].into());
+
+        // 表示範囲より狭い場合は中央に固定する
+        ctrl.bounds = Some([[0., 0.].into(), [30., 100.].into()]);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.position, [15., 50.].into());
+    }
+
+    #[test]
+    fn zoom_to_fit() {
+        let mut cam = camera();
+        let mut ctrl = controller(&cam);
+        ctrl.follow([100., 100.]);
+        ctrl.zoom_to_fit(&cam, [[-10., 2.], [10., -2.], [0., 0.]], 0.);
+        assert_eq!(ctrl.target, None);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.position, [0., 0.].into());
+        assert_eq!(cam.zoom, 2.);
+
+        // 点が無い場合は何もしない
+        ctrl.zoom_to_fit(&cam, std::iter::empty::<[f32; 2]>(), 0.);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.zoom, 2.);
+    }
+
+    #[test]
+    fn reversed_zoom_range() {
+        let mut cam = camera();
+        let mut ctrl = controller(&cam);
+        ctrl.zoom_range = [4., 0.5];
+        ctrl.set_zoom(10.);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.zoom, 4.);
+        ctrl.set_zoom(0.1);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.zoom, 0.5);
+        ctrl.set_zoom(2.);
+        ctrl.update(&mut cam, 0.1);
+        assert_eq!(cam.zoom, 2.);
+    }
+}
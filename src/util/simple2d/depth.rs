@@ -0,0 +1,141 @@
+//! 深度バッファ
+//! 
+//! 各インスタンスの`z`を深度として書き込み、異なるレンダラ間でも
+//! 前後関係が正しくなるように描画します。
+//! `z`が大きいほど手前に描画され、同じ`z`では後に描画したものが手前になります。
+//! 
+//! 完全に透明な画素は深度を書き込みませんが、半透明の画素は書き込むため、
+//! 半透明のものを重ねる場合は奥から順に描画してください。
+
+/// 深度バッファのフォーマット
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// `z`として扱える範囲の上限(`-Z_MAX`から`Z_MAX`まで)
+/// 
+/// シェーダ側の値と一致させる必要があります。
+pub const Z_MAX: f32 = 1024.;
+
+/// 深度バッファ
+/// 
+/// `S2DCamera::set_depth_buffer`で設定したカメラを通した描画で用いられます。
+/// 描画先と同じ大きさで作成し、フレームの初めに`clear`してください。
+pub struct DepthBuffer {
+    texture: wgpu::Texture, 
+    view: wgpu::TextureView, 
+    size: [u32; 2], 
+}
+impl DepthBuffer {
+    pub fn new(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        width: u32, 
+        height: u32, 
+    ) -> Self {
+        let size = [width.max(1), height.max(1)];
+        let (texture, view) = Self::create_target(gfx, size);
+        Self {
+            texture, 
+            view, 
+            size, 
+        }
+    }
+
+    fn create_target(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        size: [u32; 2], 
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = gfx.device.create_texture(
+            &wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: size[0], 
+                    height: size[1], 
+                    depth_or_array_layers: 1, 
+                }, 
+                mip_level_count: 1, 
+                sample_count: 1, 
+                dimension: wgpu::TextureDimension::D2, 
+                format: DEPTH_FORMAT, 
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT, 
+                label: Some("depth buffer texture"), 
+                view_formats: &[], 
+            }
+        );
+        let view = texture.create_view(
+            &wgpu::TextureViewDescriptor::default()
+        );
+        (texture, view)
+    }
+
+    /// 深度バッファのビュー
+    pub fn view(&self) -> &wgpu::TextureView { &self.view }
+
+    /// 生のテクスチャ
+    pub fn raw_texture(&self) -> &wgpu::Texture { &self.texture }
+
+    /// 大きさ(ピクセル)
+    pub fn size(&self) -> [u32; 2] { self.size }
+
+    /// 大きさの変更
+    /// 
+    /// 内容は破棄されます。
+    pub fn resize(
+        &mut self, 
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        width: u32, 
+        height: u32, 
+    ) {
+        let size = [width.max(1), height.max(1)];
+        if size == self.size { return }
+        let (texture, view) = Self::create_target(gfx, size);
+        self.texture = texture;
+        self.view = view;
+        self.size = size;
+    }
+
+    /// 深度の初期化(最も奥の値で埋める)
+    pub fn clear(
+        &self, 
+        encoder: &mut wgpu::CommandEncoder, 
+    ) {
+        encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("depth buffer clear pass"), 
+                color_attachments: &[], 
+                depth_stencil_attachment: Some(
+                    wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.view, 
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.), 
+                            store: true, 
+                        }), 
+                        stencil_ops: None, 
+                    }
+                ), 
+            }
+        );
+    }
+
+    /// 描画パスで用いる深度のアタッチメント
+    pub(crate) fn attachment(
+        &self, 
+    ) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.view, 
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Load, 
+                store: true, 
+            }), 
+            stencil_ops: None, 
+        }
+    }
+}
+
+/// 深度バッファを用いるパイプラインの深度の設定
+pub(crate) fn depth_stencil_state() -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format: DEPTH_FORMAT, 
+        depth_write_enabled: true, 
+        depth_compare: wgpu::CompareFunction::LessEqual, 
+        stencil: wgpu::StencilState::default(), 
+        bias: wgpu::DepthBiasState::default(), 
+    }
+}
@@ -1,5 +1,4 @@
 use wgpu::{
-    Buffer, 
     VertexAttribute, 
     VertexBufferLayout, 
     VertexStepMode, 
@@ -15,12 +14,17 @@ use super::{
         InstanceGen, 
         InstanceRaw, 
         buffer::InstanceArray, 
+        batch::StaticBatchSet, 
     }, 
     shared::{
         S2DCamera, 
         ImagedShared, 
         SquareShared, 
     }, 
+    blend::{
+        BlendMode, 
+        PipelineCache, 
+    }, 
     types::Texture, 
     raw::{
         TexedVertex, 
@@ -37,14 +41,20 @@ pub struct ImgObjInstanceRaw {
     pub rotation: [f32; 2], 
     pub tex_coord: [f32; 2], 
     pub tex_size: [f32; 2], 
+    pub z: f32, 
+    pub tint: [f32; 4], 
+    pub flash: [f32; 4], 
 }
 impl ImgObjInstanceRaw {
-    const ATTRIBS: [VertexAttribute; 5] = vertex_attr_array![
+    const ATTRIBS: [VertexAttribute; 8] = vertex_attr_array![
         5 => Float32x2, 
         6 => Float32x2, 
         7 => Float32x2, 
         8 => Float32x2, 
         9 => Float32x2, 
+        10 => Float32, 
+        11 => Float32x4, 
+        12 => Float32x4, 
     ];
 }
 impl InstanceRaw for ImgObjInstanceRaw {
@@ -66,6 +76,29 @@ pub struct ImgObjInstance {
     pub tex_coord: [f32; 2], 
     pub tex_size: [f32; 2], 
     pub tex_rev: [bool; 2], 
+
+    /// 奥行き(大きいほど手前)
+    pub z: f32, 
+
+    /// 乗算する色(`[1., 1., 1., 1.]`でそのまま、wは不透明度)
+    pub tint: [f32; 4], 
+
+    /// 加算する色(wは加算する強さ、`[0., 0., 0., 0.]`で加算なし)
+    pub flash: [f32; 4], 
+}
+impl Default for ImgObjInstance {
+    /// 不透明な白の`tint`・加算なしの`flash`で、その他は0
+    fn default() -> Self { Self {
+        position: [0., 0.], 
+        size: [0., 0.], 
+        rotation: 0., 
+        tex_coord: [0., 0.], 
+        tex_size: [0., 0.], 
+        tex_rev: [false, false], 
+        z: 0., 
+        tint: [1., 1., 1., 1.], 
+        flash: [0., 0., 0., 0.], 
+    }}
 }
 impl Instance<Texture> for ImgObjInstance {
     type Raw = ImgObjInstanceRaw;
@@ -93,9 +126,14 @@ impl Instance<Texture> for ImgObjInstance {
             size, 
             rotation, 
             tex_coord, 
-            tex_size 
+            tex_size, 
+            z: self.z, 
+            tint: self.tint, 
+            flash: self.flash, 
         }
     }
+
+    fn z(&self) -> f32 { self.z }
     
 }
 impl InstanceGen<Texture, ImgObjInstance> for ImgObjInstance {
@@ -112,9 +150,19 @@ impl InstanceGen<Texture, ImgObjInstance> for ImgObjInstance {
 
 /// 画像を使ったオブジェクトの描画構造体で共有される値
 pub struct ImgObjRenderShared {
-    pipeline: RenderPipeline, 
+    pipelines: PipelineCache, 
 }
 impl ImgObjRenderShared {
+    /// 合成方法と深度バッファの有無に応じたパイプライン
+    pub(crate) fn pipeline(
+        &self, 
+        device: &wgpu::Device, 
+        mode: BlendMode, 
+        depth: bool, 
+    ) -> &RenderPipeline {
+        self.pipelines.get(device, mode, depth)
+    }
+
     pub fn new(
         gfx: &crate::ctx::gfx::WGPUCtx, 
         camera: &S2DCamera, 
@@ -143,7 +191,12 @@ impl ImgObjRenderShared {
         );
 
         // パイプラインの初期化
-        let pipeline = gfx.device.create_render_pipeline(
+        let format = gfx.config.format;
+        let pipelines = PipelineCache::new(&gfx.device, move |
+            device: &wgpu::Device, 
+            blend: BlendMode, 
+            depth_stencil: Option<wgpu::DepthStencilState>, 
+        | device.create_render_pipeline(
             &RenderPipelineDescriptor {
                 label: Some("sample pipeline"), 
                 layout: Some(&pipeline_layout), 
@@ -157,10 +210,10 @@ impl ImgObjRenderShared {
                 }, 
                 fragment: Some(wgpu::FragmentState {
                     module: &shader, 
-                    entry_point: "fs_main", 
+                    entry_point: blend.fragment_entry(), 
                     targets: &[Some(wgpu::ColorTargetState { 
-                        format: gfx.config.format, 
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING), 
+                        format, 
+                        blend: Some(blend.blend_state()), 
                         write_mask: wgpu::ColorWrites::all() 
                     })]
                 }), 
@@ -173,7 +226,7 @@ impl ImgObjRenderShared {
                     polygon_mode: wgpu::PolygonMode::Fill, 
                     conservative: false, 
                 }, 
-                depth_stencil: None, 
+                depth_stencil, 
                 multisample: wgpu::MultisampleState {
                     count: 1, 
                     mask: !0, 
@@ -181,10 +234,10 @@ impl ImgObjRenderShared {
                 }, 
                 multiview: None, 
             }
-        );
+        ));
 
         Self {
-            pipeline, 
+            pipelines, 
         }
     }
 }
@@ -193,7 +246,8 @@ impl ImgObjRenderShared {
 pub struct ImgObjRender {
     texture: Texture, 
     instances: InstanceArray<Texture, ImgObjInstance>, 
-    instance_buffer: Buffer, 
+    batches: StaticBatchSet<Texture, ImgObjInstance>, 
+    blend: BlendMode, 
 }
 impl ImgObjRender {
     pub fn new<C: std::ops::Deref<Target = [u8]>>(
@@ -212,12 +266,13 @@ impl ImgObjRender {
         let mut instances = InstanceArray::new();
 
         // インスタンスバッファの初期化
-        let instance_buffer = instances.finish(gfx, &texture);
+        instances.finish(gfx, &texture);
 
         Self {
             texture, 
             instances, 
-            instance_buffer, 
+            batches: StaticBatchSet::new(), 
+            blend: BlendMode::default(), 
         }
     }
 
@@ -237,15 +292,37 @@ impl ImgObjRender {
         let mut instances = InstanceArray::new();
 
         // インスタンスバッファの初期化
-        let instance_buffer = instances.finish(gfx, &texture);
+        instances.finish(gfx, &texture);
 
         Ok(Self {
             texture, 
             instances, 
-            instance_buffer, 
+            batches: StaticBatchSet::new(), 
+            blend: BlendMode::default(), 
         })
     }
 
+    /// 生成済みのテクスチャからの生成
+    /// 
+    /// `RenderTarget::create_texture`の結果を描画する場合などに用います。
+    pub fn from_texture(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        texture: Texture, 
+    ) -> Self {
+        // インスタンスの生成
+        let mut instances = InstanceArray::new();
+
+        // インスタンスバッファの初期化
+        instances.finish(gfx, &texture);
+
+        Self {
+            texture, 
+            instances, 
+            batches: StaticBatchSet::new(), 
+            blend: BlendMode::default(), 
+        }
+    }
+
     /// テクスチャの更新用の可変参照の取得
     pub fn get_texture(&mut self) -> &mut Texture { &mut self.texture }
 
@@ -256,6 +333,17 @@ impl ImgObjRender {
     ) {
         instance.generate(&mut self.instances);
     }
+
+    /// 固定のインスタンスの集まりの一覧
+    pub fn batches(&mut self) -> &mut StaticBatchSet<Texture, ImgObjInstance> {
+        &mut self.batches
+    }
+
+    /// 合成方法
+    pub fn blend_mode(&self) -> BlendMode { self.blend }
+
+    /// 合成方法の設定
+    pub fn set_blend_mode(&mut self, mode: BlendMode) { self.blend = mode }
 }
 impl<GCd: Send + Sync> super::Simple2DRender<GCd> for ImgObjRender {
     type Shared<'a> = (
@@ -272,41 +360,78 @@ impl<GCd: Send + Sync> super::Simple2DRender<GCd> for ImgObjRender {
         camera: &S2DCamera, 
         shared: Self::Shared<'a>, 
     ) {
-        self.instance_buffer = self.instances.finish(
+        self.rendering_multi(gfx, encoder, view, &[camera], shared)
+    }
+
+    fn rendering_multi<'a>(
+        &mut self, 
+        gfx: &crate::ctx::gfx::GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        cameras: &[&S2DCamera], 
+        shared: Self::Shared<'a>, 
+    ) where
+        Self::Shared<'a>: Clone, 
+    {
+        self.instances.finish(
             &gfx.wgpu_ctx, 
             &self.texture
         );
+        let Some(instance_buffer) = self.instances.buffer() else { return };
+        self.batches.upload(&gfx.wgpu_ctx, &self.texture);
+
+        // カメラごとに描画領域を切り替えて描画
+        for camera in cameras {
+            let mut render_pass = encoder.begin_render_pass(
+                &wgpu::RenderPassDescriptor {
+                    label: Some("render pass"), 
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
+                        view, 
+                        resolve_target: None, 
+                        ops: wgpu::Operations { 
+                            load: wgpu::LoadOp::Load, 
+                            store: true 
+                        } 
+                    })], 
+                    depth_stencil_attachment: camera.depth_attachment(), 
+                }
+            );
+            camera.apply_viewport(&mut render_pass);
+
+            render_pass.set_pipeline(
+                shared.2.pipeline(
+                    &gfx.wgpu_ctx.device, 
+                    self.blend, 
+                    camera.depth.is_some(), 
+                )
+            );
+            render_pass.set_bind_group(0, &self.texture.bind_group, &[]);
+            render_pass.set_bind_group(1, &camera.bg, &[]);
+            render_pass.set_vertex_buffer(
+                0, shared.0.vertex.slice(..)
+            );
+            render_pass.set_index_buffer(
+                shared.0.index.slice(..), wgpu::IndexFormat::Uint16
+            );
 
-        let mut render_pass = encoder.begin_render_pass(
-            &wgpu::RenderPassDescriptor {
-                label: Some("render pass"), 
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
-                    view, 
-                    resolve_target: None, 
-                    ops: wgpu::Operations { 
-                        load: wgpu::LoadOp::Load, 
-                        store: true 
-                    } 
-                })], 
-                depth_stencil_attachment: None, 
+            // 固定のインスタンスの描画
+            for (buffer, len) in self.batches.buffers() {
+                render_pass.set_vertex_buffer(1, buffer.slice(..));
+                render_pass.draw_indexed(
+                    0..INDICES.len() as _, 
+                    0, 
+                    0..len
+                );
             }
-        );
 
-        render_pass.set_pipeline(&shared.2.pipeline);
-        render_pass.set_bind_group(0, &self.texture.bind_group, &[]);
-        render_pass.set_bind_group(1, &camera.bg, &[]);
-        render_pass.set_vertex_buffer(
-            0, shared.0.vertex.slice(..)
-        );
-        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        render_pass.set_index_buffer(
-            shared.0.index.slice(..), wgpu::IndexFormat::Uint16
-        );
-        render_pass.draw_indexed(
-            0..INDICES.len() as _, 
-            0, 
-            0..self.instances.len() as _
-        );
+            // 毎フレームのインスタンスの描画
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.draw_indexed(
+                0..INDICES.len() as _, 
+                0, 
+                0..self.instances.len() as _
+            );
+        }
     }
     
 }
\ No newline at end of file
@@ -0,0 +1,216 @@
+//! 固定のインスタンスの集まり
+//! 
+//! 背景のタイルなど毎フレーム変わらないインスタンスを一度だけ転送し、
+//! 変更があった範囲だけを再転送します。
+
+use super::Instance;
+
+/// 固定のインスタンスの集まり
+/// 
+/// 追加順のまま描画されるため、奥行きで前後関係を決める場合は
+/// 深度バッファを用いてください。
+pub struct StaticBatch<V, I: Instance<V> + Clone> {
+    _dummy: std::marker::PhantomData<V>, 
+    instances: Vec<I>, 
+    raw: Vec<I::Raw>, 
+    buffer: Option<wgpu::Buffer>, 
+    capacity: usize, 
+    dirty: Option<std::ops::Range<usize>>, 
+}
+impl<V, I: Instance<V> + Clone> StaticBatch<V, I> {
+    pub fn new() -> Self { Self {
+        _dummy: std::marker::PhantomData, 
+        instances: Vec::new(), 
+        raw: Vec::new(), 
+        buffer: None, 
+        capacity: 0, 
+        dirty: None, 
+    }}
+
+    /// インスタンスの追加
+    /// 
+    /// 追加したインスタンスの番号を返します。
+    pub fn push(&mut self, instance: I) -> usize {
+        let index = self.instances.len();
+        self.instances.push(instance);
+        self.mark(index..index + 1);
+        index
+    }
+
+    /// 番号を指定したインスタンスの置き換え
+    /// 
+    /// 番号が範囲外の場合は`instance`をそのまま返します。
+    pub fn set(&mut self, index: usize, instance: I) -> Result<(), I> {
+        match self.instances.get_mut(index) {
+            Some(i) => {
+                *i = instance;
+                self.mark(index..index + 1);
+                Ok(())
+            }, 
+            None => Err(instance), 
+        }
+    }
+
+    /// 番号を指定したインスタンスの変更
+    pub fn modify<R>(
+        &mut self, 
+        index: usize, 
+        f: impl FnOnce(&mut I) -> R, 
+    ) -> Option<R> {
+        let r = self.instances.get_mut(index).map(f)?;
+        self.mark(index..index + 1);
+        Some(r)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&I> { self.instances.get(index) }
+
+    pub fn len(&self) -> usize { self.instances.len() }
+
+    pub fn is_empty(&self) -> bool { self.instances.is_empty() }
+
+    /// 全インスタンスの削除
+    pub fn clear(&mut self) {
+        self.instances.clear();
+        self.raw.clear();
+        self.dirty = None;
+    }
+
+    /// 全インスタンスの再転送の要求
+    /// 
+    /// テクスチャやアトラスの変更など、インスタンスの変換に用いる値が
+    /// 変わった場合に呼び出します。
+    pub fn invalidate(&mut self) {
+        self.mark(0..self.instances.len())
+    }
+
+    fn mark(&mut self, range: std::ops::Range<usize>) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(d) => d.start.min(range.start)..d.end.max(range.end), 
+            None => range, 
+        });
+    }
+
+    /// 変更された範囲のGPUへの転送
+    pub(crate) fn upload(
+        &mut self, 
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        value: &V, 
+    ) {
+        let Some(dirty) = self.dirty.take() else { return };
+        let len = self.instances.len();
+
+        // 生のインスタンスへの変換
+        self.raw.resize(len, bytemuck::Zeroable::zeroed());
+        let dirty = dirty.start.min(len)..dirty.end.min(len);
+        for i in dirty.clone() {
+            self.raw[i] = self.instances[i].clone().as_raw(value);
+        }
+
+        // 容量が足りない場合は作り直して全体を転送する
+        let dirty = if self.buffer.is_none() || self.capacity < len {
+            let capacity = len.next_power_of_two().max(1);
+            self.buffer = Some(gfx.device.create_buffer(
+                &wgpu::BufferDescriptor {
+                    label: Some("static batch buffer"), 
+                    size: (capacity * std::mem::size_of::<I::Raw>()) as wgpu::BufferAddress, 
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST, 
+                    mapped_at_creation: false, 
+                }
+            ));
+            self.capacity = capacity;
+            0..len
+        } else {
+            dirty
+        };
+
+        if let Some(buffer) = &self.buffer {
+            if !dirty.is_empty() {
+                gfx.queue.write_buffer(
+                    buffer, 
+                    (dirty.start * std::mem::size_of::<I::Raw>()) as wgpu::BufferAddress, 
+                    bytemuck::cast_slice(&self.raw[dirty]), 
+                );
+            }
+        }
+    }
+
+    /// GPU上のインスタンスバッファ
+    pub(crate) fn buffer(&self) -> Option<&wgpu::Buffer> {
+        if self.raw.is_empty() { None } else { self.buffer.as_ref() }
+    }
+
+    /// 転送済みのインスタンス数
+    pub(crate) fn uploaded_len(&self) -> usize { self.raw.len() }
+}
+impl<V, I: Instance<V> + Clone> Default for StaticBatch<V, I> {
+    fn default() -> Self { Self::new() }
+}
+impl<V, I: Instance<V> + Clone> FromIterator<I> for StaticBatch<V, I> {
+    fn from_iter<T: IntoIterator<Item = I>>(iter: T) -> Self {
+        let mut batch = Self::new();
+        iter.into_iter().for_each(|i| { batch.push(i); });
+        batch
+    }
+}
+
+/// レンダラが保持する固定のインスタンスの集まりの一覧
+/// 
+/// 各集まりは追加順に、毎フレームのインスタンスより先に描画されます。
+pub struct StaticBatchSet<V, I: Instance<V> + Clone> {
+    batches: Vec<Option<StaticBatch<V, I>>>, 
+}
+impl<V, I: Instance<V> + Clone> StaticBatchSet<V, I> {
+    pub fn new() -> Self { Self {
+        batches: Vec::new(), 
+    }}
+
+    /// 集まりの追加
+    /// 
+    /// 集まりの番号を返します。
+    pub fn insert(&mut self, batch: StaticBatch<V, I>) -> usize {
+        match self.batches.iter().position(Option::is_none) {
+            Some(id) => {
+                self.batches[id] = Some(batch);
+                id
+            }, 
+            None => {
+                self.batches.push(Some(batch));
+                self.batches.len() - 1
+            }, 
+        }
+    }
+
+    /// 集まりの削除
+    pub fn remove(&mut self, id: usize) -> Option<StaticBatch<V, I>> {
+        self.batches.get_mut(id)?.take()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&StaticBatch<V, I>> {
+        self.batches.get(id)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut StaticBatch<V, I>> {
+        self.batches.get_mut(id)?.as_mut()
+    }
+
+    /// 全ての集まりの転送
+    pub(crate) fn upload(
+        &mut self, 
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        value: &V, 
+    ) {
+        self.batches.iter_mut()
+            .flatten()
+            .for_each(|b| b.upload(gfx, value))
+    }
+
+    /// 描画するバッファとインスタンス数の一覧
+    pub(crate) fn buffers(&self) -> impl Iterator<Item = (&wgpu::Buffer, u32)> {
+        self.batches.iter()
+            .flatten()
+            .filter_map(|b| b.buffer().map(|buf| (buf, b.uploaded_len() as u32)))
+    }
+}
+impl<V, I: Instance<V> + Clone> Default for StaticBatchSet<V, I> {
+    fn default() -> Self { Self::new() }
+}
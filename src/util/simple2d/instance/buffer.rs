@@ -1,27 +1,74 @@
-use wgpu::util::DeviceExt;
-
 use super::Instance;
 
+/// GPU上のインスタンスバッファの最小の容量(インスタンス数)
+const MIN_CAPACITY: usize = 16;
+
 pub(crate) struct RawInstanceBuffer<V, I: Instance<V>> {
     pub(crate) instances: Vec<I::Raw>, 
+    staging: Vec<I::Raw>, 
+    buffer: Option<wgpu::Buffer>, 
+    capacity: usize, 
+    dirty: bool, 
 }
 impl<V, I: Instance<V>> RawInstanceBuffer<V, I> {
     pub fn new() -> Self { Self {
         instances: Vec::new(),
+        staging: Vec::new(), 
+        buffer: None, 
+        capacity: 0, 
+        dirty: true, 
     }}
 
-    pub fn gen_buffer(
+    /// 新しいインスタンスの反映
+    /// 
+    /// 前回と内容が同じ場合は転送を行いません。
+    fn replace(&mut self) {
+        if bytemuck::cast_slice::<_, u8>(&self.staging)
+            != bytemuck::cast_slice::<_, u8>(&self.instances)
+        {
+            std::mem::swap(&mut self.instances, &mut self.staging);
+            self.dirty = true;
+        }
+        self.staging.clear();
+    }
+
+    /// GPU上のバッファへの転送
+    /// 
+    /// 容量が足りない場合のみバッファを作り直します。
+    pub fn upload(
         &mut self, 
         gfx: &crate::ctx::gfx::WGPUCtx, 
-    ) -> wgpu::Buffer {
-        gfx.device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("instance buffer"),
-                contents: bytemuck::cast_slice(self.instances.as_slice()),
-                usage: wgpu::BufferUsages::VERTEX,
+    ) {
+        let len = self.instances.len();
+        if self.buffer.is_none() || self.capacity < len {
+            let capacity = len.next_power_of_two().max(MIN_CAPACITY);
+            self.buffer = Some(gfx.device.create_buffer(
+                &wgpu::BufferDescriptor {
+                    label: Some("instance buffer"),
+                    size: (capacity * std::mem::size_of::<I::Raw>()) as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false, 
+                }
+            ));
+            self.capacity = capacity;
+            self.dirty = true;
+        }
+
+        if self.dirty {
+            if let Some(buffer) = &self.buffer {
+                if 0 < len {
+                    gfx.queue.write_buffer(
+                        buffer, 
+                        0, 
+                        bytemuck::cast_slice(self.instances.as_slice()), 
+                    );
+                }
             }
-        )
+            self.dirty = false;
+        }
     }
+
+    pub fn buffer(&self) -> Option<&wgpu::Buffer> { self.buffer.as_ref() }
 }
 
 pub(crate) struct InstanceBuffer<V, I: Instance<V>> {
@@ -41,11 +88,18 @@ impl<V, I: Instance<V>> InstanceBuffer<V, I> {
         ria: &mut RawInstanceBuffer<V, I>, 
         value: &V, 
     ) {
-        ria.instances.clear();
+        // 奥から順に並べる(同じ奥行きでは追加順を保つ)
+        self.buffer.sort_by(|a, b| {
+            let a = a.as_ref().map_or(0., I::z);
+            let b = b.as_ref().map_or(0., I::z);
+            a.total_cmp(&b)
+        });
+
         self.buffer.iter_mut()
             .filter_map(|v| v.take())
-            .for_each(|i| ria.instances.push(i.as_raw(value)));
+            .for_each(|i| ria.staging.push(i.as_raw(value)));
         self.buffer.clear();
+        ria.replace();
     }
 }
 
@@ -63,14 +117,23 @@ impl<V, I: Instance<V>> InstanceArray<V, I> {
         self.bake.push(instance)
     }
 
+    /// 追加されたインスタンスの確定とGPUへの転送
+    /// 
+    /// GPU上のバッファは使い回され、内容が変わらない場合は転送しません。
     pub fn finish(
         &mut self, 
         gfx: &crate::ctx::gfx::WGPUCtx, 
         value: &V, 
-    ) -> wgpu::Buffer {
+    ) {
         self.bake.finish(&mut self.raw, value);
-        self.raw.gen_buffer(gfx)
+        self.raw.upload(gfx)
     }
 
+    /// GPU上のインスタンスバッファ
+    /// 
+    /// 一度も`finish`していない場合は`None`を返します。
+    /// 容量は確定したインスタンス数より大きい場合があります。
+    pub fn buffer(&self) -> Option<&wgpu::Buffer> { self.raw.buffer() }
+
     pub fn len(&self) -> usize { self.raw.instances.len() }
 }
\ No newline at end of file
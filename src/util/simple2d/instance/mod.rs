@@ -1,4 +1,5 @@
 pub mod buffer;
+pub mod batch;
 
 pub trait InstanceRaw where
     Self: Send + Sync + Sized + Copy + bytemuck::Pod + bytemuck::Zeroable
@@ -10,6 +11,9 @@ pub trait Instance<V>: Send + Sync + Sized {
     type Raw: InstanceRaw;
 
     fn as_raw(self, value: &V) -> Self::Raw;
+
+    /// 描画順を決める奥行き(大きいほど手前)
+    fn z(&self) -> f32 { 0. }
 }
 
 pub trait InstanceGen<V, I: Instance<V>>: Send + Sync {
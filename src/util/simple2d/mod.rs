@@ -2,6 +2,11 @@ pub mod instance;
 pub mod raw;
 pub mod types;
 pub mod shared;
+pub mod render_target;
+pub mod camera_ctrl;
+pub mod depth;
+pub mod blend;
+pub mod particle;
 
 /// レンダラ
 pub mod img_obj;
@@ -11,13 +16,37 @@ pub use instance::{
     Instance, 
     InstanceGen, 
     buffer::InstanceArray, 
+    batch::{
+        StaticBatch, 
+        StaticBatchSet, 
+    }, 
+};
+pub use types::{
+    Camera, 
+    SamplerOption, 
+    premultiply_alpha, 
 };
-pub use types::Camera;
 pub use shared::{
     ImagedShared, 
     SquareShared, 
     S2DCamera, 
 };
+pub use render_target::RenderTarget;
+pub use depth::DepthBuffer;
+pub use blend::BlendMode;
+pub use particle::{
+    EmitterParam, 
+    ParticleEmitter, 
+};
+pub use camera_ctrl::{
+    CameraController, 
+    ShakeParam, 
+};
+
+/// 1フレームとして扱う経過時間の上限(秒)
+/// 
+/// アニメーション・粒子・カメラの更新は、これを超える経過時間を切り詰めます。
+pub const MAX_DT: f32 = 0.25;
 
 pub mod entity_holder;
 pub mod physic;
@@ -37,4 +66,25 @@ pub trait Simple2DRender<GCd> where
         camera: &shared::S2DCamera, 
         shared: Self::Shared<'a>, 
     );
+
+    /// 複数のカメラを通した描画
+    /// 
+    /// 同じインスタンスを各カメラの描画領域へ描画します。
+    /// 既定の実装は`rendering`を繰り返し呼び出すため、描画のたびに
+    /// インスタンスを消費するレンダラはこれを上書きしてください。
+    /// `Shared`が`Clone`の場合のみ呼び出せます。上書きする場合も同じ`where`句を付けてください。
+    fn rendering_multi<'a>(
+        &mut self, 
+        gfx: &crate::ctx::gfx::GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        cameras: &[&shared::S2DCamera], 
+        shared: Self::Shared<'a>, 
+    ) where
+        Self::Shared<'a>: Clone, 
+    {
+        for camera in cameras {
+            self.rendering(gfx, encoder, view, camera, shared.clone())
+        }
+    }
 }
\ No newline at end of file
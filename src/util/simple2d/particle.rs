@@ -0,0 +1,432 @@
+//! パーティクル
+//! 
+//! CPUで粒子の移動を計算し、`SqObjRender`または`AtlasRenderer`の
+//! インスタンスとして描画します。
+//! `update`には`CycleMeasure::dur`を渡して毎フレーム呼び出し、
+//! `push_instance`にエミッタを渡して描画します。
+//! 乱数は種から決まるため、同じ種と経過時間からは同じ結果が得られます。
+
+use std::hash::Hash;
+
+use super::instance::{
+    InstanceGen, 
+    buffer::InstanceArray, 
+};
+use super::MAX_DT;
+use super::square_obj::SqObjInstance;
+use super::util::atlas::AtlasController;
+use super::util::rdr_atlas_obj::{
+    AtlasElemParam, 
+    AtlasRenderingModule, 
+    instance::AtlasObjInstance, 
+};
+
+/// 種を指定できる乱数生成器(xorshift64*)
+#[derive(Debug, Clone)]
+pub struct ParticleRng {
+    state: u64, 
+}
+impl ParticleRng {
+    pub fn new(seed: u64) -> Self {
+        // 0の状態からは0しか生成されないため種を攪拌する
+        let mut rng = Self { state: seed ^ 0x9E37_79B9_7F4A_7C15 };
+        if rng.state == 0 { rng.state = 1 }
+        rng.next_u64();
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// 0以上1未満の値
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// 範囲内の値
+    pub fn range(&mut self, range: [f32; 2]) -> f32 {
+        range[0] + (range[1] - range[0]) * self.next_f32()
+    }
+}
+
+/// 寿命に対する値の変化
+/// 
+/// キーは`(寿命に対する割合, 値)`で、割合の順に並べます。
+/// キーの間は線形に補間され、範囲外は端のキーの値になります。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<const N: usize> {
+    pub keys: Vec<(f32, [f32; N])>, 
+}
+impl<const N: usize> Curve<N> {
+    /// 一定の値
+    pub fn constant(value: [f32; N]) -> Self { Self {
+        keys: vec![(0., value)], 
+    }}
+
+    /// 始めから終わりまでの線形な変化
+    pub fn linear(start: [f32; N], end: [f32; N]) -> Self { Self {
+        keys: vec![(0., start), (1., end)], 
+    }}
+
+    /// キーの追加
+    pub fn key(mut self, t: f32, value: [f32; N]) -> Self {
+        let i = self.keys.partition_point(|(k, _)| *k <= t);
+        self.keys.insert(i, (t, value));
+        self
+    }
+
+    /// 寿命に対する割合`t`での値
+    pub fn sample(&self, t: f32) -> [f32; N] {
+        let i = self.keys.partition_point(|(k, _)| *k <= t);
+        match (i.checked_sub(1).and_then(|i| self.keys.get(i)), self.keys.get(i)) {
+            (Some(&(t0, a)), Some(&(t1, b))) => {
+                let r = if t1 - t0 <= 0. { 0. } else { (t - t0) / (t1 - t0) };
+                std::array::from_fn(|n| a[n] + (b[n] - a[n]) * r)
+            }, 
+            (Some(&(_, v)), None) | (None, Some(&(_, v))) => v, 
+            (None, None) => [0.; N], 
+        }
+    }
+}
+
+/// アトラスのフレーム
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleFrame {
+    /// アトラスでの画像の番号
+    pub atlas_id: usize, 
+
+    /// テクスチャ内部での座標(ピクセル)
+    pub tex_coord: nalgebra::Point2<f32>, 
+
+    /// テクスチャ内部での大きさ(ピクセル)
+    pub tex_size: nalgebra::Vector2<f32>, 
+}
+
+/// エミッタの設定
+#[derive(Debug, Clone)]
+pub struct EmitterParam {
+    /// 1秒あたりの発生数
+    pub rate: f32, 
+
+    /// 寿命の範囲(秒)
+    pub lifetime: [f32; 2], 
+
+    /// 発生位置の範囲(エミッタの位置を中心とした半分の大きさ)
+    pub spawn_area: nalgebra::Vector2<f32>, 
+
+    /// 発射方向(ラジアン)
+    pub direction: f32, 
+
+    /// 発射方向のばらつき(ラジアン、両側)
+    pub spread: f32, 
+
+    /// 速さの範囲
+    pub speed: [f32; 2], 
+
+    /// 加速度
+    pub gravity: nalgebra::Vector2<f32>, 
+
+    /// 1秒あたりの速度の減衰率(0で減衰なし)
+    pub drag: f32, 
+
+    /// 初期の回転の範囲(ラジアン)
+    pub rotation: [f32; 2], 
+
+    /// 角速度の範囲(ラジアン毎秒)
+    pub angular_velocity: [f32; 2], 
+
+    /// 色の変化
+    pub color: Curve<4>, 
+
+    /// 大きさの変化
+    pub size: Curve<2>, 
+
+    /// アトラスのフレームの変化
+    /// 
+    /// 寿命を等分して順に表示します。`AtlasRenderer`で描画する場合に用います。
+    pub frames: Vec<ParticleFrame>, 
+
+    /// 奥行き(大きいほど手前)
+    pub z: f32, 
+}
+impl Default for EmitterParam {
+    fn default() -> Self { Self {
+        rate: 10., 
+        lifetime: [1., 1.], 
+        spawn_area: nalgebra::Vector2::zeros(), 
+        direction: std::f32::consts::FRAC_PI_2, 
+        spread: std::f32::consts::PI, 
+        speed: [50., 100.], 
+        gravity: nalgebra::Vector2::zeros(), 
+        drag: 0., 
+        rotation: [0., 0.], 
+        angular_velocity: [0., 0.], 
+        color: Curve::linear([1., 1., 1., 1.], [1., 1., 1., 0.]), 
+        size: Curve::constant([8., 8.]), 
+        frames: Vec::new(), 
+        z: 0., 
+    }}
+}
+
+/// 粒子
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: nalgebra::Point2<f32>, 
+    pub velocity: nalgebra::Vector2<f32>, 
+    pub rotation: f32, 
+    pub angular_velocity: f32, 
+
+    /// 経過時間(秒)
+    pub age: f32, 
+
+    /// 寿命(秒)
+    pub lifetime: f32, 
+}
+impl Particle {
+    /// 寿命に対する経過の割合
+    pub fn progress(&self) -> f32 {
+        if self.lifetime <= 0. { 1. } else { (self.age / self.lifetime).min(1.) }
+    }
+}
+
+/// エミッタ
+pub struct ParticleEmitter {
+    pub param: EmitterParam, 
+
+    /// 発生位置(ワールド座標)
+    pub position: nalgebra::Point2<f32>, 
+
+    /// 連続して発生させるかどうか
+    pub emitting: bool, 
+
+    particles: Vec<Particle>, 
+    capacity: Option<usize>, 
+    spawn_acc: f32, 
+    rng: ParticleRng, 
+}
+impl ParticleEmitter {
+    /// 上限なしの生成
+    /// 
+    /// 粒子数に上限は無く、寿命が長く発生数が多い設定では際限なく増えます。
+    /// 上限が必要な場合は`with_capacity`を用います。
+    pub fn new(
+        param: EmitterParam, 
+        position: impl Into<nalgebra::Point2<f32>>, 
+        seed: u64, 
+    ) -> Self { Self {
+        param, 
+        position: position.into(), 
+        emitting: true, 
+        particles: Vec::new(), 
+        capacity: None, 
+        spawn_acc: 0., 
+        rng: ParticleRng::new(seed), 
+    }}
+
+    /// 粒子数の上限を指定した生成
+    /// 
+    /// 上限分の領域を始めに確保し、以降は確保を行いません。
+    /// 上限に達している間は新しい粒子は発生しません。
+    pub fn with_capacity(
+        param: EmitterParam, 
+        position: impl Into<nalgebra::Point2<f32>>, 
+        seed: u64, 
+        capacity: usize, 
+    ) -> Self {
+        let mut emitter = Self::new(param, position, seed);
+        emitter.particles.reserve_exact(capacity);
+        emitter.capacity = Some(capacity);
+        emitter
+    }
+
+    /// 乱数の種の再設定と粒子の削除
+    pub fn reset(&mut self, seed: u64) {
+        self.rng = ParticleRng::new(seed);
+        self.particles.clear();
+        self.spawn_acc = 0.;
+    }
+
+    /// 粒子の一括発生
+    pub fn burst(&mut self, count: usize) {
+        for _ in 0..count {
+            if !self.spawn() { break }
+        }
+    }
+
+    /// 粒子の発生
+    /// 
+    /// 上限に達している場合は`false`を返します。
+    fn spawn(&mut self) -> bool {
+        if self.capacity.is_some_and(|c| c <= self.particles.len()) { return false }
+
+        let p = &self.param;
+        let rng = &mut self.rng;
+        let offset = nalgebra::Vector2::new(
+            p.spawn_area.x * (rng.next_f32() * 2. - 1.), 
+            p.spawn_area.y * (rng.next_f32() * 2. - 1.), 
+        );
+        let angle = p.direction + p.spread * (rng.next_f32() * 2. - 1.);
+        let speed = rng.range(p.speed);
+        let particle = Particle {
+            position: self.position + offset, 
+            velocity: nalgebra::Vector2::new(angle.cos(), angle.sin()) * speed, 
+            rotation: rng.range(p.rotation), 
+            angular_velocity: rng.range(p.angular_velocity), 
+            age: 0., 
+            lifetime: rng.range(p.lifetime), 
+        };
+        self.particles.push(particle);
+        true
+    }
+
+    /// 粒子の更新
+    /// 
+    /// `dur`は経過時間(秒)です。
+    pub fn update(&mut self, dur: f32) {
+        let dt = dur.clamp(0., MAX_DT);
+
+        // 移動と寿命の判定(順序は保たない)
+        let gravity = self.param.gravity;
+        let damping = (1. - self.param.drag * dt).max(0.);
+        let mut i = 0;
+        while i < self.particles.len() {
+            let p = &mut self.particles[i];
+            p.age += dt;
+            if p.lifetime <= p.age {
+                self.particles.swap_remove(i);
+                continue
+            }
+            p.velocity = (p.velocity + gravity * dt) * damping;
+            p.position += p.velocity * dt;
+            p.rotation += p.angular_velocity * dt;
+            i += 1;
+        }
+
+        // 連続した発生
+        if self.emitting && 0. < self.param.rate {
+            self.spawn_acc += dt * self.param.rate;
+            while 1. <= self.spawn_acc {
+                self.spawn_acc -= 1.;
+                if !self.spawn() {
+                    self.spawn_acc = 0.;
+                    break
+                }
+            }
+        } else {
+            self.spawn_acc = 0.;
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] { &self.particles }
+
+    pub fn len(&self) -> usize { self.particles.len() }
+
+    pub fn is_empty(&self) -> bool { self.particles.is_empty() }
+
+    /// 粒子の現在のフレーム
+    fn frame(&self, particle: &Particle) -> Option<&ParticleFrame> {
+        let len = self.param.frames.len();
+        if len == 0 { return None }
+        let i = (particle.progress() * len as f32) as usize;
+        self.param.frames.get(i.min(len - 1))
+    }
+}
+impl InstanceGen<(), SqObjInstance> for ParticleEmitter {
+    fn generate(&self, instances: &mut InstanceArray<(), SqObjInstance>) {
+        for p in &self.particles {
+            let t = p.progress();
+            instances.push(SqObjInstance {
+                position: p.position.into(), 
+                size: self.param.size.sample(t), 
+                rotation: p.rotation, 
+                color: self.param.color.sample(t), 
+                z: self.param.z, 
+            })
+        }
+    }
+}
+impl<K, I> InstanceGen<
+    AtlasRenderingModule<K, I>, 
+    AtlasObjInstance, 
+> for ParticleEmitter where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+{
+    fn generate(
+        &self, 
+        instances: &mut InstanceArray<AtlasRenderingModule<K, I>, AtlasObjInstance>, 
+    ) {
+        for p in &self.particles {
+            let Some(frame) = self.frame(p) else { continue };
+            let t = p.progress();
+            instances.push(AtlasObjInstance {
+                atlas_id: frame.atlas_id, 
+                position: p.position, 
+                size: self.param.size.sample(t).into(), 
+                rotation: p.rotation, 
+                tex_coord: frame.tex_coord, 
+                tex_size: frame.tex_size, 
+                z: self.param.z, 
+                tint: self.param.color.sample(t), 
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param() -> EmitterParam { EmitterParam {
+        rate: 120., 
+        lifetime: [0.2, 1.5], 
+        spawn_area: [16., 4.].into(), 
+        spread: 0.8, 
+        gravity: [0., -98.].into(), 
+        drag: 0.5, 
+        rotation: [-1., 1.], 
+        angular_velocity: [-3., 3.], 
+        ..Default::default()
+    }}
+
+    #[test]
+    fn same_seed_same_particles() {
+        let mut a = ParticleEmitter::new(param(), [10., 20.], 42);
+        let mut b = ParticleEmitter::new(param(), [10., 20.], 42);
+        a.burst(32);
+        b.burst(32);
+        for dur in [0.016, 0.033, 0.5, 0.008, 0.1, 0.016] {
+            a.update(dur);
+            b.update(dur);
+        }
+        assert!(!a.is_empty());
+        assert_eq!(a.particles(), b.particles());
+    }
+
+    #[test]
+    fn different_seed_different_particles() {
+        let mut a = ParticleEmitter::new(param(), [0., 0.], 1);
+        let mut b = ParticleEmitter::new(param(), [0., 0.], 2);
+        a.burst(8);
+        b.burst(8);
+        assert_ne!(a.particles(), b.particles());
+    }
+
+    #[test]
+    fn capacity_limits_particles() {
+        let mut emitter = ParticleEmitter::with_capacity(param(), [0., 0.], 7, 10);
+        emitter.burst(32);
+        emitter.update(0.25);
+        assert_eq!(emitter.len(), 10);
+    }
+}
@@ -0,0 +1,159 @@
+/// オフスクリーンの描画先
+/// 
+/// 各レンダラの描画先として`view`を渡し、描画結果は
+/// `create_texture`で得たテクスチャを通して他の描画に使えます。
+pub struct RenderTarget {
+    texture: wgpu::Texture, 
+    view: wgpu::TextureView, 
+    format: wgpu::TextureFormat, 
+    size: [u32; 2], 
+}
+impl RenderTarget {
+    /// サーフェスと同じフォーマットの描画先の生成
+    /// 
+    /// 既存のレンダラのパイプラインはサーフェスのフォーマットで
+    /// 作られるため、通常はこちらを用います。
+    pub fn new(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        width: u32, 
+        height: u32, 
+    ) -> Self { Self::with_format(
+        gfx, 
+        width, 
+        height, 
+        gfx.config.format, 
+    )}
+
+    /// フォーマットを指定した描画先の生成
+    pub fn with_format(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        width: u32, 
+        height: u32, 
+        format: wgpu::TextureFormat, 
+    ) -> Self {
+        let size = [width.max(1), height.max(1)];
+        let (texture, view) = Self::create_target(gfx, size, format);
+
+        Self {
+            texture, 
+            view, 
+            format, 
+            size, 
+        }
+    }
+
+    fn create_target(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        size: [u32; 2], 
+        format: wgpu::TextureFormat, 
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = gfx.device.create_texture(
+            &wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: size[0], 
+                    height: size[1], 
+                    depth_or_array_layers: 1, 
+                }, 
+                mip_level_count: 1, 
+                sample_count: 1, 
+                dimension: wgpu::TextureDimension::D2, 
+                format, 
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC, 
+                label: Some("render target texture"), 
+                view_formats: &[], 
+            }
+        );
+        let view = texture.create_view(
+            &wgpu::TextureViewDescriptor::default()
+        );
+        (texture, view)
+    }
+
+    /// 描画先のビュー
+    pub fn view(&self) -> &wgpu::TextureView { &self.view }
+
+    /// 生のテクスチャ
+    pub fn raw_texture(&self) -> &wgpu::Texture { &self.texture }
+
+    /// フォーマット
+    pub fn format(&self) -> wgpu::TextureFormat { self.format }
+
+    /// 大きさ(ピクセル)
+    pub fn size(&self) -> [u32; 2] { self.size }
+
+    /// 大きさの変更
+    /// 
+    /// 内容は破棄されます。以前に生成したテクスチャは古い描画先を
+    /// 指したままになるため、`create_texture`で作り直してください。
+    pub fn resize(
+        &mut self, 
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        width: u32, 
+        height: u32, 
+    ) {
+        let size = [width.max(1), height.max(1)];
+        if size == self.size { return }
+        let (texture, view) = Self::create_target(gfx, size, self.format);
+        self.texture = texture;
+        self.view = view;
+        self.size = size;
+    }
+
+    /// 描画結果を参照するテクスチャの生成
+    /// 
+    /// `bind_group_layout`には`ImagedShared::diffuse`を渡します。
+    pub fn create_texture(
+        &self, 
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        bind_group_layout: &wgpu::BindGroupLayout, 
+    ) -> super::types::Texture {
+        // 拡大時にドットが滲まないよう最近傍補間
+        self.create_texture_with(
+            gfx, 
+            bind_group_layout, 
+            super::types::SamplerOption::PIXEL, 
+        )
+    }
+
+    /// サンプリングの設定を指定した描画結果を参照するテクスチャの生成
+    /// 
+    /// 描画先はミップマップを持たないため、`option.mipmap`は用いられません。
+    pub fn create_texture_with(
+        &self, 
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        bind_group_layout: &wgpu::BindGroupLayout, 
+        option: super::types::SamplerOption, 
+    ) -> super::types::Texture {
+        super::types::Texture::from_view(
+            gfx, 
+            bind_group_layout, 
+            &self.view, 
+            self.size, 
+            option, 
+        )
+    }
+
+    /// 描画先の塗りつぶし
+    pub fn clear(
+        &self, 
+        encoder: &mut wgpu::CommandEncoder, 
+        color: wgpu::Color, 
+    ) {
+        encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("render target clear pass"), 
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.view, 
+                    resolve_target: None, 
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(color), 
+                        store: true
+                    }
+                })], 
+                depth_stencil_attachment: None, 
+            }
+        );
+    }
+}
@@ -74,8 +74,18 @@ impl ImagedShared {
 }
 
 /// カメラ
+/// 
+/// 画面分割などで複数のカメラを使う場合は、カメラごとに
+/// `S2DCamera`を作成して`viewport`で描画領域を指定します。
 pub struct S2DCamera {
     pub camera: super::types::Camera, 
+
+    /// 描画領域(`None`で描画先全体)
+    pub viewport: Option<crate::ctx::gfx::virtual_res::Viewport>, 
+
+    /// 深度バッファ(`None`で深度を用いず描画順で重ねる)
+    pub depth: Option<std::sync::Arc<super::depth::DepthBuffer>>, 
+
     raw: super::raw::CameraRaw, 
     buffer: wgpu::Buffer, 
     pub bg: wgpu::BindGroup, 
@@ -135,6 +145,8 @@ impl S2DCamera {
 
         Self {
             camera,
+            viewport: None, 
+            depth: None, 
             raw,
             buffer,
             bg,
@@ -155,4 +167,83 @@ impl S2DCamera {
         );
         &self.bg
     }
-}   
\ No newline at end of file
+
+    /// 描画領域の設定
+    pub fn set_viewport(
+        &mut self, 
+        viewport: Option<crate::ctx::gfx::virtual_res::Viewport>, 
+    ) { self.viewport = viewport }
+
+    /// 深度バッファの設定
+    /// 
+    /// 複数のカメラで同じ深度バッファを共有できます。
+    pub fn set_depth_buffer(
+        &mut self, 
+        depth: Option<std::sync::Arc<super::depth::DepthBuffer>>, 
+    ) { self.depth = depth }
+
+    /// 描画パスで用いる深度のアタッチメント
+    pub(crate) fn depth_attachment(
+        &self, 
+    ) -> Option<wgpu::RenderPassDepthStencilAttachment<'_>> {
+        self.depth.as_ref().map(|depth| depth.attachment())
+    }
+
+    /// 描画パスへの描画領域の適用
+    pub fn apply_viewport(
+        &self, 
+        render_pass: &mut wgpu::RenderPass<'_>, 
+    ) {
+        if let Some(vp) = self.viewport {
+            render_pass.set_viewport(vp.x, vp.y, vp.width, vp.height, 0., 1.);
+        }
+    }
+
+    /// 描画領域に対応する、左上原点のウィンドウ座標での位置と大きさ
+    fn screen_rect(
+        &self, 
+        surface_size: [u32; 2], 
+    ) -> ([f32; 2], [u32; 2]) {
+        match self.viewport {
+            Some(vp) => (
+                [vp.x, vp.y], 
+                [vp.width.max(1.) as u32, vp.height.max(1.) as u32], 
+            ), 
+            None => ([0., 0.], surface_size), 
+        }
+    }
+
+    /// 位置が描画領域内にあるか
+    pub fn contains_screen(
+        &self, 
+        screen: impl Into<nalgebra::Point2<f32>>, 
+    ) -> bool {
+        let screen = screen.into();
+        self.viewport.is_none_or(|vp| vp.contains([screen.x, screen.y]))
+    }
+
+    /// 描画領域を考慮したウィンドウ座標からワールド座標への変換
+    pub fn screen_to_world(
+        &self, 
+        screen: impl Into<nalgebra::Point2<f32>>, 
+        surface_size: [u32; 2], 
+    ) -> nalgebra::Point2<f32> {
+        let (origin, size) = self.screen_rect(surface_size);
+        let screen = screen.into();
+        self.camera.screen_to_world(
+            [screen.x - origin[0], screen.y - origin[1]], 
+            size, 
+        )
+    }
+
+    /// 描画領域を考慮したワールド座標からウィンドウ座標への変換
+    pub fn world_to_screen(
+        &self, 
+        world: impl Into<nalgebra::Point2<f32>>, 
+        surface_size: [u32; 2], 
+    ) -> nalgebra::Point2<f32> {
+        let (origin, size) = self.screen_rect(surface_size);
+        let screen = self.camera.world_to_screen(world, size);
+        [screen.x + origin[0], screen.y + origin[1]].into()
+    }
+}
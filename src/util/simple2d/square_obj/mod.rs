@@ -1,5 +1,4 @@
 use wgpu::{
-    Buffer, 
     VertexAttribute, 
     VertexBufferLayout, 
     VertexStepMode, 
@@ -15,11 +14,16 @@ use super::{
         InstanceGen, 
         InstanceRaw, 
         buffer::InstanceArray, 
+        batch::StaticBatchSet, 
     }, 
     shared::{
         S2DCamera, 
         SquareShared, 
     }, 
+    blend::{
+        BlendMode, 
+        PipelineCache, 
+    }, 
     raw::{
         TexedVertex, 
         INDICES, 
@@ -34,13 +38,15 @@ pub struct SqObjInstanceRaw {
     pub size: [f32; 2], 
     pub rotation: [f32; 2], 
     pub color: [f32; 4], 
+    pub z: f32, 
 }
 impl SqObjInstanceRaw {
-    const ATTRIBS: [VertexAttribute; 4] = vertex_attr_array![
+    const ATTRIBS: [VertexAttribute; 5] = vertex_attr_array![
         5 => Float32x2, 
         6 => Float32x2, 
         7 => Float32x2, 
         8 => Float32x4, 
+        9 => Float32, 
     ];
 }
 impl InstanceRaw for SqObjInstanceRaw {
@@ -60,6 +66,9 @@ pub struct SqObjInstance {
     pub size: [f32; 2], 
     pub rotation: f32, 
     pub color: [f32; 4], 
+
+    /// 奥行き(大きいほど手前)
+    pub z: f32, 
 }
 impl Instance<()> for SqObjInstance {
     type Raw = SqObjInstanceRaw;
@@ -77,8 +86,11 @@ impl Instance<()> for SqObjInstance {
             size, 
             rotation, 
             color: self.color, 
+            z: self.z, 
         }
     }
+
+    fn z(&self) -> f32 { self.z }
     
 }
 impl InstanceGen<(), SqObjInstance> for SqObjInstance {
@@ -95,9 +107,19 @@ impl InstanceGen<(), SqObjInstance> for SqObjInstance {
 
 /// 画像を使ったオブジェクトの描画構造体で共有される値
 pub struct SqObjRenderShared {
-    pipeline: RenderPipeline, 
+    pipelines: PipelineCache, 
 }
 impl SqObjRenderShared {
+    /// 合成方法と深度バッファの有無に応じたパイプライン
+    pub(crate) fn pipeline(
+        &self, 
+        device: &wgpu::Device, 
+        mode: BlendMode, 
+        depth: bool, 
+    ) -> &RenderPipeline {
+        self.pipelines.get(device, mode, depth)
+    }
+
     pub fn new(
         gfx: &crate::ctx::gfx::WGPUCtx, 
         camera: &S2DCamera, 
@@ -125,7 +147,12 @@ impl SqObjRenderShared {
         );
 
         // パイプラインの初期化
-        let pipeline = gfx.device.create_render_pipeline(
+        let format = gfx.config.format;
+        let pipelines = PipelineCache::new(&gfx.device, move |
+            device: &wgpu::Device, 
+            blend: BlendMode, 
+            depth_stencil: Option<wgpu::DepthStencilState>, 
+        | device.create_render_pipeline(
             &RenderPipelineDescriptor {
                 label: Some("sample pipeline"), 
                 layout: Some(&pipeline_layout), 
@@ -139,10 +166,10 @@ impl SqObjRenderShared {
                 }, 
                 fragment: Some(wgpu::FragmentState {
                     module: &shader, 
-                    entry_point: "fs_main", 
+                    entry_point: blend.fragment_entry(), 
                     targets: &[Some(wgpu::ColorTargetState { 
-                        format: gfx.config.format, 
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING), 
+                        format, 
+                        blend: Some(blend.blend_state()), 
                         write_mask: wgpu::ColorWrites::all() 
                     })]
                 }), 
@@ -155,7 +182,7 @@ impl SqObjRenderShared {
                     polygon_mode, 
                     conservative: false, 
                 }, 
-                depth_stencil: None, 
+                depth_stencil, 
                 multisample: wgpu::MultisampleState {
                     count: 1, 
                     mask: !0, 
@@ -163,10 +190,10 @@ impl SqObjRenderShared {
                 }, 
                 multiview: None, 
             }
-        );
+        ));
 
         Self {
-            pipeline, 
+            pipelines, 
         }
     }
 }
@@ -174,7 +201,8 @@ impl SqObjRenderShared {
 /// 画像用レンダラ
 pub struct SqObjRender {
     instances: InstanceArray<(), SqObjInstance>, 
-    instance_buffer: Buffer, 
+    batches: StaticBatchSet<(), SqObjInstance>, 
+    blend: BlendMode, 
 }
 impl SqObjRender {
     pub fn new(
@@ -185,11 +213,12 @@ impl SqObjRender {
         let mut instances = InstanceArray::new();
 
         // インスタンスバッファの初期化
-        let instance_buffer = instances.finish(gfx, &());
+        instances.finish(gfx, &());
 
         Self {
             instances, 
-            instance_buffer, 
+            batches: StaticBatchSet::new(), 
+            blend: BlendMode::default(), 
         }
     }
 
@@ -201,11 +230,12 @@ impl SqObjRender {
         let mut instances = InstanceArray::new();
 
         // インスタンスバッファの初期化
-        let instance_buffer = instances.finish(gfx, &());
+        instances.finish(gfx, &());
 
         Ok(Self {
             instances, 
-            instance_buffer, 
+            batches: StaticBatchSet::new(), 
+            blend: BlendMode::default(), 
         })
     }
 
@@ -216,6 +246,17 @@ impl SqObjRender {
     ) {
         instance.generate(&mut self.instances);
     }
+
+    /// 固定のインスタンスの集まりの一覧
+    pub fn batches(&mut self) -> &mut StaticBatchSet<(), SqObjInstance> {
+        &mut self.batches
+    }
+
+    /// 合成方法
+    pub fn blend_mode(&self) -> BlendMode { self.blend }
+
+    /// 合成方法の設定
+    pub fn set_blend_mode(&mut self, mode: BlendMode) { self.blend = mode }
 }
 impl<GCd: Send + Sync> super::Simple2DRender<GCd> for SqObjRender {
     type Shared<'a> = (
@@ -231,40 +272,77 @@ impl<GCd: Send + Sync> super::Simple2DRender<GCd> for SqObjRender {
         camera: &S2DCamera, 
         shared: Self::Shared<'a>, 
     ) {
-        self.instance_buffer = self.instances.finish(
+        self.rendering_multi(gfx, encoder, view, &[camera], shared)
+    }
+
+    fn rendering_multi<'a>(
+        &mut self, 
+        gfx: &crate::ctx::gfx::GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        cameras: &[&S2DCamera], 
+        shared: Self::Shared<'a>, 
+    ) where
+        Self::Shared<'a>: Clone, 
+    {
+        self.instances.finish(
             &gfx.wgpu_ctx, 
             &()
         );
+        let Some(instance_buffer) = self.instances.buffer() else { return };
+        self.batches.upload(&gfx.wgpu_ctx, &());
+
+        // カメラごとに描画領域を切り替えて描画
+        for camera in cameras {
+            let mut render_pass = encoder.begin_render_pass(
+                &wgpu::RenderPassDescriptor {
+                    label: Some("render pass"), 
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
+                        view, 
+                        resolve_target: None, 
+                        ops: wgpu::Operations { 
+                            load: wgpu::LoadOp::Load, 
+                            store: true 
+                        } 
+                    })], 
+                    depth_stencil_attachment: camera.depth_attachment(), 
+                }
+            );
+            camera.apply_viewport(&mut render_pass);
 
-        let mut render_pass = encoder.begin_render_pass(
-            &wgpu::RenderPassDescriptor {
-                label: Some("render pass"), 
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
-                    view, 
-                    resolve_target: None, 
-                    ops: wgpu::Operations { 
-                        load: wgpu::LoadOp::Load, 
-                        store: true 
-                    } 
-                })], 
-                depth_stencil_attachment: None, 
+            render_pass.set_pipeline(
+                shared.1.pipeline(
+                    &gfx.wgpu_ctx.device, 
+                    self.blend, 
+                    camera.depth.is_some(), 
+                )
+            );
+            render_pass.set_bind_group(0, &camera.bg, &[]);
+            render_pass.set_vertex_buffer(
+                0, shared.0.vertex.slice(..)
+            );
+            render_pass.set_index_buffer(
+                shared.0.index.slice(..), wgpu::IndexFormat::Uint16
+            );
+
+            // 固定のインスタンスの描画
+            for (buffer, len) in self.batches.buffers() {
+                render_pass.set_vertex_buffer(1, buffer.slice(..));
+                render_pass.draw_indexed(
+                    0..INDICES.len() as _, 
+                    0, 
+                    0..len
+                );
             }
-        );
 
-        render_pass.set_pipeline(&shared.1.pipeline);
-        render_pass.set_bind_group(0, &camera.bg, &[]);
-        render_pass.set_vertex_buffer(
-            0, shared.0.vertex.slice(..)
-        );
-        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        render_pass.set_index_buffer(
-            shared.0.index.slice(..), wgpu::IndexFormat::Uint16
-        );
-        render_pass.draw_indexed(
-            0..INDICES.len() as _, 
-            0, 
-            0..self.instances.len() as _
-        );
+            // 毎フレームのインスタンスの描画
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.draw_indexed(
+                0..INDICES.len() as _, 
+                0, 
+                0..self.instances.len() as _
+            );
+        }
     }
     
 }
\ No newline at end of file
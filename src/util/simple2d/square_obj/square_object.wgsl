@@ -20,6 +20,7 @@ struct InstanceInput {
     @location(6) size: vec2<f32>, 
     @location(7) rotation: vec2<f32>, 
     @location(8) color: vec4<f32>, 
+    @location(9) z: f32, 
 }
 
 struct VertexOutput {
@@ -61,7 +62,13 @@ fn vs_main(
         1., 
     );
     pos_temp *= vec4<f32>(camera.size.x, camera.size.y, 0., 1.);
-    out.clip_position = pos_temp;
+
+    // 奥行きを深度へ変換(zは-1024から1024、大きいほど手前)
+    out.clip_position = vec4<f32>(
+        pos_temp.xy, 
+        clamp(0.5 - instance.z * (0.5 / 1024.), 0., 1.), 
+        1., 
+    );
     return out;
 }
 
@@ -69,5 +76,19 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 完全に透明な画素は深度を書き込まない
+    if in.color.w <= 0. {
+        discard;
+    }
     return in.color;
-}
\ No newline at end of file
+}
+
+// 乗算済みアルファの合成用
+@fragment
+fn fs_premultiplied(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 完全に透明な画素は深度を書き込まない
+    if in.color.w <= 0. {
+        discard;
+    }
+    return vec4<f32>(in.color.xyz * in.color.w, in.color.w);
+}
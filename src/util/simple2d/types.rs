@@ -15,6 +15,24 @@ impl VisibleField {
         cam_res: camera.size, 
     }}
 
+    /// ズームと回転を考慮した描画領域
+    /// 
+    /// 回転している場合は、描画領域を囲む軸に平行な矩形になります。
+    pub fn bounding(
+        camera: &Camera, 
+    ) -> Self {
+        let half = camera.size * 0.5 / camera.zoom.max(f32::EPSILON);
+        let (sin, cos) = camera.rotation.sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        Self {
+            cam_pos: camera.position, 
+            cam_res: nalgebra::Vector2::new(
+                half.x * cos + half.y * sin, 
+                half.x * sin + half.y * cos, 
+            ) * 2., 
+        }
+    }
+
     pub fn in_visible<P, S>(
         &self, 
         pos: P, 
@@ -59,19 +77,198 @@ impl Camera {
             (-self.rotation).sin(), 
         ];
         super::raw::CameraRaw {
-            position,
-            size,
-            rotation,
-            _dummy: Default::default(),
+            position, 
+            size, 
+            rotation, 
+            _dummy: Default::default(), 
         }
     }
+
+    /// ワールド座標から正規化デバイス座標への変換
+    /// 
+    /// シェーダでの変換と同じ計算を行います。
+    pub fn world_to_ndc(
+        &self, 
+        world: impl Into<nalgebra::Point2<f32>>, 
+    ) -> nalgebra::Point2<f32> {
+        let raw = self.as_raw();
+        let rel = world.into() - self.position;
+        let rotated = [
+            rel.x * raw.rotation[0] - rel.y * raw.rotation[1], 
+            rel.x * raw.rotation[1] + rel.y * raw.rotation[0], 
+        ];
+        [
+            rotated[0] * raw.size[0], 
+            rotated[1] * raw.size[1], 
+        ].into()
+    }
+
+    /// 正規化デバイス座標からワールド座標への変換
+    pub fn ndc_to_world(
+        &self, 
+        ndc: impl Into<nalgebra::Point2<f32>>, 
+    ) -> nalgebra::Point2<f32> {
+        let raw = self.as_raw();
+        let ndc = ndc.into();
+        let rotated = [
+            ndc.x / raw.size[0], 
+            ndc.y / raw.size[1], 
+        ];
+
+        // 逆回転(回転行列の転置)
+        let rel = nalgebra::Vector2::new(
+            rotated[0] * raw.rotation[0] + rotated[1] * raw.rotation[1], 
+            -rotated[0] * raw.rotation[1] + rotated[1] * raw.rotation[0], 
+        );
+        self.position + rel
+    }
+
+    /// ウィンドウ座標(左上原点・下向きのピクセル)から正規化デバイス座標への変換
+    pub fn screen_to_ndc(
+        screen: impl Into<nalgebra::Point2<f32>>, 
+        surface_size: [u32; 2], 
+    ) -> nalgebra::Point2<f32> {
+        let screen = screen.into();
+        [
+            screen.x / surface_size[0].max(1) as f32 * 2. - 1., 
+            1. - screen.y / surface_size[1].max(1) as f32 * 2., 
+        ].into()
+    }
+
+    /// 正規化デバイス座標からウィンドウ座標への変換
+    pub fn ndc_to_screen(
+        ndc: impl Into<nalgebra::Point2<f32>>, 
+        surface_size: [u32; 2], 
+    ) -> nalgebra::Point2<f32> {
+        let ndc = ndc.into();
+        [
+            (ndc.x + 1.) * 0.5 * surface_size[0] as f32, 
+            (1. - ndc.y) * 0.5 * surface_size[1] as f32, 
+        ].into()
+    }
+
+    /// ウィンドウ座標からワールド座標への変換
+    pub fn screen_to_world(
+        &self, 
+        screen: impl Into<nalgebra::Point2<f32>>, 
+        surface_size: [u32; 2], 
+    ) -> nalgebra::Point2<f32> {
+        self.ndc_to_world(Self::screen_to_ndc(screen, surface_size))
+    }
+
+    /// ワールド座標からウィンドウ座標への変換
+    pub fn world_to_screen(
+        &self, 
+        world: impl Into<nalgebra::Point2<f32>>, 
+        surface_size: [u32; 2], 
+    ) -> nalgebra::Point2<f32> {
+        Self::ndc_to_screen(self.world_to_ndc(world), surface_size)
+    }
+}
+
+/// テクスチャのサンプリングの設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplerOption {
+    /// 拡大・縮小時の補間
+    pub filter: wgpu::FilterMode, 
+
+    /// テクスチャの範囲外の扱い
+    pub address: wgpu::AddressMode, 
+
+    /// ミップマップの生成
+    /// 
+    /// 縮小して表示する画像で有効にします。
+    /// 縮小は画像全体に対して行われるため、アトラスのように複数の画像を
+    /// 詰めたテクスチャでは隣の画像の色が混ざります。
+    /// その場合は画像の間に余白を設けてください。
+    pub mipmap: bool, 
+
+    /// 読み込み時の乗算済みアルファへの変換
+    /// 
+    /// `BlendMode::Premultiplied`で描画する画像で有効にします。
+    pub premultiply: bool, 
+}
+impl SamplerOption {
+    /// ドット絵向けの設定(最近傍補間・範囲外は端の色)
+    pub const PIXEL: Self = Self {
+        filter: wgpu::FilterMode::Nearest, 
+        address: wgpu::AddressMode::ClampToEdge, 
+        mipmap: false, 
+        premultiply: false, 
+    };
+
+    /// 縮小して表示する画像向けの設定(線形補間・ミップマップ)
+    pub const SMOOTH: Self = Self {
+        filter: wgpu::FilterMode::Linear, 
+        address: wgpu::AddressMode::ClampToEdge, 
+        mipmap: true, 
+        premultiply: false, 
+    };
+
+    pub fn with_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// 範囲外の扱いの設定
+    /// 
+    /// 背景を敷き詰める場合は`Repeat`や`MirrorRepeat`を指定します。
+    pub fn with_address(mut self, address: wgpu::AddressMode) -> Self {
+        self.address = address;
+        self
+    }
+
+    pub fn with_mipmap(mut self, mipmap: bool) -> Self {
+        self.mipmap = mipmap;
+        self
+    }
+
+    pub fn with_premultiply(mut self, premultiply: bool) -> Self {
+        self.premultiply = premultiply;
+        self
+    }
+
+    pub fn create_sampler(
+        &self, 
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+    ) -> wgpu::Sampler {
+        gfx.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: self.address, 
+            address_mode_v: self.address, 
+            address_mode_w: self.address, 
+            mag_filter: self.filter, 
+            min_filter: self.filter, 
+            mipmap_filter: self.filter, 
+            ..Default::default()
+        })
+    }
+
+    /// ミップマップの段数
+    fn mip_level_count(&self, dim: (u32, u32)) -> u32 {
+        if self.mipmap {
+            u32::BITS - dim.0.max(dim.1).max(1).leading_zeros()
+        } else {
+            1
+        }
+    }
+}
+impl Default for SamplerOption {
+    fn default() -> Self { Self::PIXEL }
 }
 
 /// テクスチャ
+/// 
+/// 構造体リテラルで生成する場合は`option`も指定してください。
 pub struct Texture {
     pub bind_group: wgpu::BindGroup, 
     pub texture_size: nalgebra::Vector2<f32>, 
     pub sampler: wgpu::Sampler, 
+
+    /// サンプリングの設定
+    /// 
+    /// `update_image`でのミップマップの生成と乗算済みアルファへの変換に用います。
+    /// `sampler`の設定と一致させてください。
+    pub option: SamplerOption, 
 }
 impl Texture {
     pub fn create_bind_group<C: std::ops::Deref<Target = [u8]>>(
@@ -79,9 +276,63 @@ impl Texture {
         bind_group_layout: &wgpu::BindGroupLayout, 
         sampler: &wgpu::Sampler, 
         image: ImageBuffer<Rgba<u8>, C>, 
+    ) -> ((u32, u32), wgpu::BindGroup) {
+        Self::create_bind_group_with(
+            gfx, 
+            bind_group_layout, 
+            sampler, 
+            image, 
+            false, 
+        )
+    }
+
+    /// 読み込みの設定に従ったバインドグループの作成
+    fn create_bind_group_opt<C: std::ops::Deref<Target = [u8]>>(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        bind_group_layout: &wgpu::BindGroupLayout, 
+        sampler: &wgpu::Sampler, 
+        image: ImageBuffer<Rgba<u8>, C>, 
+        option: SamplerOption, 
+    ) -> ((u32, u32), wgpu::BindGroup) {
+        if !option.premultiply {
+            return Self::create_bind_group_with(
+                gfx, 
+                bind_group_layout, 
+                sampler, 
+                image, 
+                option.mipmap, 
+            )
+        }
+        let mut image = ImageBuffer::<Rgba<u8>, _>::from_raw(
+            image.width(), 
+            image.height(), 
+            image.as_raw().to_vec(), 
+        ).unwrap();
+        premultiply_alpha(&mut image);
+        Self::create_bind_group_with(
+            gfx, 
+            bind_group_layout, 
+            sampler, 
+            image, 
+            option.mipmap, 
+        )
+    }
+
+    /// ミップマップの有無を指定したバインドグループの作成
+    /// 
+    /// ミップマップは画像を線形色空間で縮小して生成し、各段へ書き込みます。
+    pub fn create_bind_group_with<C: std::ops::Deref<Target = [u8]>>(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        bind_group_layout: &wgpu::BindGroupLayout, 
+        sampler: &wgpu::Sampler, 
+        image: ImageBuffer<Rgba<u8>, C>, 
+        mipmap: bool, 
     ) -> ((u32, u32), wgpu::BindGroup) {
         // 画像の大きさ情報の取得
         let dim = image.dimensions();
+        let mip_level_count = SamplerOption::PIXEL
+            .with_mipmap(mipmap)
+            .mip_level_count(dim);
 
         // GPUで画像を扱うための大きさの情報を初期化
         let tex_size = wgpu::Extent3d {
@@ -94,7 +345,7 @@ impl Texture {
         let texture = gfx.device.create_texture(
             &wgpu::TextureDescriptor {
                 size: tex_size, 
-                mip_level_count: 1, 
+                mip_level_count, 
                 sample_count: 1, 
                 dimension: wgpu::TextureDimension::D2, 
                 format: wgpu::TextureFormat::Rgba8UnormSrgb, 
@@ -105,21 +356,59 @@ impl Texture {
         );
 
         // GPUのキューにテクスチャ情報を書き込む
-        gfx.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture, 
-                mip_level: 0, 
-                origin: wgpu::Origin3d::ZERO, 
-                aspect: wgpu::TextureAspect::All, 
-            }, 
-            &image, 
-            wgpu::ImageDataLayout { 
-                offset: 0, 
-                bytes_per_row: std::num::NonZeroU32::new(4 * dim.0), 
-                rows_per_image: std::num::NonZeroU32::new(dim.1) 
-            }, 
-            tex_size
-        );
+        let write_level = |level: u32, data: &[u8], (w, h): (u32, u32)| {
+            gfx.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture, 
+                    mip_level: level, 
+                    origin: wgpu::Origin3d::ZERO, 
+                    aspect: wgpu::TextureAspect::All, 
+                }, 
+                data, 
+                wgpu::ImageDataLayout { 
+                    offset: 0, 
+                    bytes_per_row: std::num::NonZeroU32::new(4 * w), 
+                    rows_per_image: std::num::NonZeroU32::new(h) 
+                }, 
+                wgpu::Extent3d {
+                    width: w, 
+                    height: h, 
+                    depth_or_array_layers: 1, 
+                }
+            );
+        };
+        write_level(0, &image, dim);
+
+        // ミップマップの生成(前の段を線形色空間で半分に縮小する)
+        if 1 < mip_level_count {
+            let mut prev = image::Rgba32FImage::from_fn(dim.0, dim.1, |x, y| {
+                let p = image.get_pixel(x, y).0;
+                Rgba([
+                    srgb_to_linear(p[0]), 
+                    srgb_to_linear(p[1]), 
+                    srgb_to_linear(p[2]), 
+                    p[3] as f32 / 255., 
+                ])
+            });
+            for level in 1..mip_level_count {
+                let size = ((prev.width() / 2).max(1), (prev.height() / 2).max(1));
+                prev = image::imageops::resize(
+                    &prev, 
+                    size.0, 
+                    size.1, 
+                    image::imageops::FilterType::Triangle, 
+                );
+                let data: Vec<u8> = prev.pixels()
+                    .flat_map(|p| [
+                        linear_to_srgb(p[0]), 
+                        linear_to_srgb(p[1]), 
+                        linear_to_srgb(p[2]), 
+                        (p[3].clamp(0., 1.) * 255.).round() as u8, 
+                    ])
+                    .collect();
+                write_level(level, &data, size);
+            }
+        }
 
         // ビューの作成
         let texture_view = texture.create_view(
@@ -151,11 +440,12 @@ impl Texture {
         bind_group_layout: &wgpu::BindGroupLayout, 
         image: ImageBuffer<Rgba<u8>, C>, 
     ) -> wgpu::BindGroup {
-        let (dim, bind_group) = Self::create_bind_group(
+        let (dim, bind_group) = Self::create_bind_group_opt(
             gfx, 
             bind_group_layout, 
             &self.sampler, 
-            image
+            image, 
+            self.option, 
         );
 
         self.texture_size = [dim.0 as f32, dim.1 as f32].into();
@@ -168,40 +458,104 @@ impl Texture {
         gfx: &crate::ctx::gfx::WGPUCtx, 
         bind_group_layout: &wgpu::BindGroupLayout, 
         image: ImageBuffer<Rgba<u8>, C>, 
+    ) -> Self {
+        Self::from_image_with(
+            gfx, 
+            bind_group_layout, 
+            image, 
+            SamplerOption::default(), 
+        )
+    }
+
+    /// サンプリングの設定を指定した生成
+    pub fn from_image_with<C: std::ops::Deref<Target = [u8]>>(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        bind_group_layout: &wgpu::BindGroupLayout, 
+        image: ImageBuffer<Rgba<u8>, C>, 
+        option: SamplerOption, 
     ) -> Self {
         // サンプラの作成
-        let sampler = gfx.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge, 
-            address_mode_v: wgpu::AddressMode::ClampToEdge, 
-            address_mode_w: wgpu::AddressMode::ClampToEdge, 
-            mag_filter: wgpu::FilterMode::Nearest, 
-            min_filter: wgpu::FilterMode::Nearest, 
-            mipmap_filter: wgpu::FilterMode::Nearest, 
-            ..Default::default()
-        });
+        let sampler = option.create_sampler(gfx);
 
-        let (dim, bind_group) = Self::create_bind_group(
+        let (dim, bind_group) = Self::create_bind_group_opt(
             gfx, 
             bind_group_layout, 
             &sampler, 
-            image
+            image, 
+            option, 
         );
 
         Self { 
             bind_group, 
             texture_size: [dim.0 as f32, dim.1 as f32].into(), 
             sampler, 
+            option, 
+        }
+    }
+
+    /// 既存のテクスチャビューからの生成
+    /// 
+    /// ビューにミップマップが無い場合、`option.mipmap`は用いられません。
+    pub fn from_view(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        bind_group_layout: &wgpu::BindGroupLayout, 
+        view: &wgpu::TextureView, 
+        size: [u32; 2], 
+        option: SamplerOption, 
+    ) -> Self {
+        let sampler = option.create_sampler(gfx);
+
+        let bind_group = gfx.device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: bind_group_layout, 
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0, 
+                        resource: wgpu::BindingResource::TextureView(view), 
+                    }, 
+                    wgpu::BindGroupEntry {
+                        binding: 1, 
+                        resource: wgpu::BindingResource::Sampler(&sampler), 
+                    }, 
+                ], 
+                label: Some("texture view bind group"), 
+            }
+        );
+
+        Self {
+            bind_group, 
+            texture_size: [size[0] as f32, size[1] as f32].into(), 
+            sampler, 
+            option, 
         }
     }
 
+    /// サンプリングの設定
+    pub fn sampler_option(&self) -> SamplerOption { self.option }
+
     pub fn new(
         gfx: &crate::ctx::gfx::WGPUCtx, 
         bind_group_layout: &wgpu::BindGroupLayout, 
         path: impl AsRef<std::path::Path>, 
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::new_with(
+            gfx, 
+            bind_group_layout, 
+            path, 
+            SamplerOption::default(), 
+        )
+    }
+
+    /// サンプリングの設定を指定した画像ファイルからの生成
+    pub fn new_with(
+        gfx: &crate::ctx::gfx::WGPUCtx, 
+        bind_group_layout: &wgpu::BindGroupLayout, 
+        path: impl AsRef<std::path::Path>, 
+        option: SamplerOption, 
     ) -> Result<Self, Box<dyn std::error::Error>> {
         // 画像の読み込み・インスタンス生成
 
-        Ok(Self::from_image(
+        Ok(Self::from_image_with(
             gfx, 
             bind_group_layout, 
             {
@@ -210,7 +564,146 @@ impl Texture {
                 file.read_to_end(&mut diffuse_bytes)?;
                 image::load_from_memory(&diffuse_bytes)?
                     .to_rgba8()
-            }
+            }, 
+            option, 
         ))
     }
-}
\ No newline at end of file
+}
+
+/// sRGBの値から線形色空間の値への変換
+fn srgb_to_linear(v: u8) -> f32 {
+    let v = v as f32 / 255.;
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+/// 線形色空間の値からsRGBの値への変換
+fn linear_to_srgb(v: f32) -> u8 {
+    let v = v.clamp(0., 1.);
+    let v = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(2.4f32.recip()) - 0.055 };
+    (v * 255.).round() as u8
+}
+
+/// 乗算済みアルファへの変換
+/// 
+/// 色は線形色空間で不透明度を掛けてからsRGBへ戻します。
+pub fn premultiply_alpha<C: std::ops::DerefMut<Target = [u8]>>(
+    image: &mut ImageBuffer<Rgba<u8>, C>, 
+) {
+    for p in image.pixels_mut() {
+        let a = p[3] as f32 / 255.;
+        for c in &mut p.0[..3] { *c = linear_to_srgb(srgb_to_linear(*c) * a) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-3;
+
+    fn camera() -> Camera { Camera {
+        position: [120., -40.].into(), 
+        size: [640., 360.].into(), 
+        zoom: 1.75, 
+        rotation: 0.6, 
+    }}
+
+    fn assert_near(a: nalgebra::Point2<f32>, b: nalgebra::Point2<f32>) {
+        assert!((a - b).norm() < EPS, "{a:?} != {b:?}");
+    }
+
+    /// imaged_object.wgslの頂点シェーダと同じ計算
+    fn shader_ndc(raw: &super::super::raw::CameraRaw, world: [f32; 2]) -> [f32; 2] {
+        let x = world[0] - raw.position[0];
+        let y = world[1] - raw.position[1];
+        let rx = x * raw.rotation[0] - y * raw.rotation[1];
+        let ry = x * raw.rotation[1] + y * raw.rotation[0];
+        [rx * raw.size[0], ry * raw.size[1]]
+    }
+
+    #[test]
+    fn world_to_ndc_matches_shader() {
+        let camera = camera();
+        let raw = camera.as_raw();
+        for world in [[0., 0.], [120., -40.], [300., 75.], [-512., 260.]] {
+            assert_near(camera.world_to_ndc(world), shader_ndc(&raw, world).into());
+        }
+    }
+
+    #[test]
+    fn ndc_round_trip() {
+        let camera = camera();
+        for world in [[0., 0.], [300., 75.], [-512., 260.]] {
+            let world = nalgebra::Point2::from(world);
+            assert_near(camera.ndc_to_world(camera.world_to_ndc(world)), world);
+        }
+    }
+
+    #[test]
+    fn screen_round_trip_with_viewport() {
+        let camera = camera();
+
+        // 描画先の右下に置いた描画領域(S2DCamera::screen_rectと同じ扱い)
+        let origin = [400., 250.];
+        let size = [320, 180];
+        for screen in [[400., 250.], [560., 340.], [719., 429.], [450., 400.]] {
+            let local = [screen[0] - origin[0], screen[1] - origin[1]];
+            let world = camera.screen_to_world(local, size);
+            let back = camera.world_to_screen(world, size);
+            assert_near(
+                [back.x + origin[0], back.y + origin[1]].into(), 
+                screen.into(), 
+            );
+        }
+
+        // 描画領域の中心はカメラの位置
+        assert_near(camera.screen_to_world([160., 90.], size), camera.position);
+
+        // 描画領域の左上はNDCの(-1, 1)
+        assert_near(
+            camera.world_to_screen(camera.ndc_to_world([-1., 1.]), size), 
+            [0., 0.].into(), 
+        );
+    }
+
+    #[test]
+    fn rotation_and_zoom() {
+        let camera = Camera {
+            position: [0., 0.].into(), 
+            size: [200., 100.].into(), 
+            zoom: 2., 
+            rotation: std::f32::consts::FRAC_PI_2, 
+        };
+
+        // 90度回したカメラでは、ワールドの+y方向が画面の右になる
+        let ndc = camera.world_to_ndc([0., 25.]);
+        assert_near(ndc, [0.5, 0.].into());
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        for v in 0..=255u8 {
+            assert_eq!(linear_to_srgb(srgb_to_linear(v)), v);
+        }
+
+        // sRGBの中間値は線形色空間では半分より暗い
+        assert!(srgb_to_linear(128) < 0.25);
+    }
+
+    #[test]
+    fn premultiply() {
+        let mut image = image::RgbaImage::from_raw(3, 1, vec![
+            255, 255, 255, 255, 
+            255, 128, 0, 0, 
+            255, 255, 255, 128, 
+        ]).unwrap();
+        premultiply_alpha(&mut image);
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0, 0]);
+
+        // 線形色空間で半分の明るさになる
+        let p = image.get_pixel(2, 0).0;
+        assert!((srgb_to_linear(p[0]) - 128. / 255.).abs() < 0.01);
+        assert_eq!(p[3], 128);
+    }
+}
@@ -15,10 +15,16 @@ use super::{
     super::rev_ref::RevRefContainer, 
 };
 pub mod bl;
+pub mod packed;
 pub mod prelude {
     pub use super::bl::{
         BLInserter, 
         BLInserterInitializer, 
         error as bl_error, 
     };
+    pub use super::packed::{
+        PackedInserter, 
+        PackedInserterInitializer, 
+        error as packed_error, 
+    };
 }
\ No newline at end of file
@@ -0,0 +1,251 @@
+//! 配置済みの領域をそのまま登録するインサータ
+//! 
+//! AsepriteやTexturePackerなどが出力した、既に詰め込まれたアトラスを
+//! 扱うためのものです。配置は行わないため、領域の登録は
+//! `Atlas::insert_region`で行います。
+
+use std::hash::Hash;
+
+pub mod error {
+    /// 挿入処理に失敗
+    #[derive(Debug, Clone)]
+    pub enum PackedInsertError {
+        KeyDuplicate, 
+        NotPacked, 
+        OutOfBounds, 
+    }
+
+    /// 除去処理に失敗
+    #[derive(Debug, Clone)]
+    pub enum PackedRemoveError {
+        EntryNotExist, 
+    }
+
+    impl std::fmt::Display for PackedInsertError {
+        fn fmt(
+            &self, 
+            f: &mut std::fmt::Formatter<'_>
+        ) -> std::fmt::Result { f.write_fmt(match self {
+            PackedInsertError::KeyDuplicate => format_args!(
+                "Insert key duplicate"
+            ), 
+            PackedInsertError::NotPacked => format_args!(
+                "Insert data must have a packed region"
+            ), 
+            PackedInsertError::OutOfBounds => format_args!(
+                "Insert region is out of the atlas"
+            ), 
+        })}
+    }
+    impl std::error::Error for PackedInsertError {}
+
+    impl std::fmt::Display for PackedRemoveError {
+        fn fmt(
+            &self, 
+            f: &mut std::fmt::Formatter<'_>
+        ) -> std::fmt::Result { f.write_fmt(match self {
+            PackedRemoveError::EntryNotExist => format_args!(
+                "Entry is not exist"
+            ), 
+        })}
+    }
+    impl std::error::Error for PackedRemoveError {}
+}
+
+/// # 配置済みの領域を登録するインサータのイニシャライザ
+#[derive(Debug)]
+pub struct PackedInserterInitializer;
+impl<
+    const BL: usize, 
+    P: Copy, 
+    K: Eq + Hash, 
+    T, 
+> super::AtlasControllerInitializer<
+    BL, 
+    P, 
+    K, 
+    T, 
+> for PackedInserterInitializer {
+    type Initialized = PackedInserter;
+    type InitError = &'static str;
+
+    fn initialize(
+        self, 
+        _size: super::SqSize, 
+        _memory: &mut super::AtlasMem<BL, P>, 
+    ) -> Result<Self::Initialized, Self::InitError> { Ok(PackedInserter) }
+}
+
+/// # 配置済みの領域を登録するインサータ
+/// 
+/// 大きさを指定した挿入は`NotPacked`で失敗します。
+#[derive(Debug)]
+pub struct PackedInserter;
+impl<
+    const BL: usize, 
+    P: Copy, 
+    K: Eq + Hash, 
+    T, 
+> super::AtlasController<BL, P, K, T> for PackedInserter {
+    type InsertError = error::PackedInsertError;
+    type RemoveError = error::PackedRemoveError;
+    type ControllerElemData = ();
+
+    fn insert<
+        'a, 
+        'b, 
+        Q: Eq + Hash + ?Sized + ToOwned<Owned = K>, 
+    >(
+        &mut self, 
+        _atlas: &mut super::AtlasMem<BL, P>, 
+        elem: &'a mut super::RevRefContainer<
+            K, 
+            super::AtlasElem<T, Self::ControllerElemData>
+        >, 
+        key: &'b Q, 
+        size: Option<super::SqSize>, 
+    ) -> Result<
+        super::super::super::rev_ref::LazyInserter<
+            'a, 
+            'b, 
+            K, 
+            super::AtlasElem<T, Self::ControllerElemData>, 
+            (Option<super::AtlasMemParam>, Self::ControllerElemData), 
+            T, 
+            Q, 
+        >, 
+        Self::InsertError
+    > where
+        K: std::borrow::Borrow<Q>, 
+    {
+        if size.is_some() {
+            return Err(error::PackedInsertError::NotPacked)
+        }
+        elem.insert_lazy(
+            key, 
+            (None, ())
+        ).map_err(|_| error::PackedInsertError::KeyDuplicate)
+    }
+
+    fn remove(
+        &mut self, 
+        _atlas: &mut super::AtlasMem<BL, P>, 
+        elem: &mut super::RevRefContainer<
+            K, 
+            super::AtlasElem<T, Self::ControllerElemData>
+        >, 
+        id: usize, 
+    ) -> Result<
+        (T, K, Option<super::AtlasMemParam>), 
+        Self::RemoveError
+    > {
+        let (k, e) = elem.remove(id)
+            .ok_or(error::PackedRemoveError::EntryNotExist)?;
+        Ok((e.ud, k, e.memp))
+    }
+}
+
+impl<
+    const BL: usize, 
+    P: Copy, 
+    K: Eq + Hash, 
+    T, 
+> super::super::Atlas<BL, P, K, T, PackedInserter> {
+    /// 配置済みの領域の登録
+    /// 
+    /// インサータによる配置を行わず、`memp`の位置へそのまま要素を登録します。
+    /// 領域同士の重なりは確認しません。
+    #[allow(clippy::type_complexity)]
+    pub fn insert_region<
+        'a, 
+        Q, 
+    >(
+        &mut self, 
+        key: &'a Q, 
+        memp: super::AtlasMemParam, 
+    ) -> Result<(
+        super::super::super::rev_ref::LazyInserter<
+            '_, 
+            'a, 
+            K, 
+            super::AtlasElem<T, ()>, 
+            (Option<super::AtlasMemParam>, ()), 
+            T, 
+            Q, 
+        >, 
+        impl Iterator<Item = (usize, &mut [P])>, 
+    ), error::PackedInsertError> where
+        Q: Eq + Hash + ?Sized + ToOwned<Owned = K>, 
+        K: std::borrow::Borrow<Q>, 
+    {
+        let size = self.memory.size;
+        if (0..2).any(|i| {
+            memp.pos.raw()[i] as u64 + memp.size.raw()[i].get() as u64
+                > size.raw()[i].get() as u64
+        }) {
+            return Err(error::PackedInsertError::OutOfBounds)
+        }
+        let li = self.elem.insert_lazy(
+            key, 
+            (Some(memp), ())
+        ).map_err(|_| error::PackedInsertError::KeyDuplicate)?;
+        Ok((li, self.memory.get_obj_mut(&memp)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::{
+        Atlas, 
+        types::{SqPos, SqSize}, 
+        elem::AtlasMemParam, 
+    };
+
+    fn region(pos: [u32; 2], size: [u32; 2]) -> AtlasMemParam {
+        AtlasMemParam {
+            pos: SqPos::from(pos), 
+            size: SqSize::new_checked(size).unwrap(), 
+        }
+    }
+
+    #[test]
+    fn insert_region_keeps_exported_rect() {
+        let mut atlas: Atlas<1, u8, String, (), PackedInserter> = Atlas::new(
+            SqSize::new_checked([4, 4]).unwrap(), 
+            PackedInserterInitializer, 
+            0, 
+        ).unwrap();
+        let (li, iter) = atlas.insert_region("a", region([2, 1], [2, 2])).unwrap();
+        let id = li.idx();
+        li.insert(());
+        drop(iter);
+
+        let amp = atlas.get_amp(id as u32).unwrap();
+        assert_eq!(<[u32; 2]>::from(amp.pos), [2, 1]);
+        assert_eq!(<[u32; 2]>::from(amp.size), [2, 2]);
+    }
+
+    #[test]
+    fn insert_region_rejects_invalid() {
+        let mut atlas: Atlas<1, u8, String, (), PackedInserter> = Atlas::new(
+            SqSize::new_checked([4, 4]).unwrap(), 
+            PackedInserterInitializer, 
+            0, 
+        ).unwrap();
+        assert!(matches!(
+            atlas.insert_region("a", region([3, 0], [2, 1])), 
+            Err(error::PackedInsertError::OutOfBounds), 
+        ));
+        let (li, _) = atlas.insert_region("a", region([0, 0], [1, 1])).unwrap();
+        li.insert(());
+        assert!(matches!(
+            atlas.insert_region("a", region([1, 0], [1, 1])), 
+            Err(error::PackedInsertError::KeyDuplicate), 
+        ));
+        assert!(matches!(
+            atlas.insert("b", SqSize::new_checked([1, 1])), 
+            Err(error::PackedInsertError::NotPacked), 
+        ));
+    }
+}
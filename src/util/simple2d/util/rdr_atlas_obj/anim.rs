@@ -0,0 +1,360 @@
+//! アトラスを用いたスプライトアニメーション
+//! 
+//! フレームの並びをクリップとして定義し、`Animator`で再生します。
+//! `Animator::update`には`CycleMeasure::dur`を渡して毎フレーム呼び出し、
+//! `Animator::instance`で描画するインスタンスを得ます。
+
+use std::sync::Arc;
+
+use super::*;
+use super::instance::AtlasObjInstance;
+use super::super::super::MAX_DT;
+
+/// 再生の方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimMode {
+    /// 最後のフレームで停止する
+    Once, 
+
+    /// 最初のフレームへ戻って繰り返す
+    #[default]
+    Loop, 
+
+    /// 最後のフレームで折り返して繰り返す
+    PingPong, 
+}
+
+/// アニメーションの1フレーム
+#[derive(Debug, Clone)]
+pub struct AnimFrame<K, E> {
+    /// アトラスのキー
+    pub key: K, 
+
+    /// テクスチャ内部での座標(ピクセル)
+    pub tex_coord: nalgebra::Point2<f32>, 
+
+    /// テクスチャ内部での大きさ(ピクセル)
+    pub tex_size: nalgebra::Vector2<f32>, 
+
+    /// 表示する時間(秒)
+    pub duration: f32, 
+
+    /// フレームに入った時に発生するイベント
+    pub event: Option<E>, 
+}
+
+/// アニメーションのクリップ
+#[derive(Debug, Clone)]
+pub struct AnimClip<K, E = ()> {
+    pub frames: Vec<AnimFrame<K, E>>, 
+    pub mode: AnimMode, 
+}
+impl<K, E> AnimClip<K, E> {
+    pub fn new(mode: AnimMode) -> Self { Self {
+        frames: Vec::new(), 
+        mode, 
+    }}
+
+    /// テクスチャ全体を表示するフレームの追加
+    /// 
+    /// `tex_size`にはテクスチャの大きさ(ピクセル)を指定します。
+    pub fn frame(
+        self, 
+        key: K, 
+        tex_size: impl Into<nalgebra::Vector2<f32>>, 
+        duration: f32, 
+    ) -> Self {
+        self.frame_rect(key, [0., 0.], tex_size, duration)
+    }
+
+    /// テクスチャの一部を表示するフレームの追加
+    pub fn frame_rect(
+        mut self, 
+        key: K, 
+        tex_coord: impl Into<nalgebra::Point2<f32>>, 
+        tex_size: impl Into<nalgebra::Vector2<f32>>, 
+        duration: f32, 
+    ) -> Self {
+        self.frames.push(AnimFrame {
+            key, 
+            tex_coord: tex_coord.into(), 
+            tex_size: tex_size.into(), 
+            duration, 
+            event: None, 
+        });
+        self
+    }
+
+    /// 直前に追加したフレームへのイベントの設定
+    pub fn with_event(mut self, event: E) -> Self {
+        if let Some(f) = self.frames.last_mut() { f.event = Some(event) }
+        self
+    }
+
+    /// 1周の再生時間(秒)
+    pub fn total_duration(&self) -> f32 {
+        self.frames.iter().map(|f| f.duration).sum()
+    }
+}
+
+/// アニメーションの再生器
+/// 
+/// クリップは`Arc`で共有され、複数の再生器から同時に再生できます。
+#[derive(Debug, Clone)]
+pub struct Animator<K, E = ()> {
+    clip: Arc<AnimClip<K, E>>, 
+    frame: usize, 
+    elapsed: f32, 
+    reverse: bool, 
+    finished: bool, 
+    events: Vec<E>, 
+
+    /// 再生速度の倍率
+    /// 
+    /// 逆再生には対応しておらず、負の値やNaNは0として扱います。
+    pub speed: f32, 
+
+    /// 再生中かどうか
+    pub playing: bool, 
+}
+impl<K, E: Clone> Animator<K, E> {
+    pub fn new(clip: Arc<AnimClip<K, E>>) -> Self {
+        let mut animator = Self {
+            clip, 
+            frame: 0, 
+            elapsed: 0., 
+            reverse: false, 
+            finished: false, 
+            events: Vec::new(), 
+            speed: 1., 
+            playing: true, 
+        };
+        animator.restart();
+        animator
+    }
+
+    /// クリップの切り替え
+    /// 
+    /// 再生中のクリップと同じ場合は何もしません。
+    pub fn play(&mut self, clip: &Arc<AnimClip<K, E>>) {
+        if Arc::ptr_eq(&self.clip, clip) { return }
+        self.clip = clip.clone();
+        self.restart();
+    }
+
+    /// 最初のフレームからの再生
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.;
+        self.reverse = false;
+        self.finished = false;
+        self.playing = true;
+        self.events.clear();
+        self.enter_frame();
+    }
+
+    pub fn clip(&self) -> &Arc<AnimClip<K, E>> { &self.clip }
+
+    /// 現在のフレームの番号
+    pub fn frame_index(&self) -> usize { self.frame }
+
+    /// 現在のフレーム
+    pub fn frame(&self) -> Option<&AnimFrame<K, E>> { self.clip.frames.get(self.frame) }
+
+    /// `AnimMode::Once`の再生が終わったかどうか
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    /// 直前の`update`(または`restart`)で発生したイベント
+    pub fn events(&self) -> &[E] { &self.events }
+
+    /// 再生の進行
+    /// 
+    /// `dur`は経過時間(秒)で、`MAX_DT`秒を上限とします。
+    /// 長い経過時間では複数のフレームを進め、通過したフレームのイベントを記録します。
+    /// ただし繰り返しの再生で1周を超えた分は読み飛ばすため、
+    /// その間のイベントは記録されません。
+    pub fn update(&mut self, dur: f32) {
+        self.events.clear();
+        let len = self.clip.frames.len();
+        if !self.playing || self.finished || len == 0 { return }
+
+        self.elapsed += dur.clamp(0., MAX_DT) * self.speed.max(0.);
+        if !self.elapsed.is_finite() { self.elapsed = 0. }
+        // 1周分を超える経過は読み飛ばす(繰り返しの場合のみ)
+        let frames = &self.clip.frames;
+        let cycle = match self.clip.mode {
+            AnimMode::PingPong if 1 < len => self.clip.total_duration() * 2. 
+                - frames[0].duration 
+                - frames[len - 1].duration, 
+            _ => self.clip.total_duration(), 
+        };
+        if self.clip.mode != AnimMode::Once {
+            if cycle <= 0. { return }
+            if cycle < self.elapsed { self.elapsed %= cycle }
+        }
+
+        loop {
+            let duration = self.clip.frames[self.frame].duration;
+            if self.elapsed < duration { break }
+            self.elapsed -= duration.max(0.);
+            if !self.advance(len) {
+                self.elapsed = 0.;
+                break
+            }
+        }
+    }
+
+    /// 次のフレームへ進める
+    /// 
+    /// 再生が終わった場合は`false`を返します。
+    fn advance(&mut self, len: usize) -> bool {
+        match self.clip.mode {
+            AnimMode::Once => {
+                if self.frame + 1 < len {
+                    self.frame += 1;
+                } else {
+                    self.finished = true;
+                    return false
+                }
+            }, 
+            AnimMode::Loop => self.frame = (self.frame + 1) % len, 
+            AnimMode::PingPong => {
+                if len == 1 {
+                    self.frame = 0;
+                } else if self.reverse {
+                    if self.frame == 0 {
+                        self.reverse = false;
+                        self.frame = 1;
+                    } else {
+                        self.frame -= 1;
+                    }
+                } else if self.frame + 1 == len {
+                    self.reverse = true;
+                    self.frame -= 1;
+                } else {
+                    self.frame += 1;
+                }
+            }, 
+        }
+        self.enter_frame();
+        true
+    }
+
+    fn enter_frame(&mut self) {
+        if let Some(e) = self.clip.frames.get(self.frame).and_then(|f| f.event.clone()) {
+            self.events.push(e)
+        }
+    }
+}
+impl<K, E> Animator<K, E> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+{
+    /// 描画するインスタンスの生成
+    /// 
+    /// `base`の位置・大きさ・回転・反転・奥行きはそのままに、
+    /// 現在のフレームのテクスチャを設定します。
+    /// フレームのキーがアトラスに無い場合は`None`を返します。
+    pub fn instance<I>(
+        &self, 
+        module: &AtlasRenderingModule<K, I>, 
+        base: AtlasObjInstance, 
+    ) -> Option<AtlasObjInstance> where
+        I: AtlasController<
+            4, 
+            u8, 
+            K, 
+            AtlasElemParam, 
+        > + Send + Sync, 
+    {
+        let frame = self.clip.frames.get(self.frame)?;
+        Some(AtlasObjInstance {
+            atlas_id: module.atlas.get_id(&frame.key)? as usize, 
+            tex_coord: frame.tex_coord, 
+            tex_size: frame.tex_size, 
+            ..base
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(mode: AnimMode) -> Arc<AnimClip<&'static str, u32>> {
+        Arc::new(AnimClip::new(mode)
+            .frame("a", [8., 8.], 0.1)
+            .with_event(0)
+            .frame("b", [8., 8.], 0.1)
+            .frame("c", [8., 8.], 0.1)
+            .with_event(2))
+    }
+
+    /// `dur`ずつ進めた時のフレームの番号の一覧
+    fn frames(animator: &mut Animator<&'static str, u32>, dur: f32, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                animator.update(dur);
+                animator.frame_index()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn once_stops_at_last_frame() {
+        let mut animator = Animator::new(clip(AnimMode::Once));
+        assert_eq!(frames(&mut animator, 0.1, 4), [1, 2, 2, 2]);
+        assert!(animator.is_finished());
+
+        animator.restart();
+        assert!(!animator.is_finished());
+        assert_eq!(animator.frame_index(), 0);
+    }
+
+    #[test]
+    fn pingpong_turns_around() {
+        let mut animator = Animator::new(clip(AnimMode::PingPong));
+        assert_eq!(frames(&mut animator, 0.1, 6), [1, 2, 1, 0, 1, 2]);
+        assert!(!animator.is_finished());
+    }
+
+    #[test]
+    fn events_on_enter() {
+        let mut animator = Animator::new(clip(AnimMode::Loop));
+        assert_eq!(animator.events(), [0]);
+        animator.update(0.1);
+        assert!(animator.events().is_empty());
+
+        // 複数のフレームを進めた場合は通過したフレームのイベントを全て記録する
+        animator.update(0.2);
+        assert_eq!(animator.frame_index(), 0);
+        assert_eq!(animator.events(), [2, 0]);
+    }
+
+    #[test]
+    fn loop_wraps_long_duration() {
+        let clip = Arc::new(AnimClip::new(AnimMode::Loop)
+            .frame("a", [8., 8.], 0.05)
+            .with_event(0)
+            .frame("b", [8., 8.], 0.05)
+            .frame("c", [8., 8.], 0.05)
+            .with_event(2));
+        let mut animator = Animator::new(clip);
+
+        // 1周(0.15秒)を超えた分は読み飛ばし、残りの0.075秒だけ進める
+        animator.update(0.225);
+        assert_eq!(animator.frame_index(), 1);
+        assert!(animator.events().is_empty());
+    }
+
+    #[test]
+    fn negative_speed_pauses() {
+        for speed in [-1., f32::NAN, f32::INFINITY] {
+            let mut animator = Animator::new(clip(AnimMode::Loop));
+            animator.speed = speed;
+            animator.update(0.1);
+            animator.speed = 1.;
+            animator.update(0.1);
+            assert_eq!(animator.frame_index(), 1, "{speed}");
+        }
+    }
+}
@@ -1,4 +1,8 @@
 use super::*;
+use super::super::atlas::{
+    elem::AtlasMemParam, 
+    inserter::packed::PackedInserter, 
+};
 impl<K, I> AtlasRenderingModule<K, I> where
     K: Eq + Hash + Send + Sync + Sized + 'static, 
     I: AtlasController<
@@ -34,6 +38,70 @@ impl<K, I> AtlasRenderingModule<K, I> where
             AtlasElemParam, 
             Initialized = I, 
         >, 
+    {
+        Self::insert_image_raw(atlas, key, image).map_err(|e| e.into())
+    }
+
+    /// 画像の追加
+    /// 
+    /// 同じキーの画像が既にある場合は追加せずにその番号を返します。
+    /// 追加した画像は次の`update`でテクスチャへ反映されます。
+    pub fn insert_image<Q, C>(
+        &mut self, 
+        key: &Q, 
+        image: image::ImageBuffer<
+            image::Rgba<u8>, C
+        >
+    ) -> Result<usize, error::InsertImageError<K, I>> where
+        Q: Eq + Hash + ?Sized + ToOwned<Owned = K>, 
+        K: std::borrow::Borrow<Q>, 
+        C: std::ops::Deref<Target = [u8]>, 
+    {
+        if let Some(id) = self.atlas.get_id(key) {
+            return Ok(id as usize)
+        }
+        let id = Self::insert_image_raw(&mut self.atlas, key, image)?;
+        self.atlas_modified = true;
+        Ok(id)
+    }
+
+    /// 乗算済みアルファへの変換の有無を指定した画像の追加
+    /// 
+    /// `BlendMode::Premultiplied`で描画する場合は`premultiply`を有効にします。
+    pub fn insert_image_with<Q, C>(
+        &mut self, 
+        key: &Q, 
+        mut image: image::ImageBuffer<
+            image::Rgba<u8>, C
+        >, 
+        premultiply: bool, 
+    ) -> Result<usize, error::InsertImageError<K, I>> where
+        Q: Eq + Hash + ?Sized + ToOwned<Owned = K>, 
+        K: std::borrow::Borrow<Q>, 
+        C: std::ops::DerefMut<Target = [u8]>, 
+    {
+        if premultiply && self.atlas.get_id(key).is_none() {
+            super::super::super::types::premultiply_alpha(&mut image)
+        }
+        self.insert_image(key, image)
+    }
+
+    fn insert_image_raw<Q, C>(
+        atlas: &mut Atlas<
+            4, 
+            u8, 
+            K, 
+            AtlasElemParam, 
+            I, 
+        >, 
+        key: &Q, 
+        image: image::ImageBuffer<
+            image::Rgba<u8>, C
+        >
+    ) -> Result<usize, error::InsertImageError<K, I>> where
+        Q: Eq + Hash + ?Sized + ToOwned<Owned = K>, 
+        K: std::borrow::Borrow<Q>, 
+        C: std::ops::Deref<Target = [u8]>, 
     {
         let atlas_size = atlas.size();
         let (
@@ -43,17 +111,13 @@ impl<K, I> AtlasRenderingModule<K, I> where
             key, 
             Some(SqSize::from([
                 std::num::NonZeroU32::new(image.width())
-                    .ok_or(error::RdrInitError::OtherError(
-                        "image width is below zero.".into()
-                    ))?, 
+                    .ok_or(error::InsertImageError::EmptyImage)?, 
                 std::num::NonZeroU32::new(image.height())
-                    .ok_or(error::RdrInitError::OtherError(
-                        "image height is below zero.".into()
-                    ))?, 
+                    .ok_or(error::InsertImageError::EmptyImage)?, 
             ]))
         ).map_err(|
             e
-        | error::RdrInitError::AtlasInsertionError(e))?;
+        | error::InsertImageError::AtlasInsertionError(e))?;
 
         let id = lazy_inserter.idx();
 
@@ -61,19 +125,7 @@ impl<K, I> AtlasRenderingModule<K, I> where
             .as_ref()
             .unwrap()
             .clone();
-        lazy_inserter.insert(super::AtlasElemParam {
-            texture_size: std::array::from_fn(|
-                i
-            | (amp.size.raw()[i].get() as f32).recip()).into(),
-            in_atras: (
-                std::array::from_fn(|
-                    i
-                | amp.pos.raw()[i] as f32 / atlas_size.raw()[i].get() as f32).into(), 
-                std::array::from_fn(|
-                    i
-                | amp.size.raw()[i].get() as f32 / atlas_size.raw()[i].get() as f32).into(), 
-            ),
-        });
+        lazy_inserter.insert(elem_param(&amp, atlas_size));
 
         for (i, pix) in iter.unwrap() {
             (0..4)
@@ -83,4 +135,67 @@ impl<K, I> AtlasRenderingModule<K, I> where
 
         Ok(id)
     }
-}
\ No newline at end of file
+}
+impl<K> AtlasRenderingModule<K, PackedInserter> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+{
+    /// 配置済みの領域への画像の追加
+    /// 
+    /// インサータによる配置を行わず、アトラス上の`pos`の位置へ
+    /// 画像をそのまま書き込みます。
+    /// 追加した画像は次の`update`でテクスチャへ反映されます。
+    pub fn insert_region<Q, C>(
+        &mut self, 
+        key: &Q, 
+        pos: [u32; 2], 
+        image: image::ImageBuffer<
+            image::Rgba<u8>, C
+        >
+    ) -> Result<usize, error::InsertImageError<K, PackedInserter>> where
+        Q: Eq + Hash + ?Sized + ToOwned<Owned = K>, 
+        K: std::borrow::Borrow<Q>, 
+        C: std::ops::Deref<Target = [u8]>, 
+    {
+        let atlas_size = self.atlas.size();
+        let amp = AtlasMemParam {
+            pos: pos.into(), 
+            size: SqSize::new_checked([image.width(), image.height()])
+                .ok_or(error::InsertImageError::EmptyImage)?, 
+        };
+        let (
+            lazy_inserter, 
+            iter, 
+        ) = self.atlas.insert_region(key, amp)
+            .map_err(error::InsertImageError::AtlasInsertionError)?;
+
+        let id = lazy_inserter.idx();
+        lazy_inserter.insert(elem_param(&amp, atlas_size));
+
+        for (i, pix) in iter {
+            (0..4)
+                .map(|j| (j, i * 4 + j))
+                .for_each(|(j, k)| pix[j] = image.as_raw()[k])
+        }
+        self.atlas_modified = true;
+
+        Ok(id)
+    }
+}
+
+/// アトラス上の領域からの要素ごとのパラメータの計算
+fn elem_param(
+    amp: &AtlasMemParam, 
+    atlas_size: SqSize, 
+) -> super::AtlasElemParam { super::AtlasElemParam {
+    texture_size: std::array::from_fn(|
+        i
+    | (amp.size.raw()[i].get() as f32).recip()).into(),
+    in_atras: (
+        std::array::from_fn(|
+            i
+        | amp.pos.raw()[i] as f32 / atlas_size.raw()[i].get() as f32).into(), 
+        std::array::from_fn(|
+            i
+        | amp.size.raw()[i].get() as f32 / atlas_size.raw()[i].get() as f32).into(), 
+    ),
+}}
\ No newline at end of file
@@ -23,11 +23,16 @@ struct InstanceInput {
     @location(9) tex_size: vec2<f32>, 
     @location(10) atlas_obj_coord: vec2<f32>, 
     @location(11) atlas_obj_size: vec2<f32>, 
+    @location(12) z: f32, 
+    @location(13) tint: vec4<f32>, 
+    @location(14) flash: vec4<f32>, 
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>, 
     @location(0) tex_coords: vec2<f32>,  
+    @location(1) tint: vec4<f32>, 
+    @location(2) flash: vec4<f32>, 
 }
 
 @vertex
@@ -81,7 +86,15 @@ fn vs_main(
         1., 
     );
     pos_temp *= vec4<f32>(camera.size.x, camera.size.y, 0., 1.);
-    out.clip_position = pos_temp;
+
+    // 奥行きを深度へ変換(zは-1024から1024、大きいほど手前)
+    out.clip_position = vec4<f32>(
+        pos_temp.xy, 
+        clamp(0.5 - instance.z * (0.5 / 1024.), 0., 1.), 
+        1., 
+    );
+    out.tint = instance.tint;
+    out.flash = instance.flash;
 
     // 終了。
     return out;
@@ -97,5 +110,40 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
-}
\ No newline at end of file
+    let sample = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+
+    // 色の乗算と加算(加算は不透明度に影響しない)
+    let color = vec4<f32>(
+        clamp(sample.xyz * in.tint.xyz + in.flash.xyz * in.flash.w, vec3<f32>(0.), vec3<f32>(1.)), 
+        sample.w * in.tint.w, 
+    );
+
+    // 完全に透明な画素は深度を書き込まない
+    if color.w <= 0. {
+        discard;
+    }
+    return color;
+}
+
+// 乗算済みアルファのテクスチャ用
+@fragment
+fn fs_premultiplied(in: VertexOutput) -> @location(0) vec4<f32> {
+    let sample = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+
+    // 色・加算色とも不透明度を掛けて出力する
+    let alpha = sample.w * in.tint.w;
+    let color = vec4<f32>(
+        clamp(
+            sample.xyz * in.tint.xyz * in.tint.w + in.flash.xyz * in.flash.w * alpha, 
+            vec3<f32>(0.), 
+            vec3<f32>(alpha), 
+        ), 
+        alpha, 
+    );
+
+    // 完全に透明な画素は深度を書き込まない
+    if color.w <= 0. {
+        discard;
+    }
+    return color;
+}
@@ -79,4 +79,169 @@ impl<K, I, Ii> std::error::Error for RdrInitError<K, I, Ii> where
         AtlasElemParam, 
         Initialized = I, 
     > + Debug, 
-{}
\ No newline at end of file
+{}
+
+/// アトラスへの画像の追加のエラー
+#[derive(Debug)]
+pub enum InsertImageError<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    AtlasInsertionError(I::InsertError), 
+    EmptyImage, 
+}
+impl<K, I> std::fmt::Display for InsertImageError<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        InsertImageError::AtlasInsertionError(
+            e
+        ) => f.write_fmt(format_args!(
+            "atlas insertion error: {e}"
+        )), 
+        InsertImageError::EmptyImage => f.write_str(
+            "image size is zero."
+        ), 
+    }}
+}
+impl<K, I> std::error::Error for InsertImageError<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + Debug + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Debug, 
+{}
+impl<K, I, Ii> From<InsertImageError<K, I>> for RdrInitError<K, I, Ii> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+    Ii: AtlasControllerInitializer<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+        Initialized = I, 
+    >, 
+{
+    fn from(e: InsertImageError<K, I>) -> Self { match e {
+        InsertImageError::AtlasInsertionError(
+            e
+        ) => Self::AtlasInsertionError(e), 
+        InsertImageError::EmptyImage => Self::OtherError(
+            "image size is zero.".into()
+        ), 
+    }}
+}
+
+/// TiledのマップやスプライトシートなどのJSON形式の素材の読み込みのエラー
+#[derive(Debug)]
+pub enum LoadError<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    IOError(std::io::Error), 
+    ParseError(serde_json::Error), 
+    ImageError(image::ImageError), 
+    InsertImageError(InsertImageError<K, I>), 
+
+    /// 対応していない形式
+    Unsupported(&'static str), 
+}
+impl<K, I> std::fmt::Display for LoadError<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        LoadError::IOError(io) => f.write_fmt(format_args!(
+            "io error: {io}"
+        )), 
+        LoadError::ParseError(
+            e
+        ) => f.write_fmt(format_args!(
+            "json parse error: {e}"
+        )), 
+        LoadError::ImageError(
+            e
+        ) => f.write_fmt(format_args!(
+            "image error: {e}"
+        )), 
+        LoadError::InsertImageError(
+            e
+        ) => f.write_fmt(format_args!(
+            "{e}"
+        )), 
+        LoadError::Unsupported(
+            s
+        ) => f.write_fmt(format_args!(
+            "unsupported feature: {s}"
+        )), 
+    }}
+}
+impl<K, I> std::error::Error for LoadError<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + Debug + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Debug, 
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { match self {
+        LoadError::IOError(e) => Some(e), 
+        LoadError::ParseError(e) => Some(e), 
+        LoadError::ImageError(e) => Some(e), 
+        LoadError::InsertImageError(e) => Some(e), 
+        LoadError::Unsupported(_) => None, 
+    }}
+}
+
+/// タイルマップの生成のエラー
+#[derive(Debug, Clone)]
+pub enum TilemapError {
+    /// タイル数が`usize`で表せない
+    TooLarge, 
+}
+impl std::fmt::Display for TilemapError {
+    fn fmt(
+        &self, 
+        f: &mut std::fmt::Formatter<'_>, 
+    ) -> std::fmt::Result { match self {
+        TilemapError::TooLarge => f.write_str(
+            "tilemap size is too large."
+        ), 
+    }}
+}
+impl std::error::Error for TilemapError {}
\ No newline at end of file
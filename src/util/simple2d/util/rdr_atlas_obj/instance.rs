@@ -26,9 +26,12 @@ pub struct AtlasObjInstanceRaw {
     pub tex_size: [f32; 2], 
     pub atlas_object_coord: [f32; 2], 
     pub atlas_object_size: [f32; 2], 
+    pub z: f32, 
+    pub tint: [f32; 4], 
+    pub flash: [f32; 4], 
 }
 impl AtlasObjInstanceRaw {
-    const ATTRIB: [VertexAttribute; 7] = vertex_attr_array![
+    const ATTRIB: [VertexAttribute; 10] = vertex_attr_array![
         5 => Float32x2, 
         6 => Float32x2, 
         7 => Float32x2, 
@@ -36,6 +39,9 @@ impl AtlasObjInstanceRaw {
         9 => Float32x2, 
         10 => Float32x2, 
         11 => Float32x2, 
+        12 => Float32, 
+        13 => Float32x4, 
+        14 => Float32x4, 
     ];
 }
 impl InstanceRaw for AtlasObjInstanceRaw {
@@ -49,6 +55,7 @@ impl InstanceRaw for AtlasObjInstanceRaw {
 }
 
 /// アトラスを用いたオブジェクト描画のためのインスタンス型
+#[derive(Debug, Clone, Copy)]
 pub struct AtlasObjInstance {
     pub atlas_id: usize, 
     pub position: nalgebra::Point2<f32>, 
@@ -57,6 +64,30 @@ pub struct AtlasObjInstance {
     pub tex_rev: [bool; 2], 
     pub tex_coord: nalgebra::Point2<f32>, 
     pub tex_size: nalgebra::Vector2<f32>, 
+
+    /// 奥行き(大きいほど手前)
+    pub z: f32, 
+
+    /// 乗算する色(`[1., 1., 1., 1.]`でそのまま、wは不透明度)
+    pub tint: [f32; 4], 
+
+    /// 加算する色(wは加算する強さ、`[0., 0., 0., 0.]`で加算なし)
+    pub flash: [f32; 4], 
+}
+impl Default for AtlasObjInstance {
+    /// 不透明な白の`tint`・加算なしの`flash`で、その他は0
+    fn default() -> Self { Self {
+        atlas_id: 0, 
+        position: nalgebra::Point2::origin(), 
+        size: nalgebra::Vector2::zeros(), 
+        rotation: 0., 
+        tex_rev: [false, false], 
+        tex_coord: nalgebra::Point2::origin(), 
+        tex_size: nalgebra::Vector2::zeros(), 
+        z: 0., 
+        tint: [1., 1., 1., 1.], 
+        flash: [0., 0., 0., 0.], 
+    }}
 }
 impl<
     K: Eq + Hash + Send + Sync + Sized + 'static, 
@@ -98,6 +129,11 @@ impl<
             ),
             atlas_object_coord: (*atlas_coord).into(),
             atlas_object_size: (*atlas_size).into(),
+            z: self.z, 
+            tint: self.tint, 
+            flash: self.flash, 
         }
     }
+
+    fn z(&self) -> f32 { self.z }
 }
\ No newline at end of file
@@ -1,7 +1,6 @@
 use std::hash::Hash;
 use std::io::Read;
 
-use wgpu::Buffer;
 
 use super::atlas::{
     Atlas, 
@@ -10,16 +9,25 @@ use super::atlas::{
     types::SqSize, 
 };
 use super::super::types::Texture;
+use super::super::blend::{
+    BlendMode, 
+    PipelineCache, 
+};
 use super::super::instance::{
     InstanceRaw, 
     InstanceGen, 
     buffer::InstanceArray, 
+    batch::StaticBatchSet, 
 };
 
 pub mod instance;
 pub mod error;
 pub mod atlas_insert;
 pub mod shared;
+pub mod tilemap;
+pub mod tiled;
+pub mod anim;
+pub mod sheet;
 
 /// アトラスの要素ごとのパラメータ
 pub struct AtlasElemParam {
@@ -175,7 +183,13 @@ pub struct AtlasRenderer<K, I> where
         AtlasRenderingModule<K, I>, 
         instance::AtlasObjInstance, 
     >, 
-    instance_buffer: Buffer, 
+    batches: StaticBatchSet<
+        AtlasRenderingModule<K, I>, 
+        instance::AtlasObjInstance, 
+    >, 
+
+    /// 合成方法
+    blend: BlendMode, 
 }
 impl<K, I> AtlasRenderer<K, I> where
     K: Eq + Hash + Send + Sync + Sized + 'static, 
@@ -222,7 +236,7 @@ impl<K, I> AtlasRenderer<K, I> where
             image, 
         )?;
         let mut instances = InstanceArray::new();
-        let instance_buffer = instances.finish(
+        instances.finish(
             gfx_ctx, 
             &module
         );
@@ -230,7 +244,8 @@ impl<K, I> AtlasRenderer<K, I> where
         Ok((Self {
             module,
             instances,
-            instance_buffer,
+            batches: StaticBatchSet::new(), 
+            blend: BlendMode::default(), 
         }, image))
     }
 
@@ -253,6 +268,20 @@ impl<K, I> AtlasRenderer<K, I> where
     ) {
         instance.generate(&mut self.instances)
     }
+
+    /// 固定のインスタンスの集まりの一覧
+    pub fn batches(&mut self) -> &mut StaticBatchSet<
+        AtlasRenderingModule<K, I>, 
+        instance::AtlasObjInstance, 
+    > {
+        &mut self.batches
+    }
+
+    /// 合成方法
+    pub fn blend_mode(&self) -> BlendMode { self.blend }
+
+    /// 合成方法の設定
+    pub fn set_blend_mode(&mut self, mode: BlendMode) { self.blend = mode }
 }
 impl<
     K: Eq + Hash + Send + Sync + Sized + 'static, 
@@ -278,48 +307,85 @@ impl<
         camera: &crate::prelude::simple2d::shared::S2DCamera, 
         shared: Self::Shared<'a>, 
     ) {
-        self.instance_buffer = self.instances.finish(
+        self.rendering_multi(gfx, encoder, view, &[camera], shared)
+    }
+
+    fn rendering_multi<'a>(
+        &mut self, 
+        gfx: &crate::ctx::gfx::GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        cameras: &[&crate::prelude::simple2d::shared::S2DCamera], 
+        shared: Self::Shared<'a>, 
+    ) where
+        Self::Shared<'a>: Clone, 
+    {
+        self.instances.finish(
             &gfx.wgpu_ctx, 
             &self.module
         );
+        let Some(instance_buffer) = self.instances.buffer() else { return };
+        self.batches.upload(&gfx.wgpu_ctx, &self.module);
+
+        // カメラごとに描画領域を切り替えて描画
+        for camera in cameras {
+            let mut render_pass = encoder.begin_render_pass(
+                &wgpu::RenderPassDescriptor {
+                    label: Some("render pass"), 
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
+                        view, 
+                        resolve_target: None, 
+                        ops: wgpu::Operations { 
+                            load: wgpu::LoadOp::Load, 
+                            store: true 
+                        } 
+                    })], 
+                    depth_stencil_attachment: camera.depth_attachment(), 
+                }
+            );
+            camera.apply_viewport(&mut render_pass);
+
+            render_pass.set_pipeline(
+                shared.2.pipeline(
+                    &gfx.wgpu_ctx.device, 
+                    self.blend, 
+                    camera.depth.is_some(), 
+                )
+            );
+            render_pass.set_bind_group(
+                0, 
+                &self.module.texture.bind_group, 
+                &[]
+            );
+            render_pass.set_bind_group(1, &camera.bg, &[]);
+            render_pass.set_vertex_buffer(
+                0, 
+                shared.0.vertex.slice(..)
+            );
+            render_pass.set_index_buffer(
+                shared.0.index.slice(..), wgpu::IndexFormat::Uint16
+            );
 
-        let mut render_pass = encoder.begin_render_pass(
-            &wgpu::RenderPassDescriptor {
-                label: Some("render pass"), 
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
-                    view, 
-                    resolve_target: None, 
-                    ops: wgpu::Operations { 
-                        load: wgpu::LoadOp::Load, 
-                        store: true 
-                    } 
-                })], 
-                depth_stencil_attachment: None, 
+            // 固定のインスタンスの描画
+            for (buffer, len) in self.batches.buffers() {
+                render_pass.set_vertex_buffer(1, buffer.slice(..));
+                render_pass.draw_indexed(
+                    0..super::super::raw::INDICES.len() as _, 
+                    0, 
+                    0..len
+                );
             }
-        );
 
-        render_pass.set_pipeline(&shared.2.pipeline);
-        render_pass.set_bind_group(
-            0, 
-            &self.module.texture.bind_group, 
-            &[]
-        );
-        render_pass.set_bind_group(1, &camera.bg, &[]);
-        render_pass.set_vertex_buffer(
-            0, 
-            shared.0.vertex.slice(..)
-        );
-        render_pass.set_vertex_buffer(
-            1, 
-            self.instance_buffer.slice(..)
-        );
-        render_pass.set_index_buffer(
-            shared.0.index.slice(..), wgpu::IndexFormat::Uint16
-        );
-        render_pass.draw_indexed(
-            0..super::super::raw::INDICES.len() as _, 
-            0, 
-            0..self.instances.len() as _
-        );
+            // 毎フレームのインスタンスの描画
+            render_pass.set_vertex_buffer(
+                1, 
+                instance_buffer.slice(..)
+            );
+            render_pass.draw_indexed(
+                0..super::super::raw::INDICES.len() as _, 
+                0, 
+                0..self.instances.len() as _
+            );
+        }
     }
 }
\ No newline at end of file
@@ -2,9 +2,19 @@ use super::*;
 
 /// アトラスを用いた描画構造体で共有される値
 pub struct AtlasObjRenderShared {
-    pub(super) pipeline: wgpu::RenderPipeline, 
+    pub(super) pipelines: PipelineCache, 
 }
 impl AtlasObjRenderShared {
+    /// 合成方法と深度バッファの有無に応じたパイプライン
+    pub(crate) fn pipeline(
+        &self, 
+        device: &wgpu::Device, 
+        mode: BlendMode, 
+        depth: bool, 
+    ) -> &wgpu::RenderPipeline {
+        self.pipelines.get(device, mode, depth)
+    }
+
     pub fn new(
         gfx: &crate::ctx::gfx::WGPUCtx, 
         camera: &super::super::super::S2DCamera, 
@@ -32,7 +42,12 @@ impl AtlasObjRenderShared {
         );
 
         // パイプラインの初期化
-        let pipeline = gfx.device.create_render_pipeline(
+        let format = gfx.config.format;
+        let pipelines = PipelineCache::new(&gfx.device, move |
+            device: &wgpu::Device, 
+            blend: BlendMode, 
+            depth_stencil: Option<wgpu::DepthStencilState>, 
+        | device.create_render_pipeline(
             &wgpu::RenderPipelineDescriptor {
                 label: Some("sample pipeline"), 
                 layout: Some(&pipeline_layout), 
@@ -46,10 +61,10 @@ impl AtlasObjRenderShared {
                 }, 
                 fragment: Some(wgpu::FragmentState {
                     module: &shader, 
-                    entry_point: "fs_main", 
+                    entry_point: blend.fragment_entry(), 
                     targets: &[Some(wgpu::ColorTargetState { 
-                        format: gfx.config.format, 
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING), 
+                        format, 
+                        blend: Some(blend.blend_state()), 
                         write_mask: wgpu::ColorWrites::all() 
                     })]
                 }), 
@@ -62,7 +77,7 @@ impl AtlasObjRenderShared {
                     polygon_mode: wgpu::PolygonMode::Fill, 
                     conservative: false, 
                 }, 
-                depth_stencil: None, 
+                depth_stencil, 
                 multisample: wgpu::MultisampleState {
                     count: 1, 
                     mask: !0, 
@@ -70,10 +85,10 @@ impl AtlasObjRenderShared {
                 }, 
                 multiview: None, 
             }
-        );
+        ));
 
         Self {
-            pipeline, 
+            pipelines, 
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,517 @@
+//! スプライトシートの読み込み
+//! 
+//! AsepriteとTexturePackerが出力するJSON(ハッシュ形式・配列形式)に対応します。
+//! 各フレームは`PackedInserter`のアトラスへ、出力された位置のまま
+//! それぞれのキーを持つ要素として登録されます。
+//! 回転して詰め込まれたフレームには対応していません。
+
+use std::path::Path;
+use hashbrown::HashMap;
+use serde::Deserialize;
+
+use super::*;
+use super::super::atlas::inserter::packed::PackedInserter;
+use super::anim::{AnimClip, AnimFrame, AnimMode};
+use super::instance::AtlasObjInstance;
+
+#[derive(Deserialize)]
+struct RawSheet {
+    frames: RawFrames, 
+    meta: RawMeta, 
+    #[serde(default)]
+    animations: Option<RawAnimations>, 
+}
+
+/// フレームの一覧(ハッシュ形式は出力順を保って読み込む)
+struct RawFrames(Vec<RawFrame>);
+impl<'de> Deserialize<'de> for RawFrames {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D, 
+    ) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = RawFrames;
+
+            fn expecting(
+                &self, 
+                f: &mut std::fmt::Formatter, 
+            ) -> std::fmt::Result { f.write_str("frame array or frame map") }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self, 
+                mut seq: A, 
+            ) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(frame) = seq.next_element()? { frames.push(frame) }
+                Ok(RawFrames(frames))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self, 
+                mut map: A, 
+            ) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+                while let Some((name, mut frame)) = map.next_entry::<String, RawFrame>()? {
+                    frame.filename = Some(name);
+                    frames.push(frame)
+                }
+                Ok(RawFrames(frames))
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// TexturePackerのアニメーションの一覧(出力順を保って読み込む)
+struct RawAnimations(Vec<(String, Vec<String>)>);
+impl<'de> Deserialize<'de> for RawAnimations {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D, 
+    ) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = RawAnimations;
+
+            fn expecting(
+                &self, 
+                f: &mut std::fmt::Formatter, 
+            ) -> std::fmt::Result { f.write_str("animation map") }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self, 
+                mut map: A, 
+            ) -> Result<Self::Value, A::Error> {
+                let mut animations = Vec::new();
+                while let Some(entry) = map.next_entry()? { animations.push(entry) }
+                Ok(RawAnimations(animations))
+            }
+        }
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawFrame {
+    filename: Option<String>, 
+    frame: RawRect, 
+    #[serde(default)]
+    rotated: bool, 
+    #[serde(rename = "spriteSourceSize")]
+    sprite_source_size: Option<RawRect>, 
+    #[serde(rename = "sourceSize")]
+    source_size: Option<RawSize>, 
+    duration: Option<f32>, 
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct RawRect {
+    x: f32, 
+    y: f32, 
+    w: f32, 
+    h: f32, 
+}
+impl RawRect {
+    fn into_rect(self) -> (nalgebra::Point2<f32>, nalgebra::Vector2<f32>) {
+        ([self.x, self.y].into(), [self.w, self.h].into())
+    }
+}
+
+#[derive(Deserialize)]
+struct RawSize {
+    w: f32, 
+    h: f32, 
+}
+
+#[derive(Deserialize)]
+struct RawMeta {
+    image: String, 
+    #[serde(rename = "frameTags", default)]
+    frame_tags: Vec<RawTag>, 
+    #[serde(default)]
+    slices: Vec<RawSlice>, 
+}
+
+#[derive(Deserialize)]
+struct RawTag {
+    name: String, 
+    from: usize, 
+    to: usize, 
+    #[serde(default)]
+    direction: String, 
+}
+
+#[derive(Deserialize)]
+struct RawSlice {
+    name: String, 
+    keys: Vec<RawSliceKey>, 
+}
+
+#[derive(Deserialize)]
+struct RawSliceKey {
+    frame: usize, 
+    bounds: RawRect, 
+    center: Option<RawRect>, 
+    pivot: Option<RawPoint>, 
+}
+
+#[derive(Deserialize)]
+struct RawPoint {
+    x: f32, 
+    y: f32, 
+}
+
+/// シートの1フレーム
+#[derive(Debug, Clone)]
+pub struct SheetFrame<K> {
+    pub name: String, 
+
+    /// アトラスでのキー
+    pub key: K, 
+
+    /// アトラスでの番号
+    pub atlas_id: usize, 
+
+    /// フレームの大きさ(ピクセル)
+    pub tex_size: nalgebra::Vector2<f32>, 
+
+    /// 余白を切り取る前の画像での位置(ピクセル)
+    pub offset: nalgebra::Vector2<f32>, 
+
+    /// 余白を切り取る前の画像の大きさ(ピクセル)
+    pub source_size: nalgebra::Vector2<f32>, 
+
+    /// 表示する時間(秒)
+    /// 
+    /// 時間が出力されない形式では`default_duration`になります。
+    pub duration: f32, 
+}
+
+/// フレームのタグ(アニメーション)
+#[derive(Debug, Clone)]
+pub struct SheetTag<K> {
+    pub name: String, 
+
+    /// 再生順のフレームのキーと表示する時間(秒)
+    pub frames: Vec<(K, f32)>, 
+    pub mode: AnimMode, 
+}
+
+/// スライスのキー
+#[derive(Debug, Clone)]
+pub struct SheetSliceKey {
+    /// このキーが有効になるフレームの番号
+    pub frame: usize, 
+
+    /// 範囲(座標と大きさ、ピクセル)
+    pub bounds: (nalgebra::Point2<f32>, nalgebra::Vector2<f32>), 
+
+    /// 9スライスの中央の範囲
+    pub center: Option<(nalgebra::Point2<f32>, nalgebra::Vector2<f32>)>, 
+    pub pivot: Option<nalgebra::Point2<f32>>, 
+}
+
+/// スライス
+#[derive(Debug, Clone)]
+pub struct SheetSlice {
+    pub name: String, 
+    pub keys: Vec<SheetSliceKey>, 
+}
+
+/// 読み込んだスプライトシート
+#[derive(Debug, Clone)]
+pub struct SpriteSheet<K> {
+    pub frames: Vec<SheetFrame<K>>, 
+    pub tags: Vec<SheetTag<K>>, 
+    pub slices: Vec<SheetSlice>, 
+    frame_names: HashMap<String, usize>, 
+    frame_keys: HashMap<K, usize>, 
+}
+impl<K: Clone + Eq + Hash> SpriteSheet<K> {
+    /// スプライトシートの読み込み
+    /// 
+    /// シート画像はJSONファイルからの相対パスで探し、各フレームを
+    /// フレーム名から`key`で得たキーで、出力された位置のままアトラスへ登録します。
+    /// アトラスはシート画像以上の大きさで作成しておく必要があります。
+    /// `default_duration`はフレームの表示時間が
+    /// 出力されない形式(TexturePacker)で用いる時間(秒)です。
+    pub fn load(
+        module: &mut AtlasRenderingModule<K, PackedInserter>, 
+        path: impl AsRef<Path>, 
+        key: impl FnMut(&str) -> K, 
+        default_duration: f32, 
+    ) -> Result<Self, error::LoadError<K, PackedInserter>> where
+        K: Send + Sync + 'static, 
+    {
+        let path = path.as_ref();
+        let fp = std::fs::File::open(path)
+            .map_err(error::LoadError::IOError)?;
+        let raw: RawSheet = serde_json::from_reader(std::io::BufReader::new(fp))
+            .map_err(error::LoadError::ParseError)?;
+
+        let image_path = path.parent()
+            .map(|dir| dir.join(&raw.meta.image))
+            .unwrap_or_else(|| raw.meta.image.clone().into());
+        let image = image::open(&image_path)
+            .map_err(error::LoadError::ImageError)?
+            .to_rgba8();
+
+        Self::from_raw(raw, key, default_duration, |key, [x, y, w, h]| module
+            .insert_region(
+                key, 
+                [x, y], 
+                image::imageops::crop_imm(&image, x, y, w, h).to_image(), 
+            )
+            .map_err(error::LoadError::InsertImageError)
+        )
+    }
+
+    /// 読み込んだJSONからの生成
+    /// 
+    /// `insert`はフレームのキーと領域(`[x, y, w, h]`)を受け取り、
+    /// アトラスへ登録した番号を返します。
+    fn from_raw(
+        raw: RawSheet, 
+        mut key: impl FnMut(&str) -> K, 
+        default_duration: f32, 
+        mut insert: impl FnMut(
+            &K, 
+            [u32; 4], 
+        ) -> Result<usize, error::LoadError<K, PackedInserter>>, 
+    ) -> Result<Self, error::LoadError<K, PackedInserter>> where
+        K: Send + Sync + 'static, 
+    {
+        // フレーム
+        let mut frames = Vec::with_capacity(raw.frames.0.len());
+        for (i, f) in raw.frames.0.into_iter().enumerate() {
+            if f.rotated {
+                return Err(error::LoadError::Unsupported("rotated frame"))
+            }
+            let name = f.filename.unwrap_or_else(|| i.to_string());
+            let key = key(&name);
+            let rect = [f.frame.x, f.frame.y, f.frame.w, f.frame.h]
+                .map(|v| v.max(0.) as u32);
+            let atlas_id = insert(&key, rect)?;
+            let tex_size = [rect[2] as f32, rect[3] as f32].into();
+            frames.push(SheetFrame {
+                name, 
+                key, 
+                atlas_id, 
+                tex_size, 
+                offset: f.sprite_source_size
+                    .map_or(nalgebra::Vector2::zeros(), |r| [r.x, r.y].into()), 
+                source_size: f.source_size
+                    .map_or(tex_size, |s| [s.w, s.h].into()), 
+                duration: f.duration.map_or(default_duration, |ms| ms / 1000.), 
+            })
+        }
+        let frame_names: HashMap<_, _> = frames.iter()
+            .enumerate()
+            .map(|(i, f)| (f.name.clone(), i))
+            .collect();
+        let frame_keys: HashMap<_, _> = frames.iter()
+            .enumerate()
+            .map(|(i, f)| (f.key.clone(), i))
+            .collect();
+        let entry = |i: usize| (frames[i].key.clone(), frames[i].duration);
+
+        // タグ
+        let mut tags: Vec<_> = raw.meta.frame_tags.into_iter()
+            .filter(|_| !frames.is_empty())
+            .map(|t| {
+                let to = t.to.min(frames.len() - 1);
+                let mut order: Vec<_> = (t.from.min(to)..=to).collect();
+                let mode = match t.direction.as_str() {
+                    "reverse" => {
+                        order.reverse();
+                        AnimMode::Loop
+                    }, 
+                    "pingpong" => AnimMode::PingPong, 
+                    "pingpong_reverse" => {
+                        order.reverse();
+                        AnimMode::PingPong
+                    }, 
+                    _ => AnimMode::Loop, 
+                };
+                SheetTag {
+                    name: t.name, 
+                    frames: order.into_iter().map(entry).collect(), 
+                    mode, 
+                }
+            })
+            .collect();
+        if let Some(animations) = raw.animations {
+            tags.extend(animations.0.into_iter().map(|(name, names)| SheetTag {
+                name, 
+                frames: names.iter()
+                    .filter_map(|n| frame_names.get(n).copied())
+                    .map(entry)
+                    .collect(), 
+                mode: AnimMode::Loop, 
+            }))
+        }
+
+        // スライス
+        let slices = raw.meta.slices.into_iter()
+            .map(|s| SheetSlice {
+                name: s.name, 
+                keys: s.keys.into_iter()
+                    .map(|k| SheetSliceKey {
+                        frame: k.frame, 
+                        bounds: k.bounds.into_rect(), 
+                        center: k.center.map(RawRect::into_rect), 
+                        pivot: k.pivot.map(|p| [p.x, p.y].into()), 
+                    })
+                    .collect(), 
+            })
+            .collect();
+
+        Ok(Self {
+            frames, 
+            tags, 
+            slices, 
+            frame_names, 
+            frame_keys, 
+        })
+    }
+
+    /// 名前によるフレームの番号の取得
+    pub fn frame_id(&self, name: &str) -> Option<usize> {
+        self.frame_names.get(name).copied()
+    }
+
+    pub fn frame(&self, name: &str) -> Option<&SheetFrame<K>> {
+        self.frames.get(self.frame_id(name)?)
+    }
+
+    /// キーによるフレームの取得
+    pub fn frame_by_key(&self, key: &K) -> Option<&SheetFrame<K>> {
+        self.frames.get(*self.frame_keys.get(key)?)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&SheetTag<K>> {
+        self.tags.iter().find(|t| t.name == name)
+    }
+
+    pub fn slice(&self, name: &str) -> Option<&SheetSlice> {
+        self.slices.iter().find(|s| s.name == name)
+    }
+
+    /// タグからのアニメーションのクリップの生成
+    pub fn clip<E>(&self, tag: &str) -> Option<AnimClip<K, E>> {
+        let tag = self.tag(tag)?;
+        Some(AnimClip {
+            frames: tag.frames.iter()
+                .filter_map(|(key, duration)| Some(AnimFrame {
+                    key: key.clone(), 
+                    tex_coord: [0., 0.].into(), 
+                    tex_size: self.frame_by_key(key)?.tex_size, 
+                    duration: *duration, 
+                    event: None, 
+                }))
+                .collect(), 
+            mode: tag.mode, 
+        })
+    }
+
+    /// フレームを表示するインスタンスの生成
+    /// 
+    /// `base`の位置・大きさ・回転・反転・奥行きはそのままに、
+    /// フレームのテクスチャを設定します。
+    pub fn instance(
+        &self, 
+        frame: usize, 
+        base: AtlasObjInstance, 
+    ) -> Option<AtlasObjInstance> {
+        let f = self.frames.get(frame)?;
+        Some(AtlasObjInstance {
+            atlas_id: f.atlas_id, 
+            tex_coord: [0., 0.].into(), 
+            tex_size: f.tex_size, 
+            ..base
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(json: &str) -> (SpriteSheet<String>, Vec<(String, [u32; 4])>) {
+        let raw: RawSheet = serde_json::from_str(json).unwrap();
+        let mut regions = Vec::new();
+        let sheet = SpriteSheet::from_raw(raw, |n| n.to_string(), 0.1, |k, r| {
+            regions.push((k.clone(), r));
+            Ok(regions.len() - 1)
+        }).ok().unwrap();
+        (sheet, regions)
+    }
+
+    #[test]
+    fn hash_frames_keep_order() {
+        let (sheet, regions) = build(r#"{
+            "frames": {
+                "walk 1": {"frame": {"x": 16, "y": 0, "w": 16, "h": 8}, "duration": 200}, 
+                "walk 0": {"frame": {"x": 0, "y": 0, "w": 16, "h": 8}, "duration": 100}
+            }, 
+            "meta": {
+                "image": "walk.png", 
+                "frameTags": [
+                    {"name": "walk", "from": 0, "to": 1, "direction": "pingpong_reverse"}
+                ]
+            }
+        }"#);
+        assert_eq!(regions, [
+            ("walk 1".to_string(), [16, 0, 16, 8]), 
+            ("walk 0".to_string(), [0, 0, 16, 8]), 
+        ]);
+        assert_eq!(sheet.frame_id("walk 0"), Some(1));
+        assert_eq!(sheet.frame("walk 1").unwrap().atlas_id, 0);
+
+        let tag = sheet.tag("walk").unwrap();
+        assert_eq!(tag.mode, AnimMode::PingPong);
+        assert_eq!(tag.frames, [
+            ("walk 0".to_string(), 0.1), 
+            ("walk 1".to_string(), 0.2), 
+        ]);
+    }
+
+    #[test]
+    fn array_frames_and_animations() {
+        let (sheet, regions) = build(r#"{
+            "frames": [
+                {"filename": "b", "frame": {"x": 0, "y": 4, "w": 2, "h": 2}, 
+                    "spriteSourceSize": {"x": 1, "y": 1, "w": 2, "h": 2}, 
+                    "sourceSize": {"w": 4, "h": 4}}, 
+                {"filename": "a", "frame": {"x": 0, "y": 0, "w": 4, "h": 4}}
+            ], 
+            "animations": {"run": ["a", "b", "missing", "a"], "idle": ["b"]}, 
+            "meta": {"image": "run.png"}
+        }"#);
+        assert_eq!(regions[0], ("b".to_string(), [0, 4, 2, 2]));
+        let b = sheet.frame_by_key(&"b".to_string()).unwrap();
+        assert_eq!(b.offset, nalgebra::Vector2::new(1., 1.));
+        assert_eq!(b.source_size, nalgebra::Vector2::new(4., 4.));
+
+        let names: Vec<_> = sheet.tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["run", "idle"]);
+        let keys: Vec<_> = sheet.tag("run").unwrap().frames.iter()
+            .map(|(k, d)| (k.as_str(), *d))
+            .collect();
+        assert_eq!(keys, [("a", 0.1), ("b", 0.1), ("a", 0.1)]);
+
+        let clip = sheet.clip::<()>("run").unwrap();
+        assert_eq!(clip.frames[1].tex_size, nalgebra::Vector2::new(2., 2.));
+    }
+
+    #[test]
+    fn rotated_frame_is_rejected() {
+        let raw: RawSheet = serde_json::from_str(r#"{
+            "frames": [{"frame": {"x": 0, "y": 0, "w": 1, "h": 1}, "rotated": true}], 
+            "meta": {"image": "a.png"}
+        }"#).unwrap();
+        assert!(matches!(
+            SpriteSheet::from_raw(raw, |n| n.to_string(), 0.1, |_, _| Ok(0)), 
+            Err(error::LoadError::Unsupported(_)), 
+        ));
+    }
+}
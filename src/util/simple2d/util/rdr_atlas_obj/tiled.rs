@@ -0,0 +1,768 @@
+//! TiledのJSON形式のマップの読み込み
+//! 
+//! `TiledMap::load`でタイルセットの画像をアトラスへ追加してマップを読み込み、
+//! `Tilemap::from_tiled`でタイルレイヤを`Tilemap`のレイヤにします。
+//! オブジェクトレイヤは`TiledObjectLayer`として読み込みます。
+//! 圧縮・base64形式のタイルデータ、無限マップ、画像集合のタイルセット、
+//! 対角方向に反転したタイルには対応していません。
+
+use std::path::{Path, PathBuf};
+use hashbrown::HashMap;
+use serde::Deserialize;
+
+use super::*;
+use super::tilemap::{Tile, Tilemap};
+use super::super::super::physic::{PhysicBody, PhysicRigidSquare};
+
+/// GIDの反転フラグ(水平・垂直・対角・六角形の回転)
+const GID_FLAG_MASK: u32 = 0xF000_0000;
+const GID_FLIP_H: u32 = 0x8000_0000;
+const GID_FLIP_V: u32 = 0x4000_0000;
+const GID_FLIP_D: u32 = 0x2000_0000;
+
+#[derive(Deserialize)]
+struct RawMap {
+    width: u32, 
+    height: u32, 
+    tilewidth: u32, 
+    tileheight: u32, 
+    #[serde(default)]
+    infinite: bool, 
+    layers: Vec<RawLayer>, 
+    #[serde(default)]
+    tilesets: Vec<RawTilesetRef>, 
+    #[serde(default)]
+    properties: Vec<RawProperty>, 
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum RawLayer {
+    #[serde(rename = "tilelayer")]
+    Tile {
+        #[serde(default)]
+        name: String, 
+        data: RawData, 
+        #[serde(default = "default_true")]
+        visible: bool, 
+        #[serde(default)]
+        properties: Vec<RawProperty>, 
+    }, 
+    #[serde(rename = "objectgroup")]
+    Object {
+        #[serde(default)]
+        name: String, 
+        objects: Vec<RawObject>, 
+        #[serde(default = "default_true")]
+        visible: bool, 
+        #[serde(default)]
+        properties: Vec<RawProperty>, 
+    }, 
+    #[serde(rename = "group")]
+    Group {
+        layers: Vec<RawLayer>, 
+    }, 
+    #[serde(other)]
+    Other, 
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawData {
+    Array(Vec<u32>), 
+    Encoded(serde::de::IgnoredAny), 
+}
+
+#[derive(Deserialize)]
+struct RawTilesetRef {
+    firstgid: u32, 
+    source: Option<String>, 
+    #[serde(flatten)]
+    inline: RawTileset, 
+}
+
+#[derive(Deserialize, Default)]
+struct RawTileset {
+    image: Option<String>, 
+    tilewidth: Option<u32>, 
+    tileheight: Option<u32>, 
+    columns: Option<u32>, 
+    #[serde(default)]
+    margin: u32, 
+    #[serde(default)]
+    spacing: u32, 
+}
+
+#[derive(Deserialize)]
+struct RawObject {
+    id: u32, 
+    #[serde(default)]
+    name: String, 
+    #[serde(rename = "type", alias = "class", default)]
+    class: String, 
+    x: f32, 
+    y: f32, 
+    #[serde(default)]
+    width: f32, 
+    #[serde(default)]
+    height: f32, 
+    #[serde(default)]
+    rotation: f32, 
+    #[serde(default)]
+    point: bool, 
+    #[serde(default)]
+    ellipse: bool, 
+    polygon: Option<Vec<RawPoint>>, 
+    polyline: Option<Vec<RawPoint>>, 
+    gid: Option<u32>, 
+    #[serde(default)]
+    properties: Vec<RawProperty>, 
+}
+
+#[derive(Deserialize)]
+struct RawPoint {
+    x: f32, 
+    y: f32, 
+}
+
+#[derive(Deserialize)]
+struct RawProperty {
+    name: String, 
+    #[serde(rename = "type", default)]
+    ty: String, 
+    value: serde_json::Value, 
+}
+
+fn default_true() -> bool { true }
+
+/// プロパティの値
+#[derive(Debug, Clone, PartialEq)]
+pub enum TiledProperty {
+    Bool(bool), 
+    Int(i64), 
+    Float(f64), 
+    String(String), 
+}
+impl TiledProperty {
+    fn from_raw(raw: RawProperty) -> (String, Self) {
+        let value = match (raw.ty.as_str(), raw.value) {
+            ("bool", serde_json::Value::Bool(b)) => Self::Bool(b), 
+            ("int" | "object", v) if v.is_i64() => Self::Int(v.as_i64().unwrap_or_default()), 
+            ("float", v) if v.is_number() => Self::Float(v.as_f64().unwrap_or_default()), 
+            (_, serde_json::Value::String(s)) => Self::String(s), 
+            (_, v) => Self::String(v.to_string()), 
+        };
+        (raw.name, value)
+    }
+
+    fn collect(raw: Vec<RawProperty>) -> HashMap<String, Self> {
+        raw.into_iter().map(Self::from_raw).collect()
+    }
+}
+
+/// オブジェクトの形状
+#[derive(Debug, Clone, PartialEq)]
+pub enum TiledShape {
+    Rectangle, 
+    Ellipse, 
+    Point, 
+
+    /// 多角形(頂点のワールド座標)
+    Polygon(Vec<nalgebra::Point2<f32>>), 
+
+    /// 折れ線(頂点のワールド座標)
+    Polyline(Vec<nalgebra::Point2<f32>>), 
+
+    /// タイルを表示するオブジェクト
+    Tile(Tile), 
+}
+
+/// オブジェクト
+/// 
+/// 位置は中心のワールド座標で、当たり判定にそのまま用いることができます。
+#[derive(Debug, Clone)]
+pub struct TiledObject {
+    pub id: u32, 
+    pub name: String, 
+    pub class: String, 
+    pub shape: TiledShape, 
+    pub position: nalgebra::Point2<f32>, 
+    pub size: nalgebra::Vector2<f32>, 
+    pub rotation: f32, 
+    pub properties: HashMap<String, TiledProperty>, 
+}
+impl PhysicBody for TiledObject {
+    fn position(&self) -> nalgebra::Point2<f32> { self.position }
+    fn size(&self) -> nalgebra::Vector2<f32> { self.size }
+    fn rotation(&self) -> f32 { self.rotation }
+    fn velocity(&self) -> nalgebra::Vector2<f32> { nalgebra::Vector2::zeros() }
+}
+impl PhysicRigidSquare for TiledObject {
+    fn position(&self) -> nalgebra::Point2<f32> { self.position }
+    fn size(&self) -> nalgebra::Vector2<f32> { self.size }
+}
+
+/// オブジェクトレイヤ
+#[derive(Debug, Clone)]
+pub struct TiledObjectLayer {
+    pub name: String, 
+    pub visible: bool, 
+    pub objects: Vec<TiledObject>, 
+    pub properties: HashMap<String, TiledProperty>, 
+}
+
+/// タイルレイヤ
+#[derive(Debug, Clone)]
+pub struct TiledTileLayer {
+    pub name: String, 
+
+    /// `Tilemap::from_tiled`で生成した`Tilemap`でのレイヤの番号
+    pub layer: usize, 
+    pub visible: bool, 
+
+    /// タイル(左上から行ごと)
+    pub tiles: Vec<Option<Tile>>, 
+    pub properties: HashMap<String, TiledProperty>, 
+}
+
+/// 読み込んだマップ
+#[derive(Debug, Clone)]
+pub struct TiledMap {
+    /// マップの大きさ(タイル数)
+    pub size: [u32; 2], 
+
+    /// タイル1枚の大きさ(ワールド座標)
+    pub tile_size: nalgebra::Vector2<f32>, 
+    pub tile_layers: Vec<TiledTileLayer>, 
+    pub object_layers: Vec<TiledObjectLayer>, 
+    pub properties: HashMap<String, TiledProperty>, 
+}
+
+/// 読み込み済みのタイルセット
+struct Tileset {
+    firstgid: u32, 
+    atlas_id: usize, 
+    tile_size: [u32; 2], 
+    columns: u32, 
+    margin: u32, 
+    spacing: u32, 
+}
+
+/// 座標の変換に用いる値
+struct Conv {
+    origin: nalgebra::Point2<f32>, 
+    scale: f32, 
+}
+impl Conv {
+    /// Tiledのピクセル座標(下向き)からワールド座標への変換
+    fn point(&self, x: f32, y: f32) -> nalgebra::Point2<f32> {
+        self.origin + nalgebra::Vector2::new(x * self.scale, -y * self.scale)
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>, K, I>(
+    path: &Path, 
+) -> Result<T, error::LoadError<K, I>> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    let fp = std::fs::File::open(path)
+        .map_err(error::LoadError::IOError)?;
+    serde_json::from_reader(std::io::BufReader::new(fp))
+        .map_err(error::LoadError::ParseError)
+}
+
+/// 内容が不正な場合の解析エラー
+fn invalid<K, I>(msg: &'static str) -> error::LoadError<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    error::LoadError::ParseError(<serde_json::Error as serde::de::Error>::custom(msg))
+}
+
+/// GIDからタイルへの変換
+/// 
+/// 対角方向の反転は`Tile`で表せないためエラーとします。
+fn resolve_gid<K, I>(
+    tilesets: &[Tileset], 
+    gid: u32, 
+) -> Result<Option<Tile>, error::LoadError<K, I>> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    if gid & GID_FLIP_D != 0 {
+        return Err(error::LoadError::Unsupported("diagonal flip"))
+    }
+    let id = gid & !GID_FLAG_MASK;
+    if id == 0 { return Ok(None) }
+    let Some(ts) = tilesets.iter().rev().find(|ts| ts.firstgid <= id) else {
+        return Ok(None)
+    };
+    let local = id - ts.firstgid;
+    let col = local % ts.columns;
+    let row = local / ts.columns;
+    Ok(Some(Tile::new(
+        ts.atlas_id, 
+        [
+            (ts.margin + col * (ts.tile_size[0] + ts.spacing)) as f32, 
+            (ts.margin + row * (ts.tile_size[1] + ts.spacing)) as f32, 
+        ], 
+        [ts.tile_size[0] as f32, ts.tile_size[1] as f32], 
+    ).flipped([gid & GID_FLIP_H != 0, gid & GID_FLIP_V != 0])))
+}
+
+impl Tileset {
+    /// タイルセットの情報からの生成
+    /// 
+    /// 列数が出力されていない場合は画像の幅から求めます。
+    /// アトラスでの番号は画像を追加した後に設定します。
+    fn new<K, I>(
+        firstgid: u32, 
+        raw: &RawTileset, 
+        image_width: u32, 
+    ) -> Result<Self, error::LoadError<K, I>> where
+        K: Eq + Hash + Send + Sync + Sized + 'static, 
+        I: AtlasController<
+            4, 
+            u8, 
+            K, 
+            AtlasElemParam, 
+        >, 
+    {
+        let (Some(tw), Some(th)) = (raw.tilewidth, raw.tileheight) else {
+            return Err(error::LoadError::Unsupported("image collection tileset"))
+        };
+        if tw == 0 || th == 0 {
+            return Err(invalid("tile size must not be zero"))
+        }
+        let columns = match raw.columns.filter(|c| 0 < *c) {
+            Some(columns) => columns, 
+            None => (image_width
+                .checked_sub(raw.margin.saturating_mul(2))
+                .ok_or_else(|| invalid("tileset margin exceeds image width"))?
+                .saturating_add(raw.spacing)
+                / tw.saturating_add(raw.spacing)
+            ).max(1), 
+        };
+        Ok(Self {
+            firstgid, 
+            atlas_id: 0, 
+            tile_size: [tw, th], 
+            columns, 
+            margin: raw.margin, 
+            spacing: raw.spacing, 
+        })
+    }
+}
+
+impl TiledMap {
+    /// TiledのJSON形式のマップの読み込み
+    /// 
+    /// タイルセットの画像は`key`で得たキーでアトラスへ追加されます。
+    /// `scale`はTiledの1ピクセルあたりのワールド座標での大きさです。
+    /// マップの左上の角がワールド座標の原点になります。
+    pub fn load<K, I>(
+        module: &mut AtlasRenderingModule<K, I>, 
+        path: impl AsRef<Path>, 
+        mut key: impl FnMut(&Path) -> K, 
+        scale: f32, 
+    ) -> Result<Self, error::LoadError<K, I>> where
+        K: Eq + Hash + Send + Sync + Sized + Clone + 'static, 
+        I: AtlasController<
+            4, 
+            u8, 
+            K, 
+            AtlasElemParam, 
+        > + Send + Sync, 
+    {
+        let path = path.as_ref();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut raw: RawMap = read_json(path)?;
+        raw.validate()?;
+
+        // タイルセットの読み込み
+        let mut tilesets = Vec::new();
+        for ts_ref in std::mem::take(&mut raw.tilesets) {
+            let (ts, ts_dir) = match &ts_ref.source {
+                Some(source) => {
+                    let ts_path = dir.join(source);
+                    let ts_dir = ts_path.parent()
+                        .map(Path::to_path_buf)
+                        .unwrap_or_default();
+                    (read_json::<RawTileset, K, I>(&ts_path)?, ts_dir)
+                }, 
+                None => (ts_ref.inline, dir.clone()), 
+            };
+            let Some(image) = &ts.image else {
+                return Err(error::LoadError::Unsupported("image collection tileset"))
+            };
+
+            let image_path: PathBuf = ts_dir.join(image);
+            let image = image::open(&image_path)
+                .map_err(error::LoadError::ImageError)?
+                .to_rgba8();
+            let mut tileset = Tileset::new(ts_ref.firstgid, &ts, image.width())?;
+            tileset.atlas_id = module.insert_image(&key(&image_path), image)
+                .map_err(error::LoadError::InsertImageError)?;
+            tilesets.push(tileset);
+        }
+        tilesets.sort_by_key(|ts| ts.firstgid);
+
+        Self::from_raw(raw, &tilesets, scale)
+    }
+
+    /// 読み込んだJSONとタイルセットからの生成
+    fn from_raw<K, I>(
+        raw: RawMap, 
+        tilesets: &[Tileset], 
+        scale: f32, 
+    ) -> Result<Self, error::LoadError<K, I>> where
+        K: Eq + Hash + Send + Sync + Sized + 'static, 
+        I: AtlasController<
+            4, 
+            u8, 
+            K, 
+            AtlasElemParam, 
+        >, 
+    {
+        let tile_count = raw.validate()?;
+        let conv = Conv {
+            origin: nalgebra::Point2::origin(), 
+            scale, 
+        };
+        let mut map = TiledMap {
+            size: [raw.width, raw.height], 
+            tile_size: [raw.tilewidth as f32 * scale, raw.tileheight as f32 * scale].into(), 
+            tile_layers: Vec::new(), 
+            object_layers: Vec::new(), 
+            properties: TiledProperty::collect(raw.properties), 
+        };
+
+        // グループを展開したレイヤの一覧
+        let mut layers = Vec::new();
+        let mut stack: Vec<RawLayer> = raw.layers.into_iter().rev().collect();
+        while let Some(layer) = stack.pop() {
+            match layer {
+                RawLayer::Group { layers: children } => stack.extend(children.into_iter().rev()), 
+                RawLayer::Other => {}, 
+                layer => layers.push(layer), 
+            }
+        }
+
+        for layer in layers {
+            match layer {
+                RawLayer::Tile {
+                    name, 
+                    data, 
+                    visible, 
+                    properties, 
+                } => {
+                    let RawData::Array(data) = data else {
+                        return Err(error::LoadError::Unsupported("encoded tile data"))
+                    };
+                    let mut tiles = vec![None; tile_count];
+                    for (tile, gid) in tiles.iter_mut().zip(data) {
+                        *tile = resolve_gid(tilesets, gid)?;
+                    }
+                    map.tile_layers.push(TiledTileLayer {
+                        name, 
+                        layer: map.tile_layers.len(), 
+                        visible, 
+                        tiles, 
+                        properties: TiledProperty::collect(properties), 
+                    });
+                }, 
+                RawLayer::Object {
+                    name, 
+                    objects, 
+                    visible, 
+                    properties, 
+                } => map.object_layers.push(TiledObjectLayer {
+                    name, 
+                    visible, 
+                    objects: objects.into_iter()
+                        .map(|o| convert_object(o, tilesets, &conv))
+                        .collect::<Result<_, _>>()?, 
+                    properties: TiledProperty::collect(properties), 
+                }), 
+                RawLayer::Group { .. } | RawLayer::Other => {}, 
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+impl RawMap {
+    /// 対応していない・不正なマップの確認
+    /// 
+    /// マップのタイル数を返します。
+    fn validate<K, I>(&self) -> Result<usize, error::LoadError<K, I>> where
+        K: Eq + Hash + Send + Sync + Sized + 'static, 
+        I: AtlasController<
+            4, 
+            u8, 
+            K, 
+            AtlasElemParam, 
+        >, 
+    {
+        if self.infinite {
+            return Err(error::LoadError::Unsupported("infinite map"))
+        }
+        if self.width == 0 || self.height == 0 {
+            return Err(invalid("map size must not be zero"))
+        }
+        (self.width as usize).checked_mul(self.height as usize)
+            .ok_or_else(|| invalid("map size is too large"))
+    }
+}
+
+impl<K, I> Tilemap<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+{
+    /// 読み込んだTiledのマップからの生成
+    /// 
+    /// `module`は`TiledMap::load`でタイルセットを追加したものを渡します。
+    /// タイルレイヤは奥から順に`z`が0, 1, 2...のレイヤになります。
+    pub fn from_tiled(
+        module: AtlasRenderingModule<K, I>, 
+        map: &TiledMap, 
+        chunk_size: u32, 
+    ) -> Result<Self, error::TilemapError> {
+        let mut tilemap = Self::new(
+            module, 
+            map.size, 
+            map.tile_size, 
+            chunk_size, 
+        )?;
+        for layer in &map.tile_layers {
+            let index = tilemap.add_layer(tilemap.layer_count() as f32);
+            if let Some(l) = tilemap.layer_mut(index) { l.visible = layer.visible }
+            for (i, tile) in layer.tiles.iter().enumerate() {
+                let width = map.size[0] as usize;
+                let pos = [(i % width) as u32, (i / width) as u32];
+                tilemap.set_tile(index, pos, *tile);
+            }
+        }
+        Ok(tilemap)
+    }
+}
+
+/// オブジェクトの変換
+fn convert_object<K, I>(
+    raw: RawObject, 
+    tilesets: &[Tileset], 
+    conv: &Conv, 
+) -> Result<TiledObject, error::LoadError<K, I>> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    >, 
+{
+    // Tiledの回転は左上(タイルの場合は左下)を中心とした時計回り
+    let (sin, cos) = raw.rotation.to_radians().sin_cos();
+    let rotate = |x: f32, y: f32| (x * cos - y * sin, x * sin + y * cos);
+    let to_world = |x: f32, y: f32| {
+        let (dx, dy) = rotate(x, y);
+        conv.point(raw.x + dx, raw.y + dy)
+    };
+
+    let tile = match raw.gid {
+        Some(gid) => resolve_gid(tilesets, gid)?, 
+        None => None, 
+    };
+    let (shape, center) = if let Some(tile) = tile {
+        (TiledShape::Tile(tile), to_world(raw.width * 0.5, -raw.height * 0.5))
+    } else if raw.point {
+        (TiledShape::Point, to_world(0., 0.))
+    } else if let Some(points) = raw.polygon {
+        (
+            TiledShape::Polygon(points.iter().map(|p| to_world(p.x, p.y)).collect()), 
+            to_world(0., 0.), 
+        )
+    } else if let Some(points) = raw.polyline {
+        (
+            TiledShape::Polyline(points.iter().map(|p| to_world(p.x, p.y)).collect()), 
+            to_world(0., 0.), 
+        )
+    } else if raw.ellipse {
+        (TiledShape::Ellipse, to_world(raw.width * 0.5, raw.height * 0.5))
+    } else {
+        (TiledShape::Rectangle, to_world(raw.width * 0.5, raw.height * 0.5))
+    };
+
+    Ok(TiledObject {
+        id: raw.id, 
+        name: raw.name, 
+        class: raw.class, 
+        shape, 
+        position: center, 
+        size: nalgebra::Vector2::new(raw.width, raw.height) * conv.scale, 
+        rotation: -raw.rotation.to_radians(), 
+        properties: TiledProperty::collect(raw.properties), 
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::atlas::inserter::bl::BLInserter;
+
+    type Error = error::LoadError<String, BLInserter>;
+
+    fn tileset(firstgid: u32, json: &str, image_width: u32) -> Tileset {
+        let raw: RawTileset = serde_json::from_str(json).unwrap();
+        Tileset::new::<String, BLInserter>(firstgid, &raw, image_width).ok().unwrap()
+    }
+
+    fn tilesets() -> Vec<Tileset> {
+        let mut first = tileset(1, r#"{"image": "a.png", "tilewidth": 16, "tileheight": 16}"#, 64);
+        first.atlas_id = 3;
+        let mut second = tileset(
+            17, 
+            r#"{"image": "b.png", "tilewidth": 16, "tileheight": 8, "margin": 1, "spacing": 2}"#, 
+            74, 
+        );
+        second.atlas_id = 5;
+        vec![first, second]
+    }
+
+    fn map(json: &str) -> RawMap { serde_json::from_str(json).unwrap() }
+
+    #[test]
+    fn resolve_gid_tilesets() {
+        let tilesets = tilesets();
+        assert_eq!(tilesets[0].columns, 4);
+        assert_eq!(tilesets[1].columns, 4);
+
+        assert_eq!(resolve_gid::<String, BLInserter>(&tilesets, 0).ok().unwrap(), None);
+        assert_eq!(
+            resolve_gid::<String, BLInserter>(&tilesets, 6).ok().unwrap(), 
+            Some(Tile::new(3, [16., 16.], [16., 16.])), 
+        );
+
+        // 2つ目のタイルセットは余白と間隔を含めて数える
+        assert_eq!(
+            resolve_gid::<String, BLInserter>(&tilesets, 17 + 5).ok().unwrap(), 
+            Some(Tile::new(5, [19., 11.], [16., 8.])), 
+        );
+    }
+
+    #[test]
+    fn resolve_gid_flip() {
+        let tilesets = tilesets();
+        let tile = |gid| resolve_gid::<String, BLInserter>(&tilesets, gid).ok().unwrap().unwrap();
+        assert_eq!(tile(1 | GID_FLIP_H).flip, [true, false]);
+        assert_eq!(tile(1 | GID_FLIP_V).flip, [false, true]);
+        assert_eq!(tile(1 | GID_FLIP_H | GID_FLIP_V).tex_coord, [0., 0.].into());
+        assert!(matches!(
+            resolve_gid::<String, BLInserter>(&tilesets, 1 | GID_FLIP_D), 
+            Err(Error::Unsupported(_)), 
+        ));
+    }
+
+    #[test]
+    fn convert_object_rotation_and_tile_origin() {
+        let tilesets = tilesets();
+        let conv = Conv {
+            origin: nalgebra::Point2::origin(), 
+            scale: 0.5, 
+        };
+        let object = |json: &str| convert_object::<String, BLInserter>(
+            serde_json::from_str(json).unwrap(), 
+            &tilesets, 
+            &conv, 
+        ).ok().unwrap();
+
+        // 左上を中心に時計回りに90度回転
+        let rect = object(r#"{"id": 1, "x": 10, "y": 20, "width": 4, "height": 2, "rotation": 90}"#);
+        assert_eq!(rect.shape, TiledShape::Rectangle);
+        assert!((rect.position - nalgebra::Point2::new(4.5, -11.)).norm() < 1e-4);
+        assert!((rect.rotation + std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+        assert_eq!(rect.size, nalgebra::Vector2::new(2., 1.));
+
+        // タイルのオブジェクトは左下が基準
+        let tile = object(r#"{"id": 2, "x": 0, "y": 32, "width": 16, "height": 16, "gid": 1}"#);
+        assert!(matches!(tile.shape, TiledShape::Tile(_)));
+        assert!((tile.position - nalgebra::Point2::new(4., -12.)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn from_raw_layers() {
+        let tilesets = tilesets();
+        let raw = map(r#"{
+            "width": 2, "height": 2, "tilewidth": 16, "tileheight": 16, 
+            "layers": [
+                {"type": "tilelayer", "name": "ground", "data": [1, 0, 2, 18]}, 
+                {"type": "group", "layers": [
+                    {"type": "objectgroup", "name": "spawn", "objects": [
+                        {"id": 1, "x": 0, "y": 0, "point": true}
+                    ]}, 
+                    {"type": "tilelayer", "name": "deco", "visible": false, "data": [0, 0, 0, 3]}
+                ]}, 
+                {"type": "imagelayer"}
+            ]
+        }"#);
+        let map = TiledMap::from_raw::<String, BLInserter>(raw, &tilesets, 1.).ok().unwrap();
+        assert_eq!(map.size, [2, 2]);
+        assert_eq!(map.tile_size, nalgebra::Vector2::new(16., 16.));
+
+        let names: Vec<_> = map.tile_layers.iter().map(|l| (l.name.as_str(), l.layer)).collect();
+        assert_eq!(names, [("ground", 0), ("deco", 1)]);
+        assert!(!map.tile_layers[1].visible);
+        assert_eq!(map.tile_layers[0].tiles[1], None);
+        assert_eq!(map.tile_layers[0].tiles[3].unwrap().atlas_id, 5);
+        assert_eq!(map.object_layers[0].objects[0].shape, TiledShape::Point);
+    }
+
+    #[test]
+    fn invalid_maps() {
+        let zero = map(r#"{"width": 0, "height": 4, "tilewidth": 16, "tileheight": 16, "layers": []}"#);
+        assert!(matches!(zero.validate::<String, BLInserter>(), Err(Error::ParseError(_))));
+
+        let infinite = map(r#"{
+            "width": 4, "height": 4, "tilewidth": 16, "tileheight": 16, "infinite": true, "layers": []
+        }"#);
+        assert!(matches!(infinite.validate::<String, BLInserter>(), Err(Error::Unsupported(_))));
+
+        // 余白が画像の幅を超える場合
+        let raw: RawTileset = serde_json::from_str(
+            r#"{"image": "a.png", "tilewidth": 16, "tileheight": 16, "margin": 20}"#
+        ).unwrap();
+        assert!(matches!(
+            Tileset::new::<String, BLInserter>(1, &raw, 32), 
+            Err(Error::ParseError(_)), 
+        ));
+        let raw: RawTileset = serde_json::from_str(
+            r#"{"image": "a.png", "tilewidth": 0, "tileheight": 16}"#
+        ).unwrap();
+        assert!(matches!(
+            Tileset::new::<String, BLInserter>(1, &raw, 32), 
+            Err(Error::ParseError(_)), 
+        ));
+    }
+}
@@ -0,0 +1,426 @@
+//! アトラスを用いたタイルマップ
+//! 
+//! タイルはチャンク単位でGPU上のバッファにまとめられ、
+//! 編集されたチャンクのみが作り直されます。
+//! 描画時はカメラから見えるチャンクのみを描画します。
+//! 
+//! タイル座標は左上を原点とし、xは右向き、yは下向きです。
+
+use super::*;
+use super::super::super::{
+    instance::batch::StaticBatch, 
+    types::VisibleField, 
+};
+
+/// タイル
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    /// アトラス内の画像の番号
+    pub atlas_id: usize, 
+
+    /// 画像内での位置(ピクセル)
+    pub tex_coord: nalgebra::Point2<f32>, 
+
+    /// 画像内での大きさ(ピクセル)
+    pub tex_size: nalgebra::Vector2<f32>, 
+
+    /// 水平・垂直方向の反転
+    pub flip: [bool; 2], 
+}
+impl Tile {
+    pub fn new(
+        atlas_id: usize, 
+        tex_coord: impl Into<nalgebra::Point2<f32>>, 
+        tex_size: impl Into<nalgebra::Vector2<f32>>, 
+    ) -> Self { Self {
+        atlas_id, 
+        tex_coord: tex_coord.into(), 
+        tex_size: tex_size.into(), 
+        flip: [false, false], 
+    }}
+
+    /// 反転の設定
+    pub fn flipped(mut self, flip: [bool; 2]) -> Self {
+        self.flip = flip;
+        self
+    }
+}
+
+/// チャンク
+struct TileChunk<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+{
+    batch: StaticBatch<AtlasRenderingModule<K, I>, instance::AtlasObjInstance>, 
+    modified: bool, 
+}
+
+/// タイルのレイヤ
+pub struct TileLayer<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+{
+    /// 奥行き(大きいほど手前)
+    pub z: f32, 
+
+    /// 表示の有無
+    pub visible: bool, 
+
+    tiles: Vec<Option<Tile>>, 
+    chunks: Vec<TileChunk<K, I>>, 
+}
+impl<K, I> TileLayer<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+{
+    fn new(z: f32, tile_count: usize, chunk_count: usize) -> Self { Self {
+        z, 
+        visible: true, 
+        tiles: vec![None; tile_count], 
+        chunks: (0..chunk_count)
+            .map(|_| TileChunk {
+                batch: StaticBatch::new(), 
+                modified: true, 
+            })
+            .collect(), 
+    }}
+}
+
+/// タイルマップ
+pub struct Tilemap<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+{
+    pub module: AtlasRenderingModule<K, I>, 
+
+    /// マップの左上の角のワールド座標
+    /// 
+    /// 変更した場合は`invalidate`を呼び出してください。
+    pub origin: nalgebra::Point2<f32>, 
+
+    /// 合成方法
+    pub blend: BlendMode, 
+
+    size: [u32; 2], 
+    tile_size: nalgebra::Vector2<f32>, 
+    chunk_size: u32, 
+    chunk_count: [u32; 2], 
+    tile_count: usize, 
+    layers: Vec<TileLayer<K, I>>, 
+}
+impl<K, I> Tilemap<K, I> where
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+{
+    /// タイルマップの生成
+    /// 
+    /// `size`はタイル数、`tile_size`はタイル1枚のワールド座標での大きさ、
+    /// `chunk_size`はチャンク1辺のタイル数です。
+    /// タイル数が`usize`で表せない場合は失敗します。
+    pub fn new(
+        module: AtlasRenderingModule<K, I>, 
+        size: [u32; 2], 
+        tile_size: impl Into<nalgebra::Vector2<f32>>, 
+        chunk_size: u32, 
+    ) -> Result<Self, error::TilemapError> {
+        let chunk_size = chunk_size.max(1);
+        let tile_count = (size[0] as usize).checked_mul(size[1] as usize)
+            .ok_or(error::TilemapError::TooLarge)?;
+        Ok(Self {
+            module, 
+            origin: nalgebra::Point2::origin(), 
+            blend: BlendMode::default(), 
+            size, 
+            tile_size: tile_size.into(), 
+            chunk_size, 
+            chunk_count: [
+                size[0].div_ceil(chunk_size), 
+                size[1].div_ceil(chunk_size), 
+            ], 
+            tile_count, 
+            layers: Vec::new(), 
+        })
+    }
+
+    /// マップの大きさ(タイル数)
+    pub fn size(&self) -> [u32; 2] { self.size }
+
+    /// タイル1枚の大きさ(ワールド座標)
+    pub fn tile_size(&self) -> nalgebra::Vector2<f32> { self.tile_size }
+
+    /// レイヤの追加
+    /// 
+    /// レイヤは追加順に描画されます。レイヤの番号を返します。
+    pub fn add_layer(&mut self, z: f32) -> usize {
+        // チャンク数は各辺でタイル数以下のため、タイル数と同様に収まる
+        let chunk_count = self.chunk_count[0] as usize * self.chunk_count[1] as usize;
+        self.layers.push(TileLayer::new(z, self.tile_count, chunk_count));
+        self.layers.len() - 1
+    }
+
+    pub fn layer_count(&self) -> usize { self.layers.len() }
+
+    pub fn layer(&self, layer: usize) -> Option<&TileLayer<K, I>> {
+        self.layers.get(layer)
+    }
+
+    /// レイヤの取得
+    /// 
+    /// `z`を変更した場合は`invalidate`を呼び出してください。
+    pub fn layer_mut(&mut self, layer: usize) -> Option<&mut TileLayer<K, I>> {
+        self.layers.get_mut(layer)
+    }
+
+    fn tile_index(&self, pos: [u32; 2]) -> Option<usize> {
+        (pos[0] < self.size[0] && pos[1] < self.size[1])
+            .then(|| pos[1] as usize * self.size[0] as usize + pos[0] as usize)
+    }
+
+    fn chunk_index(&self, pos: [u32; 2]) -> usize {
+        (pos[1] / self.chunk_size) as usize * self.chunk_count[0] as usize
+            + (pos[0] / self.chunk_size) as usize
+    }
+
+    pub fn get_tile(&self, layer: usize, pos: [u32; 2]) -> Option<&Tile> {
+        let index = self.tile_index(pos)?;
+        self.layers.get(layer)?.tiles[index].as_ref()
+    }
+
+    /// タイルの設定
+    /// 
+    /// 範囲外の場合は何もせず`false`を返します。
+    pub fn set_tile(
+        &mut self, 
+        layer: usize, 
+        pos: [u32; 2], 
+        tile: Option<Tile>, 
+    ) -> bool {
+        let Some(index) = self.tile_index(pos) else { return false };
+        let chunk = self.chunk_index(pos);
+        let Some(layer) = self.layers.get_mut(layer) else { return false };
+        if layer.tiles[index] != tile {
+            layer.tiles[index] = tile;
+            layer.chunks[chunk].modified = true;
+        }
+        true
+    }
+
+    /// 全チャンクの作り直しの要求
+    /// 
+    /// アトラスへの画像の追加などでタイルの変換結果が変わった場合に呼び出します。
+    pub fn invalidate(&mut self) {
+        self.layers.iter_mut()
+            .flat_map(|l| l.chunks.iter_mut())
+            .for_each(|c| c.modified = true)
+    }
+
+    /// タイルの中心のワールド座標
+    pub fn tile_to_world(&self, pos: [u32; 2]) -> nalgebra::Point2<f32> {
+        self.origin + nalgebra::Vector2::new(
+            (pos[0] as f32 + 0.5) * self.tile_size.x, 
+            -(pos[1] as f32 + 0.5) * self.tile_size.y, 
+        )
+    }
+
+    /// ワールド座標を含むタイルの位置
+    pub fn world_to_tile(
+        &self, 
+        world: impl Into<nalgebra::Point2<f32>>, 
+    ) -> Option<[u32; 2]> {
+        let rel = world.into() - self.origin;
+        let x = (rel.x / self.tile_size.x).floor();
+        let y = (-rel.y / self.tile_size.y).floor();
+        (0. <= x && 0. <= y && x < self.size[0] as f32 && y < self.size[1] as f32)
+            .then_some([x as u32, y as u32])
+    }
+
+    /// チャンクの左上の角のタイルの位置
+    fn chunk_origin(&self, chunk: usize) -> [u32; 2] {
+        let count = self.chunk_count[0] as usize;
+        [
+            (chunk % count) as u32 * self.chunk_size, 
+            (chunk / count) as u32 * self.chunk_size, 
+        ]
+    }
+
+    /// チャンクの中心と半分の大きさ(ワールド座標)
+    fn chunk_bounds(
+        &self, 
+        chunk: usize, 
+    ) -> (nalgebra::Point2<f32>, nalgebra::Vector2<f32>) {
+        let [cx, cy] = self.chunk_origin(chunk);
+        let w = (self.size[0] - cx).min(self.chunk_size) as f32;
+        let h = (self.size[1] - cy).min(self.chunk_size) as f32;
+        let half = nalgebra::Vector2::new(
+            w * self.tile_size.x * 0.5, 
+            h * self.tile_size.y * 0.5, 
+        );
+        let corner = self.origin + nalgebra::Vector2::new(
+            cx as f32 * self.tile_size.x, 
+            -(cy as f32) * self.tile_size.y, 
+        );
+        (corner + nalgebra::Vector2::new(half.x, -half.y), half)
+    }
+
+    /// 編集されたチャンクの作り直しと転送
+    fn rebuild(&mut self, gfx: &crate::ctx::gfx::WGPUCtx) {
+        for l in 0..self.layers.len() {
+            for c in 0..self.layers[l].chunks.len() {
+                if !self.layers[l].chunks[c].modified { continue }
+
+                let [cx, cy] = self.chunk_origin(c);
+                let instances = (cy..cy.saturating_add(self.chunk_size).min(self.size[1]))
+                    .flat_map(|y| (cx..cx.saturating_add(self.chunk_size).min(self.size[0]))
+                        .map(move |x| [x, y])
+                    )
+                    .filter_map(|pos| {
+                        let tile = self.layers[l].tiles[self.tile_index(pos)?]?;
+                        Some(instance::AtlasObjInstance {
+                            atlas_id: tile.atlas_id, 
+                            position: self.tile_to_world(pos), 
+                            size: self.tile_size, 
+                            tex_rev: tile.flip, 
+                            tex_coord: tile.tex_coord, 
+                            tex_size: tile.tex_size, 
+                            z: self.layers[l].z, 
+                            ..Default::default()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                let chunk = &mut self.layers[l].chunks[c];
+                chunk.batch.clear();
+                instances.into_iter().for_each(|i| { chunk.batch.push(i); });
+                chunk.modified = false;
+            }
+        }
+
+        let module = &self.module;
+        self.layers.iter_mut()
+            .flat_map(|l| l.chunks.iter_mut())
+            .for_each(|c| c.batch.upload(gfx, module));
+    }
+}
+impl<
+    K: Eq + Hash + Send + Sync + Sized + 'static, 
+    I: AtlasController<
+        4, 
+        u8, 
+        K, 
+        AtlasElemParam, 
+    > + Send + Sync, 
+    GCd: Send + Sync, 
+> super::super::super::Simple2DRender<GCd> for Tilemap<K, I> {
+    type Shared<'a> = (
+        &'a super::super::super::SquareShared, 
+        &'a super::super::super::ImagedShared, 
+        &'a shared::AtlasObjRenderShared, 
+    );
+
+    fn rendering<'a>(
+        &mut self, 
+        gfx: &crate::ctx::gfx::GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        camera: &crate::prelude::simple2d::shared::S2DCamera, 
+        shared: Self::Shared<'a>, 
+    ) {
+        self.rendering_multi(gfx, encoder, view, &[camera], shared)
+    }
+
+    fn rendering_multi<'a>(
+        &mut self, 
+        gfx: &crate::ctx::gfx::GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        cameras: &[&crate::prelude::simple2d::shared::S2DCamera], 
+        shared: Self::Shared<'a>, 
+    ) where
+        Self::Shared<'a>: Clone, 
+    {
+        self.rebuild(&gfx.wgpu_ctx);
+
+        // カメラごとに描画領域を切り替えて描画
+        for camera in cameras {
+            let field = VisibleField::bounding(&camera.camera);
+
+            let mut render_pass = encoder.begin_render_pass(
+                &wgpu::RenderPassDescriptor {
+                    label: Some("tilemap render pass"), 
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view, 
+                        resolve_target: None, 
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load, 
+                            store: true
+                        }
+                    })], 
+                    depth_stencil_attachment: camera.depth_attachment(), 
+                }
+            );
+            camera.apply_viewport(&mut render_pass);
+
+            render_pass.set_pipeline(
+                shared.2.pipeline(
+                    &gfx.wgpu_ctx.device, 
+                    self.blend, 
+                    camera.depth.is_some(), 
+                )
+            );
+            render_pass.set_bind_group(
+                0, 
+                &self.module.texture.bind_group, 
+                &[]
+            );
+            render_pass.set_bind_group(1, &camera.bg, &[]);
+            render_pass.set_vertex_buffer(
+                0, 
+                shared.0.vertex.slice(..)
+            );
+            render_pass.set_index_buffer(
+                shared.0.index.slice(..), wgpu::IndexFormat::Uint16
+            );
+
+            // 見えるチャンクのみ描画
+            for layer in self.layers.iter().filter(|l| l.visible) {
+                for (c, chunk) in layer.chunks.iter().enumerate() {
+                    let Some(buffer) = chunk.batch.buffer() else { continue };
+                    let (center, half) = self.chunk_bounds(c);
+                    if !field.in_visible(center, half) { continue }
+
+                    render_pass.set_vertex_buffer(1, buffer.slice(..));
+                    render_pass.draw_indexed(
+                        0..super::super::super::raw::INDICES.len() as _, 
+                        0, 
+                        0..chunk.batch.uploaded_len() as u32
+                    );
+                }
+            }
+        }
+    }
+}
@@ -207,6 +207,7 @@ impl super::TypeRenderer {
                         ],
                         tex_rev: [false, false],
                         char_color: param.color,
+                        z: param.z,
                     });
 
                     hm
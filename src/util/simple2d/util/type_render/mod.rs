@@ -105,6 +105,7 @@ impl TypeRenderer {
                     ],
                     tex_rev: [false, false],
                     char_color: color,
+                    z: 0.,
                 })
             },
             None => {},
@@ -214,6 +215,7 @@ impl TypeRenderer {
                         ],
                         tex_rev: [false, false],
                         char_color: color,
+                        z: 0.,
                     });
 
                     hm
@@ -244,4 +246,12 @@ impl TypeRenderer {
         image.write_to(&mut fp, image::ImageFormat::Png)?;
         Ok(())
     }
+
+    /// 合成方法
+    pub fn blend_mode(&self) -> super::super::BlendMode { self.rdr.blend_mode() }
+
+    /// 合成方法の設定
+    pub fn set_blend_mode(&mut self, mode: super::super::BlendMode) {
+        self.rdr.set_blend_mode(mode)
+    }
 }
\ No newline at end of file
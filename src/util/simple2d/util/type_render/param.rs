@@ -29,4 +29,7 @@ pub struct TypeParam {
     pub align: TypeAlign, 
     pub area: Option<nalgebra::Vector2<f32>>, 
     pub enable_autoreturn: bool, 
+
+    /// 奥行き(大きいほど手前)
+    pub z: f32, 
 }
\ No newline at end of file
@@ -11,6 +11,10 @@ use super::super::super::{
         ImagedShared, 
         SquareShared, 
     }, 
+    blend::{
+        BlendMode, 
+        PipelineCache, 
+    }, 
     types::Texture, 
     raw::{
         TexedVertex, 
@@ -27,7 +31,6 @@ use wgpu::{
     BufferAddress, 
     RenderPipeline, 
     RenderPipelineDescriptor, 
-    Buffer, 
 };
 use std::mem::size_of;
 
@@ -41,15 +44,17 @@ pub struct TextInstanceRaw {
     pub tex_coord: [f32; 2], 
     pub tex_size: [f32; 2], 
     pub char_color: [f32; 4], 
+    pub z: f32, 
 }
 impl TextInstanceRaw {
-    const ATTRIBS: [VertexAttribute; 6] = vertex_attr_array![
+    const ATTRIBS: [VertexAttribute; 7] = vertex_attr_array![
         5 => Float32x2, 
         6 => Float32x2, 
         7 => Float32x2, 
         8 => Float32x2, 
         9 => Float32x2, 
         10 => Float32x4, 
+        11 => Float32, 
     ];
 }
 impl InstanceRaw for TextInstanceRaw {
@@ -72,6 +77,9 @@ pub struct TextInstance {
     pub tex_size: [f32; 2], 
     pub tex_rev: [bool; 2], 
     pub char_color: [f32; 4], 
+
+    /// 奥行き(大きいほど手前)
+    pub z: f32, 
 }
 impl Instance<Texture> for TextInstance {
     type Raw = TextInstanceRaw;
@@ -101,8 +109,11 @@ impl Instance<Texture> for TextInstance {
             tex_coord: self.tex_coord, 
             tex_size: self.tex_size, 
             char_color: self.char_color, 
+            z: self.z, 
         }
     }
+
+    fn z(&self) -> f32 { self.z }
     
 }
 impl InstanceGen<Texture, TextInstance> for TextInstance {
@@ -116,9 +127,19 @@ impl InstanceGen<Texture, TextInstance> for TextInstance {
 
 /// 画像を使ったオブジェクトの描画構造体で共有される値
 pub struct TextRenderShared {
-    pipeline: RenderPipeline, 
+    pipelines: PipelineCache, 
 }
 impl TextRenderShared {
+    /// 合成方法と深度バッファの有無に応じたパイプライン
+    pub(crate) fn pipeline(
+        &self, 
+        device: &wgpu::Device, 
+        mode: BlendMode, 
+        depth: bool, 
+    ) -> &RenderPipeline {
+        self.pipelines.get(device, mode, depth)
+    }
+
     pub fn new(
         gfx: &gfx::WGPUCtx, 
         camera: &S2DCamera, 
@@ -147,7 +168,12 @@ impl TextRenderShared {
         );
 
         // パイプラインの初期化
-        let pipeline = gfx.device.create_render_pipeline(
+        let format = gfx.config.format;
+        let pipelines = PipelineCache::new(&gfx.device, move |
+            device: &wgpu::Device, 
+            blend: BlendMode, 
+            depth_stencil: Option<wgpu::DepthStencilState>, 
+        | device.create_render_pipeline(
             &RenderPipelineDescriptor {
                 label: Some("sample pipeline"), 
                 layout: Some(&pipeline_layout), 
@@ -161,10 +187,10 @@ impl TextRenderShared {
                 }, 
                 fragment: Some(wgpu::FragmentState {
                     module: &shader, 
-                    entry_point: "fs_main", 
+                    entry_point: blend.fragment_entry(), 
                     targets: &[Some(wgpu::ColorTargetState { 
-                        format: gfx.config.format, 
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING), 
+                        format, 
+                        blend: Some(blend.blend_state()), 
                         write_mask: wgpu::ColorWrites::all() 
                     })]
                 }), 
@@ -177,7 +203,7 @@ impl TextRenderShared {
                     polygon_mode: wgpu::PolygonMode::Fill, 
                     conservative: false, 
                 }, 
-                depth_stencil: None, 
+                depth_stencil, 
                 multisample: wgpu::MultisampleState {
                     count: 1, 
                     mask: !0, 
@@ -185,10 +211,10 @@ impl TextRenderShared {
                 }, 
                 multiview: None, 
             }
-        );
+        ));
 
         Self {
-            pipeline, 
+            pipelines, 
         }
     }
 }
@@ -197,7 +223,7 @@ impl TextRenderShared {
 pub struct TextRender {
     texture: Texture, 
     instances: InstanceArray<Texture, TextInstance>, 
-    instance_buffer: Buffer, 
+    blend: BlendMode, 
 }
 impl TextRender {
     pub fn new<C: std::ops::Deref<Target = [u8]>>(
@@ -216,12 +242,12 @@ impl TextRender {
         let mut instances = InstanceArray::new();
 
         // インスタンスバッファの初期化
-        let instance_buffer = instances.finish(gfx, &texture);
+        instances.finish(gfx, &texture);
 
         Self {
             texture, 
             instances, 
-            instance_buffer, 
+            blend: BlendMode::default(), 
         }
     }
 
@@ -235,6 +261,12 @@ impl TextRender {
 
     /// テクスチャの参照の取得
     pub fn texture_get(&mut self) -> &mut Texture { &mut self.texture }
+
+    /// 合成方法
+    pub fn blend_mode(&self) -> BlendMode { self.blend }
+
+    /// 合成方法の設定
+    pub fn set_blend_mode(&mut self, mode: BlendMode) { self.blend = mode }
 }
 impl<GCd: Send + Sync> Simple2DRender<GCd> for TextRender {
     type Shared<'a> = (
@@ -251,41 +283,65 @@ impl<GCd: Send + Sync> Simple2DRender<GCd> for TextRender {
         camera: &S2DCamera, 
         shared: Self::Shared<'a>, 
     ) {
-        self.instance_buffer = self.instances.finish(
+        self.rendering_multi(gfx, encoder, view, &[camera], shared)
+    }
+
+    fn rendering_multi<'a>(
+        &mut self, 
+        gfx: &gfx::GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        cameras: &[&S2DCamera], 
+        shared: Self::Shared<'a>, 
+    ) where
+        Self::Shared<'a>: Clone, 
+    {
+        self.instances.finish(
             &gfx.wgpu_ctx, 
             &self.texture
         );
+        let Some(instance_buffer) = self.instances.buffer() else { return };
 
-        let mut render_pass = encoder.begin_render_pass(
-            &wgpu::RenderPassDescriptor {
-                label: Some("render pass"), 
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
-                    view, 
-                    resolve_target: None, 
-                    ops: wgpu::Operations { 
-                        load: wgpu::LoadOp::Load, 
-                        store: true 
-                    } 
-                })], 
-                depth_stencil_attachment: None, 
-            }
-        );
+        // カメラごとに描画領域を切り替えて描画
+        for camera in cameras {
+            let mut render_pass = encoder.begin_render_pass(
+                &wgpu::RenderPassDescriptor {
+                    label: Some("render pass"), 
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
+                        view, 
+                        resolve_target: None, 
+                        ops: wgpu::Operations { 
+                            load: wgpu::LoadOp::Load, 
+                            store: true 
+                        } 
+                    })], 
+                    depth_stencil_attachment: camera.depth_attachment(), 
+                }
+            );
+            camera.apply_viewport(&mut render_pass);
 
-        render_pass.set_pipeline(&shared.2.pipeline);
-        render_pass.set_bind_group(0, &self.texture.bind_group, &[]);
-        render_pass.set_bind_group(1, &camera.bg, &[]);
-        render_pass.set_vertex_buffer(
-            0, shared.0.vertex.slice(..)
-        );
-        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        render_pass.set_index_buffer(
-            shared.0.index.slice(..), wgpu::IndexFormat::Uint16
-        );
-        render_pass.draw_indexed(
-            0..INDICES.len() as _, 
-            0, 
-            0..self.instances.len() as _
-        );
+            render_pass.set_pipeline(
+                shared.2.pipeline(
+                    &gfx.wgpu_ctx.device, 
+                    self.blend, 
+                    camera.depth.is_some(), 
+                )
+            );
+            render_pass.set_bind_group(0, &self.texture.bind_group, &[]);
+            render_pass.set_bind_group(1, &camera.bg, &[]);
+            render_pass.set_vertex_buffer(
+                0, shared.0.vertex.slice(..)
+            );
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.set_index_buffer(
+                shared.0.index.slice(..), wgpu::IndexFormat::Uint16
+            );
+            render_pass.draw_indexed(
+                0..INDICES.len() as _, 
+                0, 
+                0..self.instances.len() as _
+            );
+        }
     }
     
 }
\ No newline at end of file
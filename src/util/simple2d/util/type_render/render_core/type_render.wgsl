@@ -22,6 +22,7 @@ struct InstanceInput {
     @location(8) tex_coord: vec2<f32>, 
     @location(9) tex_size: vec2<f32>, 
     @location(10) char_color: vec4<f32>, 
+    @location(11) z: f32, 
 }
 
 struct VertexOutput {
@@ -64,7 +65,13 @@ fn vs_main(
         1., 
     );
     pos_temp *= vec4<f32>(camera.size.x, camera.size.y, 0., 1.);
-    out.clip_position = pos_temp;
+
+    // 奥行きを深度へ変換(zは-1024から1024、大きいほど手前)
+    out.clip_position = vec4<f32>(
+        pos_temp.xy, 
+        clamp(0.5 - instance.z * (0.5 / 1024.), 0., 1.), 
+        1., 
+    );
     out.char_color = instance.char_color;
     return out;
 }
@@ -84,5 +91,21 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         in.char_color.xyz, 
         in.char_color.w * sample.w
     );
+    // 完全に透明な画素は深度を書き込まない
+    if char_color.w <= 0. {
+        discard;
+    }
     return char_color;
-}
\ No newline at end of file
+}
+
+// 乗算済みアルファの合成用
+@fragment
+fn fs_premultiplied(in: VertexOutput) -> @location(0) vec4<f32> {
+    var sample = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    var alpha = in.char_color.w * sample.w;
+    // 完全に透明な画素は深度を書き込まない
+    if alpha <= 0. {
+        discard;
+    }
+    return vec4<f32>(in.char_color.xyz * alpha, alpha);
+}
@@ -25,4 +25,18 @@ impl<GCd: Send + Sync> Simple2DRender<GCd> for super::TypeRenderer {
         self.atlas.write_texture(self.rdr.texture_get(), gfx, shared.1);
         self.rdr.rendering(gfx, encoder, view, camera, shared)
     }
+
+    fn rendering_multi<'a>(
+        &mut self, 
+        gfx: &GfxCtx<GCd>, 
+        encoder: &mut wgpu::CommandEncoder, 
+        view: &wgpu::TextureView, 
+        cameras: &[&S2DCamera], 
+        shared: Self::Shared<'a>, 
+    ) where
+        Self::Shared<'a>: Clone, 
+    {
+        self.atlas.write_texture(self.rdr.texture_get(), gfx, shared.1);
+        self.rdr.rendering_multi(gfx, encoder, view, cameras, shared)
+    }
 }
\ No newline at end of file